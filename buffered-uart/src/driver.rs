@@ -0,0 +1,310 @@
+//! Interrupt-driven UART: the interrupt handler moves bytes between the hardware FIFOs and the
+//! rings, tasks read and write the rings through `embedded-io-async`
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::Poll;
+
+use critical_section::Mutex;
+use embassy_sync::waitqueue::AtomicWaker;
+use esp_hal::Blocking;
+use esp_hal::interrupt::InterruptHandler;
+use esp_hal::uart::{Uart, UartInterrupt};
+
+use crate::ring::{Consumer, Producer, Ring};
+
+/// Hardware RX FIFO size of the ESP32 UARTs, bytes
+const FIFO_SIZE: usize = 128;
+
+/// Parts owned by the interrupt handler
+struct Irq<const RX: usize, const TX: usize> {
+    uart: Uart<'static, Blocking>,
+    rx: Producer<'static, RX>,
+    tx: Consumer<'static, TX>,
+    /// TX FIFO holds bytes, `TxDone` is listened to
+    tx_busy: bool,
+}
+
+impl<const RX: usize, const TX: usize> Irq<RX, TX> {
+    /// Move the received bytes to the RX ring. Returns the number of read errors
+    fn drain(&mut self) -> u32 {
+        let mut errors = 0;
+        let mut buffer = [0_u8; FIFO_SIZE];
+        loop {
+            match self.uart.read_buffered(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => {
+                    self.rx.push_lossy(buffer.get(..len).unwrap_or_default());
+                }
+                // Ошибка сбрасывается чтением, данные остаются в FIFO
+                Err(_) => errors += 1,
+            }
+            // Ошибки подряд - линия шумит, остаток заберёт следующее прерывание
+            if errors > 1 {
+                break;
+            }
+        }
+        errors
+    }
+
+    /// Move bytes of the TX ring to the TX FIFO while it has room. Returns the moved count
+    fn refill(&mut self) -> usize {
+        let mut sent = 0;
+        while self.uart.write_ready() {
+            let chunk = self.tx.peek();
+            if chunk.is_empty() {
+                break;
+            }
+            let len = self.uart.write(chunk).unwrap_or(0);
+            self.tx.consume(len);
+            sent += len;
+        }
+        sent
+    }
+}
+
+/// Static state of one buffered UART
+///
+/// * `RX` - Receive ring size, bytes, a power of two
+/// * `TX` - Transmit ring size, bytes, a power of two
+pub struct UartState<const RX: usize, const TX: usize> {
+    rx_ring: Ring<RX>,
+    tx_ring: Ring<TX>,
+    irq: Mutex<RefCell<Option<Irq<RX, TX>>>>,
+    rx_waker: AtomicWaker,
+    tx_waker: AtomicWaker,
+    /// Framing, parity and FIFO overflow errors, written by the interrupt handler only
+    rx_errors: AtomicU32,
+}
+
+impl<const RX: usize, const TX: usize> Default for UartState<RX, TX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const RX: usize, const TX: usize> UartState<RX, TX> {
+    /// Create the state, to be placed in a `static`
+    pub const fn new() -> Self {
+        Self {
+            rx_ring: Ring::new(),
+            tx_ring: Ring::new(),
+            irq: Mutex::new(RefCell::new(None)),
+            rx_waker: AtomicWaker::new(),
+            tx_waker: AtomicWaker::new(),
+            rx_errors: AtomicU32::new(0),
+        }
+    }
+
+    /// Take over the UART and split it into the reading and the writing half.
+    /// Returns `None` if the state is already in use
+    ///
+    /// * `uart` - Configured UART, RX FIFO threshold and idle timeout decide how often the
+    ///   interrupt fires
+    /// * `handler` - Interrupt handler that calls [`UartState::on_interrupt`]
+    pub fn init(
+        &'static self,
+        mut uart: Uart<'static, Blocking>,
+        handler: InterruptHandler,
+    ) -> Option<(BufferedRx<RX, TX>, BufferedTx<RX, TX>)> {
+        let (rx, rx_consumer) = self.rx_ring.try_split()?;
+        let (tx_producer, tx) = self.tx_ring.try_split()?;
+
+        uart.set_interrupt_handler(handler);
+        critical_section::with(|cs| {
+            uart.clear_interrupts(UartInterrupt::RxFifoFull | UartInterrupt::RxTimeout);
+            uart.listen(UartInterrupt::RxFifoFull | UartInterrupt::RxTimeout);
+            self.irq.borrow_ref_mut(cs).replace(Irq {
+                uart,
+                rx,
+                tx,
+                tx_busy: false,
+            });
+        });
+
+        Some((
+            BufferedRx {
+                state: self,
+                rx: rx_consumer,
+            },
+            BufferedTx {
+                state: self,
+                tx: tx_producer,
+            },
+        ))
+    }
+
+    /// Service the UART, call from the `#[handler]` passed to [`UartState::init`]
+    pub fn on_interrupt(&self) {
+        critical_section::with(|cs| {
+            let mut irq = self.irq.borrow_ref_mut(cs);
+            let Some(irq) = irq.as_mut() else {
+                return;
+            };
+            // Сначала сбрасываем флаги, чтобы не потерять байты, пришедшие во время чтения
+            let pending = irq.uart.interrupts();
+            irq.uart.clear_interrupts(pending);
+
+            let errors = irq.drain();
+            if errors > 0 {
+                // Счётчик меняет только прерывание - хватает load/store
+                let total = self.rx_errors.load(Ordering::Relaxed);
+                self.rx_errors
+                    .store(total.saturating_add(errors), Ordering::Relaxed);
+            }
+            if !self.rx_ring.is_empty() {
+                self.rx_waker.wake();
+            }
+
+            if pending.contains(UartInterrupt::TxDone) {
+                if irq.refill() == 0 {
+                    irq.uart.unlisten(UartInterrupt::TxDone);
+                    irq.tx_busy = false;
+                }
+                self.tx_waker.wake();
+            }
+        });
+    }
+
+    /// Start the transmission unless it is running
+    fn kick(&self) {
+        critical_section::with(|cs| {
+            let mut irq = self.irq.borrow_ref_mut(cs);
+            let Some(irq) = irq.as_mut() else {
+                return;
+            };
+            if irq.tx_busy {
+                return;
+            }
+            // Старый TxDone сработал бы сразу, до отправки новых байтов
+            irq.uart.clear_interrupts(UartInterrupt::TxDone.into());
+            if irq.refill() > 0 {
+                irq.tx_busy = true;
+                irq.uart.listen(UartInterrupt::TxDone);
+            }
+        });
+    }
+
+    /// Whether the TX ring is empty and the last byte left the FIFO
+    fn tx_idle(&self) -> bool {
+        critical_section::with(|cs| {
+            self.tx_ring.is_empty()
+                && self
+                    .irq
+                    .borrow_ref(cs)
+                    .as_ref()
+                    .is_none_or(|irq| !irq.tx_busy)
+        })
+    }
+}
+
+/// Reading half of a buffered UART
+pub struct BufferedRx<const RX: usize, const TX: usize> {
+    state: &'static UartState<RX, TX>,
+    rx: Consumer<'static, RX>,
+}
+
+impl<const RX: usize, const TX: usize> BufferedRx<RX, TX> {
+    /// Take the received bytes without waiting. Returns the number of bytes
+    ///
+    /// * `buffer` - Output buffer
+    pub fn try_read(&mut self, buffer: &mut [u8]) -> usize {
+        self.rx.pop_slice(buffer)
+    }
+
+    /// Received bytes dropped because the RX ring was full
+    pub fn overflows(&self) -> u32 {
+        self.state.rx_ring.overflows()
+    }
+
+    /// Framing, parity and hardware FIFO overflow errors
+    pub fn rx_errors(&self) -> u32 {
+        self.state.rx_errors.load(Ordering::Relaxed)
+    }
+}
+
+impl<const RX: usize, const TX: usize> embedded_io_async::ErrorType for BufferedRx<RX, TX> {
+    type Error = Infallible;
+}
+
+impl<const RX: usize, const TX: usize> embedded_io_async::Read for BufferedRx<RX, TX> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| {
+            let len = self.rx.pop_slice(buf);
+            if len > 0 {
+                return Poll::Ready(Ok(len));
+            }
+            self.state.rx_waker.register(cx.waker());
+            // Байты могли прийти до регистрации
+            match self.rx.pop_slice(buf) {
+                0 => Poll::Pending,
+                len => Poll::Ready(Ok(len)),
+            }
+        })
+        .await
+    }
+}
+
+/// Writing half of a buffered UART
+pub struct BufferedTx<const RX: usize, const TX: usize> {
+    state: &'static UartState<RX, TX>,
+    tx: Producer<'static, TX>,
+}
+
+impl<const RX: usize, const TX: usize> BufferedTx<RX, TX> {
+    /// Queue as many bytes as fit without waiting. Returns the number of queued bytes
+    ///
+    /// * `bytes` - Bytes to send
+    pub fn try_write(&mut self, bytes: &[u8]) -> usize {
+        let len = self.tx.push_slice(bytes);
+        if len > 0 {
+            self.state.kick();
+        }
+        len
+    }
+}
+
+impl<const RX: usize, const TX: usize> embedded_io_async::ErrorType for BufferedTx<RX, TX> {
+    type Error = Infallible;
+}
+
+impl<const RX: usize, const TX: usize> embedded_io_async::Write for BufferedTx<RX, TX> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        poll_fn(|cx| {
+            let len = self.try_write(buf);
+            if len > 0 {
+                return Poll::Ready(Ok(len));
+            }
+            self.state.tx_waker.register(cx.waker());
+            // Место могло освободиться до регистрации
+            match self.try_write(buf) {
+                0 => Poll::Pending,
+                len => Poll::Ready(Ok(len)),
+            }
+        })
+        .await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        poll_fn(|cx| {
+            if self.state.tx_idle() {
+                return Poll::Ready(Ok(()));
+            }
+            self.state.tx_waker.register(cx.waker());
+            if self.state.tx_idle() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,49 @@
+//! Interrupt-driven UART with ring buffers
+//!
+//! The interrupt handler moves received bytes from the hardware FIFO to the RX ring and refills
+//! the TX FIFO from the TX ring, so no byte is lost while the application is busy and a
+//! message arriving in several chunks is never overwritten by the next one. The application
+//! reads and writes the rings through `embedded_io_async::{Read, Write}` or without waiting.
+//!
+//! * [`Ring`] - lock-free single-producer single-consumer byte ring, only atomic loads and
+//!   stores, so it works on the esp32c3 which has no compare-and-swap
+//! * Bytes that do not fit the RX ring are dropped and counted (`BufferedRx::overflows`),
+//!   line errors are counted separately (`BufferedRx::rx_errors`)
+//!
+//! The driver is built with exactly one of the `esp32c3` or `esp32s3` features, without them
+//! only the ring is built and tested on the host (bare-metal targets require a chip feature):
+//!
+//! ```ignore
+//! static UART: UartState<256, 256> = UartState::new();
+//!
+//! #[handler]
+//! fn uart_handler() {
+//!     UART.on_interrupt();
+//! }
+//!
+//! let uart = Uart::new(peripherals.UART1, config)?.with_rx(rx_pin).with_tx(tx_pin);
+//! let (mut rx, mut tx) = UART.init(uart, uart_handler).unwrap();
+//!
+//! let mut buffer = [0; 64];
+//! loop {
+//!     let len = rx.read(&mut buffer).await?;
+//!     tx.write_all(&buffer[..len]).await?;
+//! }
+//! ```
+
+#![no_std]
+
+#[cfg(all(feature = "esp32c3", feature = "esp32s3"))]
+compile_error!("features `esp32c3` and `esp32s3` are mutually exclusive, enable only one");
+
+#[cfg(all(not(any(feature = "esp32c3", feature = "esp32s3")), target_os = "none"))]
+compile_error!("enable the chip feature: `esp32c3` or `esp32s3`");
+
+#[cfg(any(feature = "esp32c3", feature = "esp32s3"))]
+mod driver;
+mod ring;
+
+// Public re-export of specifics that are available outside of module
+#[cfg(any(feature = "esp32c3", feature = "esp32s3"))]
+pub use driver::{BufferedRx, BufferedTx, UartState};
+pub use ring::{Consumer, Producer, Ring};
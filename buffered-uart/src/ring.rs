@@ -0,0 +1,196 @@
+//! Lock-free single-producer single-consumer byte ring
+//!
+//! Only atomic loads and stores are used (no compare-and-swap), so the ring works on cores
+//! without atomic read-modify-write instructions (esp32c3). The producer alone moves `head`
+//! and counts overflows, the consumer alone moves `tail`.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+
+/// Byte ring of `N` bytes, `N` is a power of two
+pub struct Ring<const N: usize> {
+    buffer: UnsafeCell<[u8; N]>,
+    /// Written bytes, free-running
+    head: AtomicUsize,
+    /// Read bytes, free-running
+    tail: AtomicUsize,
+    /// Bytes dropped by [`Producer::push_lossy`]
+    overflows: AtomicU32,
+    /// Producer and consumer were handed out
+    split: AtomicBool,
+}
+
+// SAFETY: the buffer is only accessed through the single producer and the single consumer,
+// which own disjoint parts of it delimited by `head` and `tail`
+unsafe impl<const N: usize> Sync for Ring<N> {}
+
+impl<const N: usize> Default for Ring<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Ring<N> {
+    /// Create an empty ring
+    pub const fn new() -> Self {
+        const { assert!(N.is_power_of_two(), "ring size must be a power of two") };
+        Self {
+            buffer: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overflows: AtomicU32::new(0),
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// Hand out the producer and the consumer, only once
+    pub fn try_split(&self) -> Option<(Producer<'_, N>, Consumer<'_, N>)> {
+        critical_section::with(|_| {
+            if self.split.load(Ordering::Relaxed) {
+                return None;
+            }
+            self.split.store(true, Ordering::Relaxed);
+            Some((Producer { ring: self }, Consumer { ring: self }))
+        })
+    }
+
+    /// Ring size, bytes
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Bytes waiting for the consumer
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        head.wrapping_sub(tail)
+    }
+
+    /// No bytes waiting
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes dropped because the ring was full
+    pub fn overflows(&self) -> u32 {
+        self.overflows.load(Ordering::Relaxed)
+    }
+
+    /// Pointer to the byte at the free-running position
+    fn slot(&self, position: usize) -> *mut u8 {
+        // SAFETY: `position % N` is inside the buffer
+        unsafe { self.buffer.get().cast::<u8>().add(position % N) }
+    }
+}
+
+/// Writing side of a [`Ring`]
+pub struct Producer<'a, const N: usize> {
+    ring: &'a Ring<N>,
+}
+
+impl<const N: usize> Producer<'_, N> {
+    /// Append as many bytes as fit. Returns the number of appended bytes
+    ///
+    /// * `bytes` - Bytes to append
+    pub fn push_slice(&mut self, bytes: &[u8]) -> usize {
+        let head = self.ring.head.load(Ordering::Relaxed);
+        let tail = self.ring.tail.load(Ordering::Acquire);
+        let free = N - head.wrapping_sub(tail);
+        let count = bytes.len().min(free);
+        for (offset, &byte) in bytes.iter().take(count).enumerate() {
+            // SAFETY: the slots between head and tail + N belong to the producer
+            unsafe { self.ring.slot(head.wrapping_add(offset)).write(byte) };
+        }
+        self.ring
+            .head
+            .store(head.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    /// Append the bytes, the ones that do not fit are dropped and counted as overflows.
+    /// Returns the number of appended bytes
+    ///
+    /// * `bytes` - Bytes to append
+    pub fn push_lossy(&mut self, bytes: &[u8]) -> usize {
+        let count = self.push_slice(bytes);
+        let dropped = u32::try_from(bytes.len() - count).unwrap_or(u32::MAX);
+        if dropped > 0 {
+            // Счётчик меняет только производитель - хватает load/store
+            let overflows = self.ring.overflows.load(Ordering::Relaxed);
+            self.ring
+                .overflows
+                .store(overflows.saturating_add(dropped), Ordering::Relaxed);
+        }
+        count
+    }
+
+    /// Free space, bytes
+    pub fn free(&self) -> usize {
+        N - self.ring.len()
+    }
+
+    /// Ring of the producer
+    pub fn ring(&self) -> &Ring<N> {
+        self.ring
+    }
+}
+
+/// Reading side of a [`Ring`]
+pub struct Consumer<'a, const N: usize> {
+    ring: &'a Ring<N>,
+}
+
+impl<const N: usize> Consumer<'_, N> {
+    /// Take the oldest byte
+    pub fn pop(&mut self) -> Option<u8> {
+        let mut byte = [0];
+        (self.pop_slice(&mut byte) == 1).then_some(byte[0])
+    }
+
+    /// Take as many bytes as are available and fit. Returns the number of bytes
+    ///
+    /// * `buffer` - Output buffer
+    pub fn pop_slice(&mut self, buffer: &mut [u8]) -> usize {
+        let mut count = 0;
+        while count < buffer.len() {
+            let chunk = self.peek();
+            let len = chunk.len().min(buffer.len() - count);
+            if len == 0 {
+                break;
+            }
+            if let (Some(out), Some(chunk)) = (buffer.get_mut(count..count + len), chunk.get(..len))
+            {
+                out.copy_from_slice(chunk);
+            }
+            self.consume(len);
+            count += len;
+        }
+        count
+    }
+
+    /// Contiguous part of the waiting bytes (up to the end of the buffer), nothing is taken
+    pub fn peek(&self) -> &[u8] {
+        let tail = self.ring.tail.load(Ordering::Relaxed);
+        let head = self.ring.head.load(Ordering::Acquire);
+        let len = head.wrapping_sub(tail).min(N - tail % N);
+        // SAFETY: the slots between tail and head are written and belong to the consumer
+        // until `consume` moves the tail
+        unsafe { core::slice::from_raw_parts(self.ring.slot(tail), len) }
+    }
+
+    /// Drop bytes returned by [`Consumer::peek`]
+    ///
+    /// * `count` - Number of bytes, limited to the waiting ones
+    pub fn consume(&mut self, count: usize) {
+        let tail = self.ring.tail.load(Ordering::Relaxed);
+        let count = count.min(self.ring.len());
+        self.ring
+            .tail
+            .store(tail.wrapping_add(count), Ordering::Release);
+    }
+
+    /// Ring of the consumer
+    pub fn ring(&self) -> &Ring<N> {
+        self.ring
+    }
+}
@@ -0,0 +1,84 @@
+//! Ring buffer shared between a producer and a consumer thread
+
+#![allow(clippy::indexing_slicing, clippy::cast_possible_truncation)]
+
+use buffered_uart::Ring;
+use std::thread;
+
+#[test]
+fn split_only_once() {
+    let ring = Ring::<8>::new();
+    assert!(ring.try_split().is_some());
+    assert!(ring.try_split().is_none());
+}
+
+#[test]
+fn full_ring_counts_overflows() {
+    let ring = Ring::<8>::new();
+    let (mut producer, mut consumer) = ring.try_split().expect("split");
+
+    assert_eq!(producer.push_lossy(&[1, 2, 3, 4, 5]), 5);
+    assert_eq!(producer.push_lossy(&[6, 7, 8, 9, 10]), 3);
+    assert_eq!((ring.len(), ring.overflows()), (8, 2));
+    assert_eq!(producer.push_slice(&[11]), 0);
+    assert_eq!(ring.overflows(), 2, "only lossy pushes count");
+
+    let mut buffer = [0; 16];
+    assert_eq!(consumer.pop_slice(&mut buffer), 8);
+    assert_eq!(buffer[..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(consumer.pop(), None);
+    assert!(ring.is_empty());
+}
+
+#[test]
+fn peek_stops_at_the_wrap_around() {
+    let ring = Ring::<8>::new();
+    let (mut producer, mut consumer) = ring.try_split().expect("split");
+
+    producer.push_slice(&[0; 6]);
+    consumer.consume(6);
+    producer.push_slice(&[1, 2, 3, 4, 5]);
+    assert_eq!(consumer.peek(), [1, 2]);
+    consumer.consume(2);
+    assert_eq!(consumer.peek(), [3, 4, 5]);
+    consumer.consume(100);
+    assert_eq!(consumer.peek(), []);
+    assert_eq!(producer.free(), 8);
+}
+
+#[test]
+fn bytes_cross_threads_in_order() {
+    const COUNT: usize = 200_000;
+    static RING: Ring<64> = Ring::new();
+    let (mut producer, mut consumer) = RING.try_split().expect("split");
+
+    let writer = thread::spawn(move || {
+        let mut next = 0;
+        while next < COUNT {
+            // Порции разной длины, чтобы пересекать границу буфера в разных местах
+            let chunk: Vec<u8> = (next..COUNT.min(next + 1 + next % 23))
+                .map(|i| i as u8)
+                .collect();
+            match producer.push_slice(&chunk) {
+                0 => thread::yield_now(),
+                len => next += len,
+            }
+        }
+    });
+
+    let mut received = 0;
+    let mut buffer = [0; 17];
+    while received < COUNT {
+        let len = consumer.pop_slice(&mut buffer);
+        if len == 0 {
+            thread::yield_now();
+        }
+        for &byte in &buffer[..len] {
+            assert_eq!(byte, received as u8);
+            received += 1;
+        }
+    }
+    writer.join().expect("writer");
+    assert!(RING.is_empty());
+    assert_eq!(RING.overflows(), 0);
+}
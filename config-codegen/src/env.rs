@@ -0,0 +1,146 @@
+//! Build environment - profile selection and `APP_CONFIG_<KEY>` overrides
+
+use crate::schema::{FieldType, Problems, Schema};
+use anyhow::{Result, bail};
+use serde_json::{Map, Number, Value};
+use std::env;
+
+/// Prefix of the environment variables overriding single keys
+pub const ENV_PREFIX: &str = "APP_CONFIG_";
+
+/// Environment variable selecting the profile (wins over the `profile-*` features)
+pub const PROFILE_ENV: &str = "APP_CONFIG_PROFILE";
+
+/// Cargo sets `CARGO_FEATURE_<NAME>` for every enabled feature of the package
+const PROFILE_FEATURE_PREFIX: &str = "CARGO_FEATURE_PROFILE_";
+
+/// Environment variable name of the configuration key
+///
+/// * `key` - Key in `config.json`
+pub fn env_name(key: &str) -> String {
+    format!("{ENV_PREFIX}{}", key.to_ascii_uppercase())
+}
+
+/// Profile selected for the build: `APP_CONFIG_PROFILE` or the enabled `profile-<name>` feature
+pub fn selected_profile() -> Result<Option<String>> {
+    println!("cargo:rerun-if-env-changed={PROFILE_ENV}");
+    // Cargo re-runs the build script itself when the feature set changes
+    let features = env::vars()
+        .filter_map(|(name, _)| name.strip_prefix(PROFILE_FEATURE_PREFIX).map(str::to_owned));
+    profile_from(env::var(PROFILE_ENV).ok(), features)
+}
+
+/// Pick the profile from the environment variable and the enabled features
+///
+/// * `from_env` - `APP_CONFIG_PROFILE` value
+/// * `features` - Enabled profile features (`CARGO_FEATURE_PROFILE_` suffixes)
+fn profile_from(
+    from_env: Option<String>,
+    features: impl Iterator<Item = String>,
+) -> Result<Option<String>> {
+    // Feature `profile-dev` becomes `CARGO_FEATURE_PROFILE_DEV`
+    let mut features: Vec<String> = features
+        .map(|feature| feature.to_ascii_lowercase().replace('_', "-"))
+        .collect();
+    features.sort();
+
+    let profile = match (
+        from_env.filter(|name| !name.is_empty()),
+        features.as_slice(),
+    ) {
+        (Some(name), _) => name,
+        (None, []) => return Ok(None),
+        (None, [feature]) => feature.clone(),
+        (None, _) => bail!(
+            "Several profile features are enabled ({}), select one or set {PROFILE_ENV}",
+            features.join(", ")
+        ),
+    };
+    if !profile
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    {
+        bail!("Profile name {profile:?} must consist of lowercase letters, digits, '-' and '_'");
+    }
+    Ok(Some(profile))
+}
+
+impl Schema {
+    /// Collect `APP_CONFIG_<KEY>` overrides, converted to the field types.
+    /// Ranges are checked later, when the overrides are merged
+    ///
+    /// * `lookup` - Environment variable lookup
+    pub(crate) fn env_overrides(
+        &self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Map<String, Value>> {
+        let mut overrides = Map::new();
+        let mut problems = Problems::default();
+        for field in &self.fields {
+            let name = env_name(&field.key);
+            println!("cargo:rerun-if-env-changed={name}");
+            let Some(text) = lookup(&name) else {
+                continue;
+            };
+
+            let value = match &field.ty {
+                FieldType::String { .. } | FieldType::Enum { .. } => Ok(Value::String(text)),
+                FieldType::Bool => match text.as_str() {
+                    "true" | "1" => Ok(Value::Bool(true)),
+                    "false" | "0" => Ok(Value::Bool(false)),
+                    _ => Err(format!("expected true/false, got {text:?}")),
+                },
+                FieldType::Integer { rust, .. } => text
+                    .trim()
+                    .parse::<i64>()
+                    .map(|number| Value::Number(Number::from(number)))
+                    .map_err(|_| format!("expected integer ({rust}), got {text:?}")),
+            };
+            match value {
+                Ok(value) => {
+                    overrides.insert(field.key.clone(), value);
+                }
+                Err(problem) => problems.push(&name, problem),
+            }
+        }
+
+        problems.finish(&format!("{ENV_PREFIX}* environment"))?;
+        Ok(overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(names: &[&str]) -> impl Iterator<Item = String> {
+        names
+            .iter()
+            .map(|name| (*name).to_owned())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn env_var_wins_over_features() {
+        let profile = profile_from(Some("wokwi".to_owned()), features(&["DEV"]));
+        assert_eq!(profile.expect("valid"), Some("wokwi".to_owned()));
+    }
+
+    #[test]
+    fn single_feature_selects_the_profile() {
+        let profile = profile_from(None, features(&["FIELD_TEST"]));
+        assert_eq!(profile.expect("valid"), Some("field-test".to_owned()));
+        assert_eq!(
+            profile_from(Some(String::new()), features(&[])).expect("valid"),
+            None
+        );
+    }
+
+    #[test]
+    fn conflicting_features_are_rejected() {
+        let error = profile_from(None, features(&["PROD", "DEV"])).expect_err("two profiles");
+        assert!(error.to_string().contains("(dev, prod)"), "{error}");
+        assert!(profile_from(Some("../prod".to_owned()), features(&[])).is_err());
+    }
+}
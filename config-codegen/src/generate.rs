@@ -0,0 +1,163 @@
+//! Rust code generation from the validated configuration
+
+use crate::schema::{FieldType, Schema, variant_name};
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::fmt::Write as _;
+
+impl Schema {
+    /// Rust literal of the validated value
+    fn literal(ty: &FieldType, value: &Value) -> String {
+        match (ty, value) {
+            (FieldType::Enum { name, .. }, Value::String(text)) => {
+                format!("{name}::{}", variant_name(text))
+            }
+            // `Debug` of `str` is a valid, properly escaped Rust string literal
+            (_, Value::String(text)) => format!("{text:?}"),
+            (_, value) => value.to_string(),
+        }
+    }
+
+    /// Generate the Rust source with enums and the configuration struct
+    ///
+    /// * `config` - Validated configuration
+    /// * `source` - Configuration origin, mentioned in the header
+    pub fn generate(&self, config: &Map<String, Value>, source: &str) -> Result<String> {
+        let mut code = String::new();
+        writeln!(
+            code,
+            "// This file is auto-generated by build.rs from {source} - DO NOT EDIT"
+        )?;
+
+        // Enum types, each generated once
+        let mut generated_enums: Vec<&str> = Vec::new();
+        for field in &self.fields {
+            let FieldType::Enum { name, values } = &field.ty else {
+                continue;
+            };
+            if generated_enums.contains(&name.as_str()) {
+                continue;
+            }
+            generated_enums.push(name);
+
+            writeln!(
+                code,
+                "\n/// {}",
+                field.doc.lines().next().unwrap_or_default()
+            )?;
+            writeln!(
+                code,
+                "#[allow(dead_code)] // not every value is used by the firmware"
+            )?;
+            writeln!(code, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]")?;
+            writeln!(code, "pub enum {name} {{")?;
+            for value in values {
+                writeln!(code, "    /// `{value}`\n    {},", variant_name(value))?;
+            }
+            writeln!(code, "}}\n\nimpl {name} {{")?;
+            writeln!(code, "    /// Value as written in the configuration")?;
+            writeln!(
+                code,
+                "    pub const fn as_str(self) -> &'static str {{\n        match self {{"
+            )?;
+            for value in values {
+                writeln!(
+                    code,
+                    "            {name}::{} => {value:?},",
+                    variant_name(value)
+                )?;
+            }
+            writeln!(code, "        }}\n    }}\n}}\n")?;
+            writeln!(code, "impl core::fmt::Display for {name} {{")?;
+            writeln!(
+                code,
+                "    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {{\n        \
+                 f.write_str(self.as_str())\n    }}\n}}"
+            )?;
+        }
+
+        writeln!(
+            code,
+            "\n/// Configuration loaded from {source} at compile time"
+        )?;
+        writeln!(code, "pub struct {};\n", self.name)?;
+        writeln!(
+            code,
+            "#[allow(dead_code)] // not every constant is used by the firmware"
+        )?;
+        writeln!(code, "impl {} {{", self.name)?;
+        for (index, field) in self.fields.iter().enumerate() {
+            if index > 0 {
+                writeln!(code)?;
+            }
+            for line in field.doc.lines() {
+                writeln!(code, "    /// {line}")?;
+            }
+            let range = match &field.ty {
+                FieldType::Integer { min, max, .. } => Some(format!("{min}..={max}")),
+                _ => None,
+            };
+            match (range, &field.unit) {
+                (Some(range), Some(unit)) => {
+                    writeln!(code, "    ///\n    /// Range: {range} {unit}")?;
+                }
+                (Some(range), None) => writeln!(code, "    ///\n    /// Range: {range}")?,
+                (None, Some(unit)) => writeln!(code, "    ///\n    /// Unit: {unit}")?,
+                (None, None) => {}
+            }
+            let rust_type = match &field.ty {
+                FieldType::String { .. } => "&str",
+                FieldType::Bool => "bool",
+                FieldType::Integer { rust, .. } => rust,
+                FieldType::Enum { name, .. } => name,
+            };
+            let value = config.get(&field.key).unwrap_or(&field.default);
+            writeln!(
+                code,
+                "    pub const {}: {rust_type} = {};",
+                field.name,
+                Self::literal(&field.ty, value)
+            )?;
+
+            // Limits for runtime validation (values received over BLE, shell, ...)
+            match &field.ty {
+                FieldType::Integer { rust, min, max } => writeln!(
+                    code,
+                    "    /// Allowed range of [`Self::{name}`]\n    pub const {name}_RANGE: core::ops::RangeInclusive<{rust}> = {min}..={max};",
+                    name = field.name
+                )?,
+                FieldType::String {
+                    max_length: Some(max),
+                    ..
+                } => writeln!(
+                    code,
+                    "    /// Maximal length of [`Self::{name}`], characters\n    pub const {name}_MAX_LEN: usize = {max};",
+                    name = field.name
+                )?,
+                _ => {}
+            }
+        }
+        writeln!(code, "}}")?;
+
+        // Checks are re-evaluated by the compiler, so hand edits of the output cannot break them
+        for check in &self.checks {
+            let constant = |key: &str| {
+                self.fields
+                    .iter()
+                    .find(|field| field.key == key)
+                    .map_or_else(String::new, |field| {
+                        format!("{}::{}", self.name, field.name)
+                    })
+            };
+            writeln!(
+                code,
+                "\nconst _: () = assert!({} {} {});",
+                constant(&check.left),
+                check.op,
+                constant(&check.right)
+            )?;
+        }
+
+        Ok(code)
+    }
+}
@@ -0,0 +1,376 @@
+//! Build-time configuration for the firmware crates
+//!
+//! A JSON schema describes the configuration fields (types, ranges, enums, defaults and docs),
+//! configuration files are merged over the schema defaults and validated, and a single
+//! `AppConfig`-like struct with `const` values is generated for `include!`.
+//!
+//! ```no_run
+//! // build.rs
+//! fn main() -> anyhow::Result<()> {
+//!     let out_dir = std::env::var("OUT_DIR")?;
+//!     config_codegen::ConfigBuilder::from_schema_file("config.schema.json")?
+//!         .merge_optional_file("config.json")?
+//!         .merge_profile("config.json")?
+//!         .merge_env()?
+//!         .write(std::path::Path::new(&out_dir).join("config.rs"))
+//! }
+//! ```
+//!
+//! Later sources win, every key can come from a different source:
+//!
+//! 1. schema defaults
+//! 2. `config.json`
+//! 3. `config.<profile>.json`, the profile is selected by `APP_CONFIG_PROFILE=<profile>` or by
+//!    a `profile-<profile>` cargo feature (the environment variable wins over the feature)
+//! 4. `APP_CONFIG_<KEY>` environment variables, e.g. `APP_CONFIG_LOG_LEVEL=TRACE`
+//!
+//! The schema lists the configuration fields in the order they are generated:
+//!
+//! ```json
+//! {
+//!   "struct": "AppConfig",
+//!   "fields": [
+//!     { "key": "log_level", "const": "LOG_LEVEL", "type": "enum", "enum": "LogLevel",
+//!       "values": ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"], "default": "INFO", "doc": "Log level" },
+//!     { "key": "button_long_press_threshold", "const": "BTN_LONG_PRESS_THRESHOLD_MS", "type": "u32",
+//!       "min": 100, "max": 2000, "unit": "ms", "default": 300, "doc": "Long press threshold" }
+//!   ],
+//!   "checks": [{ "left": "button_long_press_threshold", "op": "<", "right": "button_long_hold_threshold" }]
+//! }
+//! ```
+//!
+//! Supported types: `string` (`min_length`, `max_length`), `bool`, `u8`, `u16`, `u32`, `i32`
+//! (`min`, `max`) and `enum` (`enum` type name, `values`). Every problem is reported with the
+//! file name and the JSON path of the offending value, all problems at once.
+
+mod env;
+mod generate;
+mod schema;
+
+pub use env::{ENV_PREFIX, PROFILE_ENV, env_name, selected_profile};
+pub use schema::Schema;
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Configuration assembled from the schema defaults and partial configuration files
+#[derive(Debug, Clone)]
+pub struct ConfigBuilder {
+    schema: Schema,
+    /// Current values, always complete (starts with the defaults)
+    values: Map<String, Value>,
+    /// Applied configuration sources, in order
+    sources: Vec<String>,
+}
+
+impl ConfigBuilder {
+    /// Start from the schema defaults
+    ///
+    /// * `schema` - Parsed schema
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            values: schema.defaults(),
+            schema,
+            sources: vec!["schema defaults".to_owned()],
+        }
+    }
+
+    /// Read the schema file and start from its defaults
+    ///
+    /// * `path` - Schema file
+    pub fn from_schema_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        rerun_if_changed(path);
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let schema = Schema::parse(&source)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Self::new(schema))
+    }
+
+    /// Merge a partial configuration over the current values
+    ///
+    /// * `config` - Configuration object, only the present keys are changed
+    /// * `source` - Configuration name used in error messages
+    pub fn merge_value(&mut self, config: &Value, source: &str) -> Result<&mut Self> {
+        let partial = self.schema.validate_partial(config, source)?;
+        self.values.extend(partial);
+        self.sources.push(source.to_owned());
+        Ok(self)
+    }
+
+    /// Merge a partial configuration file, the file must exist
+    ///
+    /// * `path` - JSON file
+    pub fn merge_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self> {
+        let path = path.as_ref();
+        rerun_if_changed(path);
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config: Value = serde_json::from_str(&source)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        self.merge_value(&config, &path.display().to_string())
+    }
+
+    /// Merge a partial configuration file if it exists
+    ///
+    /// * `path` - JSON file
+    pub fn merge_optional_file(&mut self, path: impl AsRef<Path>) -> Result<&mut Self> {
+        let path = path.as_ref();
+        if path.exists() {
+            self.merge_file(path)
+        } else {
+            // Track the file even if it does not exist yet, creating it must trigger a rebuild
+            rerun_if_changed(path);
+            println!(
+                "cargo:warning={} not found, using defaults for its keys",
+                path.display()
+            );
+            Ok(self)
+        }
+    }
+
+    /// Merge `<stem>.<profile>.<ext>` next to the base file when a profile is selected
+    /// (`APP_CONFIG_PROFILE` or a `profile-<name>` cargo feature), the file must exist
+    ///
+    /// * `base` - Base configuration file, e.g. `config.json` selects `config.dev.json`
+    pub fn merge_profile(&mut self, base: impl AsRef<Path>) -> Result<&mut Self> {
+        let Some(profile) = env::selected_profile()? else {
+            return Ok(self);
+        };
+        let base = base.as_ref();
+        let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+        let path = match base.extension() {
+            Some(extension) => {
+                base.with_file_name(format!("{stem}.{profile}.{}", extension.to_string_lossy()))
+            }
+            None => base.with_file_name(format!("{stem}.{profile}")),
+        };
+        println!(
+            "cargo:warning=Using config profile '{profile}' ({})",
+            path.display()
+        );
+        self.merge_file(&path)
+            .with_context(|| format!("Config profile '{profile}' is not usable"))
+    }
+
+    /// Merge `APP_CONFIG_<KEY>` environment variables (highest precedence)
+    pub fn merge_env(&mut self) -> Result<&mut Self> {
+        // Typos would be silently ignored otherwise
+        for (name, _) in std::env::vars() {
+            if name.starts_with(ENV_PREFIX)
+                && name != PROFILE_ENV
+                && !self.schema.fields.iter().any(|f| env_name(&f.key) == name)
+            {
+                println!("cargo:warning={name} does not match any configuration key, ignored");
+            }
+        }
+        self.merge_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Merge the environment overrides provided by the lookup
+    fn merge_env_with(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<&mut Self> {
+        let overrides = self.schema.env_overrides(lookup)?;
+        if overrides.is_empty() {
+            return Ok(self);
+        }
+        for key in overrides.keys() {
+            println!(
+                "cargo:warning={} overrides the configured value",
+                env_name(key)
+            );
+        }
+        self.merge_value(
+            &Value::Object(overrides),
+            &format!("{ENV_PREFIX}* environment"),
+        )
+    }
+
+    /// Current (merged) values
+    pub fn values(&self) -> &Map<String, Value> {
+        &self.values
+    }
+
+    /// Check the merged configuration and generate the Rust source
+    pub fn generate(&self) -> Result<String> {
+        let sources = self.sources.join(", ");
+        self.schema.check(&self.values, &sources)?;
+        self.schema.generate(&self.values, &sources)
+    }
+
+    /// Check the merged configuration and write the Rust source
+    ///
+    /// * `path` - Output file (usually `$OUT_DIR/config.rs`)
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.generate()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Ask cargo to re-run the build script when the file changes
+fn rerun_if_changed(path: &Path) {
+    println!("cargo:rerun-if-changed={}", path.display());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "struct": "AppConfig",
+        "fields": [
+            { "key": "device_name", "const": "DEVICE_NAME", "type": "string", "max_length": 16,
+              "default": "ESP32", "doc": "Device name" },
+            { "key": "log_level", "const": "LOG_LEVEL", "type": "enum", "enum": "LogLevel",
+              "values": ["INFO", "DEBUG"], "default": "INFO", "doc": "Log level" },
+            { "key": "press", "const": "PRESS_MS", "type": "u32", "min": 100, "max": 2000,
+              "unit": "ms", "default": 300, "doc": "Long press" },
+            { "key": "hold", "const": "HOLD_MS", "type": "u32", "min": 500, "max": 10000,
+              "default": 2000, "doc": "Long hold" }
+        ],
+        "checks": [{ "left": "press", "op": "<", "right": "hold" }]
+    }"#;
+
+    fn builder() -> ConfigBuilder {
+        ConfigBuilder::new(Schema::parse(SCHEMA).expect("valid schema"))
+    }
+
+    fn merge(builder: &mut ConfigBuilder, json: &str) -> Result<()> {
+        let value: Value = serde_json::from_str(json).expect("valid JSON");
+        builder.merge_value(&value, "test.json").map(|_| ())
+    }
+
+    #[test]
+    fn defaults_and_empty_file_generate_the_same_constants() {
+        let defaults = builder().generate().expect("defaults are valid");
+        let mut merged = builder();
+        merge(&mut merged, "{}").expect("empty config is valid");
+        let merged = merged.generate().expect("merged config is valid");
+
+        let constants = |code: &str| -> Vec<String> {
+            code.lines()
+                .filter(|line| line.contains("pub const"))
+                .map(str::to_owned)
+                .collect()
+        };
+        assert_eq!(constants(&defaults), constants(&merged));
+        assert!(defaults.contains("pub const PRESS_MS: u32 = 300;"));
+        assert!(defaults.contains("/// Range: 100..=2000 ms"));
+        assert!(
+            defaults
+                .contains("pub const PRESS_MS_RANGE: core::ops::RangeInclusive<u32> = 100..=2000;")
+        );
+        assert!(defaults.contains("pub const DEVICE_NAME_MAX_LEN: usize = 16;"));
+    }
+
+    #[test]
+    fn partial_files_override_only_their_keys() {
+        let mut config = builder();
+        merge(&mut config, r#"{ "press": 400 }"#).expect("valid");
+        merge(&mut config, r#"{ "log_level": "DEBUG" }"#).expect("valid");
+        let code = config.generate().expect("valid");
+
+        assert!(code.contains("pub const PRESS_MS: u32 = 400;"));
+        assert!(code.contains("pub const HOLD_MS: u32 = 2000;"));
+        assert!(code.contains("pub const LOG_LEVEL: LogLevel = LogLevel::Debug;"));
+        assert!(code.contains("from schema defaults, test.json, test.json"));
+    }
+
+    #[test]
+    fn problems_are_reported_with_json_paths() {
+        let mut config = builder();
+        let error = merge(
+            &mut config,
+            r#"{ "press": 20, "log_level": "TRACE", "colour": 1, "device_name": 5 }"#,
+        )
+        .expect_err("invalid config");
+        let message = format!("{error:#}");
+
+        assert!(
+            message.contains("test.json is invalid (4 problem(s))"),
+            "{message}"
+        );
+        assert!(
+            message.contains("$.press: 20 is out of range 100..=2000"),
+            "{message}"
+        );
+        assert!(
+            message.contains(r#"$.log_level: "TRACE" is not one of "INFO", "DEBUG""#),
+            "{message}"
+        );
+        assert!(message.contains("$.colour: unknown key"), "{message}");
+        assert!(
+            message.contains("$.device_name: expected string, got 5"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn checks_apply_to_the_merged_configuration() {
+        let mut config = builder();
+        merge(&mut config, r#"{ "press": 1500 }"#).expect("valid");
+        merge(&mut config, r#"{ "hold": 1000 }"#).expect("valid on its own");
+        let error = config.generate().expect_err("press is longer than hold");
+        assert!(format!("{error:#}").contains("$.press: press (1500) must be < hold (1000)"));
+    }
+
+    #[test]
+    fn environment_overrides_win_and_are_validated() {
+        let mut config = builder();
+        merge(&mut config, r#"{ "press": 400, "device_name": "file" }"#).expect("valid");
+        config
+            .merge_env_with(|name| match name {
+                "APP_CONFIG_PRESS" => Some("500".to_owned()),
+                "APP_CONFIG_LOG_LEVEL" => Some("DEBUG".to_owned()),
+                _ => None,
+            })
+            .expect("valid overrides");
+        let code = config.generate().expect("valid");
+        assert!(code.contains("pub const PRESS_MS: u32 = 500;"));
+        assert!(code.contains(r#"pub const DEVICE_NAME: &str = "file";"#));
+        assert!(code.contains("pub const LOG_LEVEL: LogLevel = LogLevel::Debug;"));
+
+        let error = builder()
+            .merge_env_with(|name| (name == "APP_CONFIG_HOLD").then(|| "2s".to_owned()))
+            .expect_err("not an integer");
+        assert!(
+            format!("{error:#}").contains(r#"APP_CONFIG_HOLD: expected integer (u32), got "2s""#)
+        );
+
+        let error = builder()
+            .merge_env_with(|name| (name == "APP_CONFIG_HOLD").then(|| "20".to_owned()))
+            .expect_err("out of range");
+        assert!(format!("{error:#}").contains("APP_CONFIG_* environment is invalid"));
+    }
+
+    #[test]
+    fn string_literals_are_escaped() {
+        let mut config = builder();
+        merge(&mut config, r#"{ "device_name": "a\"b\\c\n" }"#).expect("valid");
+        let code = config.generate().expect("valid");
+        assert!(
+            code.contains(r#"pub const DEVICE_NAME: &str = "a\"b\\c\n";"#),
+            "{code}"
+        );
+    }
+
+    #[test]
+    fn invalid_schema_is_rejected() {
+        let error = Schema::parse(
+            r#"{ "fields": [{ "key": "x", "const": "x", "type": "u8", "max": 300, "default": 1, "doc": "" }] }"#,
+        )
+        .expect_err("invalid schema");
+        let message = format!("{error:#}");
+        assert!(
+            message.contains(r#"$.fields[0].const: "x" is not SCREAMING_SNAKE_CASE"#),
+            "{message}"
+        );
+        assert!(
+            message.contains("$.fields[0]: range 0..=300 does not fit into u8"),
+            "{message}"
+        );
+    }
+}
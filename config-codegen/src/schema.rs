@@ -0,0 +1,448 @@
+//! Configuration schema - field definitions, validation of configuration values
+
+use anyhow::{Result, bail};
+use serde_json::{Map, Value};
+
+/// Value type of a configuration field
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FieldType {
+    String {
+        min_length: Option<u64>,
+        max_length: Option<u64>,
+    },
+    Bool,
+    Integer {
+        /// Rust type name
+        rust: &'static str,
+        min: i64,
+        max: i64,
+    },
+    Enum {
+        /// Rust enum name
+        name: String,
+        /// Allowed values (`SCREAMING_CASE` strings)
+        values: Vec<String>,
+    },
+}
+
+/// Configuration field definition
+#[derive(Debug, Clone)]
+pub(crate) struct Field {
+    /// Key in `config.json`
+    pub(crate) key: String,
+    /// Generated constant name
+    pub(crate) name: String,
+    pub(crate) ty: FieldType,
+    /// Documentation of the generated constant
+    pub(crate) doc: String,
+    /// Unit shown in the documentation
+    pub(crate) unit: Option<String>,
+    /// Default value (already validated against the type)
+    pub(crate) default: Value,
+}
+
+/// Comparison between two numeric fields
+#[derive(Debug, Clone)]
+pub(crate) struct Check {
+    pub(crate) left: String,
+    pub(crate) op: String,
+    pub(crate) right: String,
+}
+
+/// Configuration schema
+#[derive(Debug, Clone)]
+pub struct Schema {
+    /// Generated struct name
+    pub(crate) name: String,
+    pub(crate) fields: Vec<Field>,
+    pub(crate) checks: Vec<Check>,
+}
+
+/// Integer types: name, minimal and maximal value
+const INTEGER_TYPES: &[(&str, i64, i64)] = &[
+    ("u8", 0, u8::MAX as i64),
+    ("u16", 0, u16::MAX as i64),
+    ("u32", 0, u32::MAX as i64),
+    ("i32", i32::MIN as i64, i32::MAX as i64),
+];
+
+/// Comparison operators supported by checks
+const CHECK_OPS: &[&str] = &["<", "<=", ">", ">=", "==", "!="];
+
+/// Collected problems, reported together
+#[derive(Default)]
+pub(crate) struct Problems(Vec<String>);
+
+impl Problems {
+    pub(crate) fn push(&mut self, path: &str, message: impl std::fmt::Display) {
+        self.0.push(format!("{path}: {message}"));
+    }
+
+    /// Fail with all collected problems
+    pub(crate) fn finish(self, what: &str) -> Result<()> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        bail!(
+            "{what} is invalid ({} problem(s)):\n  {}",
+            self.0.len(),
+            self.0.join("\n  ")
+        );
+    }
+}
+
+/// Check that the value matches the type, returns the problem description
+fn type_problem(ty: &FieldType, value: &Value) -> Option<String> {
+    match (ty, value) {
+        (
+            FieldType::String {
+                min_length,
+                max_length,
+            },
+            Value::String(text),
+        ) => {
+            let length = text.chars().count() as u64;
+            if min_length.is_some_and(|min| length < min)
+                || max_length.is_some_and(|max| length > max)
+            {
+                Some(format!(
+                    "string length {length} is out of range {}..={}",
+                    min_length.unwrap_or(0),
+                    max_length.map_or_else(String::new, |max| max.to_string())
+                ))
+            } else {
+                None
+            }
+        }
+        (FieldType::Bool, Value::Bool(_)) => None,
+        (FieldType::Integer { rust, min, max }, Value::Number(number)) => match number.as_i64() {
+            Some(number) if (*min..=*max).contains(&number) => None,
+            Some(number) => Some(format!("{number} is out of range {min}..={max}")),
+            None => Some(format!("expected integer ({rust}), got {number}")),
+        },
+        (FieldType::Enum { values, .. }, Value::String(text)) => {
+            if values.contains(text) {
+                None
+            } else {
+                Some(format!(
+                    "{text:?} is not one of {}",
+                    values
+                        .iter()
+                        .map(|value| format!("{value:?}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            }
+        }
+        (FieldType::String { .. }, _) => Some(format!("expected string, got {value}")),
+        (FieldType::Bool, _) => Some(format!("expected boolean, got {value}")),
+        (FieldType::Integer { rust, .. }, _) => {
+            Some(format!("expected integer ({rust}), got {value}"))
+        }
+        (FieldType::Enum { values, .. }, _) => Some(format!(
+            "expected one of {}, got {value}",
+            values.join(", ")
+        )),
+    }
+}
+
+/// `true` if the text is a `SCREAMING_SNAKE_CASE` identifier
+fn is_const_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// `true` if the text is a `UpperCamelCase` identifier
+fn is_type_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// `SCREAMING_CASE` enum value to `UpperCamelCase` variant name
+pub(crate) fn variant_name(value: &str) -> String {
+    value
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+            })
+        })
+        .collect()
+}
+
+impl Schema {
+    /// Parse and check the schema itself
+    ///
+    /// * `source` - Schema JSON text
+    pub fn parse(source: &str) -> Result<Self> {
+        let root: Value = serde_json::from_str(source)?;
+        let mut problems = Problems::default();
+
+        let name = match root.get("struct") {
+            None => "AppConfig".to_owned(),
+            Some(Value::String(name)) if is_type_name(name) => name.clone(),
+            Some(other) => {
+                problems.push("$.struct", format!("expected type name, got {other}"));
+                String::new()
+            }
+        };
+
+        let mut fields: Vec<Field> = Vec::new();
+        match root.get("fields") {
+            Some(Value::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    let path = format!("$.fields[{index}]");
+                    if let Some(field) = Self::parse_field(&path, item, &mut problems) {
+                        if fields.iter().any(|other| other.key == field.key) {
+                            problems.push(&path, format!("duplicate key {:?}", field.key));
+                        }
+                        if fields.iter().any(|other| other.name == field.name) {
+                            problems.push(&path, format!("duplicate constant {}", field.name));
+                        }
+                        fields.push(field);
+                    }
+                }
+            }
+            _ => problems.push("$.fields", "expected array of field definitions"),
+        }
+
+        let mut checks = Vec::new();
+        match root.get("checks") {
+            None => {}
+            Some(Value::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    let path = format!("$.checks[{index}]");
+                    let text =
+                        |name: &str| item.get(name).and_then(Value::as_str).map(str::to_owned);
+                    let (Some(left), Some(op), Some(right)) =
+                        (text("left"), text("op"), text("right"))
+                    else {
+                        problems.push(&path, "expected {\"left\", \"op\", \"right\"} strings");
+                        continue;
+                    };
+                    if !CHECK_OPS.contains(&op.as_str()) {
+                        problems.push(&path, format!("unknown operator {op:?}"));
+                    }
+                    for side in [&left, &right] {
+                        let numeric = fields
+                            .iter()
+                            .find(|field| &field.key == side)
+                            .is_some_and(|field| matches!(field.ty, FieldType::Integer { .. }));
+                        if !numeric {
+                            problems.push(&path, format!("{side:?} is not an integer field"));
+                        }
+                    }
+                    checks.push(Check { left, op, right });
+                }
+            }
+            Some(_) => problems.push("$.checks", "expected array of checks"),
+        }
+
+        problems.finish("config schema")?;
+        let schema = Self {
+            name,
+            fields,
+            checks,
+        };
+
+        // The defaults have to satisfy the checks as well
+        let defaults = schema.defaults();
+        let mut problems = Problems::default();
+        schema.run_checks(&defaults, "default", &mut problems);
+        problems.finish("config schema defaults")?;
+        Ok(schema)
+    }
+
+    /// Parse a single field definition
+    fn parse_field(path: &str, item: &Value, problems: &mut Problems) -> Option<Field> {
+        let Some(object) = item.as_object() else {
+            problems.push(path, "expected object");
+            return None;
+        };
+        let text = |name: &str, problems: &mut Problems| match object.get(name) {
+            Some(Value::String(text)) => Some(text.clone()),
+            Some(other) => {
+                problems.push(
+                    &format!("{path}.{name}"),
+                    format!("expected string, got {other}"),
+                );
+                None
+            }
+            None => {
+                problems.push(&format!("{path}.{name}"), "is required");
+                None
+            }
+        };
+        let number = |name: &str, problems: &mut Problems| match object.get(name) {
+            None => None,
+            Some(value) => {
+                let number = value.as_i64();
+                if number.is_none() {
+                    problems.push(
+                        &format!("{path}.{name}"),
+                        format!("expected integer, got {value}"),
+                    );
+                }
+                number
+            }
+        };
+
+        let key = text("key", problems)?;
+        let name = text("const", problems)?;
+        let ty_name = text("type", problems)?;
+        let doc = text("doc", problems)?;
+        if !is_const_name(&name) {
+            problems.push(
+                &format!("{path}.const"),
+                format!("{name:?} is not SCREAMING_SNAKE_CASE"),
+            );
+        }
+        let unit = object
+            .get("unit")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+
+        let ty = match ty_name.as_str() {
+            "string" => FieldType::String {
+                min_length: number("min_length", problems).map(i64::unsigned_abs),
+                max_length: number("max_length", problems).map(i64::unsigned_abs),
+            },
+            "bool" => FieldType::Bool,
+            "enum" => {
+                let name = text("enum", problems)?;
+                if !is_type_name(&name) {
+                    problems.push(
+                        &format!("{path}.enum"),
+                        format!("{name:?} is not UpperCamelCase"),
+                    );
+                }
+                let values: Vec<String> = match object.get("values") {
+                    Some(Value::Array(values)) => values
+                        .iter()
+                        .filter_map(|value| value.as_str().map(str::to_owned))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                if values.is_empty() || values.iter().any(|value| !is_const_name(value)) {
+                    problems.push(
+                        &format!("{path}.values"),
+                        "expected non-empty array of SCREAMING_CASE strings",
+                    );
+                }
+                FieldType::Enum { name, values }
+            }
+            rust => {
+                let Some(&(rust, type_min, type_max)) =
+                    INTEGER_TYPES.iter().find(|(name, _, _)| *name == rust)
+                else {
+                    problems.push(&format!("{path}.type"), format!("unknown type {rust:?}"));
+                    return None;
+                };
+                let min = number("min", problems).unwrap_or(type_min);
+                let max = number("max", problems).unwrap_or(type_max);
+                if min < type_min || max > type_max || min > max {
+                    problems.push(
+                        path,
+                        format!("range {min}..={max} does not fit into {rust}"),
+                    );
+                }
+                FieldType::Integer { rust, min, max }
+            }
+        };
+
+        let default = object.get("default").cloned().unwrap_or(Value::Null);
+        let problem = if default.is_null() {
+            Some("is required".to_owned())
+        } else {
+            type_problem(&ty, &default)
+        };
+        if let Some(problem) = problem {
+            problems.push(&format!("{path}.default"), problem);
+        }
+
+        Some(Field {
+            key,
+            name,
+            ty,
+            doc,
+            unit,
+            default,
+        })
+    }
+
+    /// Configuration made of the schema defaults only
+    pub fn defaults(&self) -> Map<String, Value> {
+        self.fields
+            .iter()
+            .map(|field| (field.key.clone(), field.default.clone()))
+            .collect()
+    }
+
+    /// Validate a (partial) configuration against the schema.
+    /// Keys that are not present keep their current values
+    ///
+    /// * `config` - Parsed configuration
+    /// * `source` - Configuration name used in error messages
+    pub fn validate_partial(&self, config: &Value, source: &str) -> Result<Map<String, Value>> {
+        let Some(object) = config.as_object() else {
+            bail!("{source}: $: expected object, got {config}");
+        };
+
+        let mut problems = Problems::default();
+        for (key, value) in object {
+            if let Some(field) = self.fields.iter().find(|field| &field.key == key) {
+                if let Some(problem) = type_problem(&field.ty, value) {
+                    problems.push(&format!("$.{key}"), problem);
+                }
+            } else {
+                let known: Vec<_> = self.fields.iter().map(|field| field.key.as_str()).collect();
+                problems.push(
+                    &format!("$.{key}"),
+                    format!("unknown key (known keys: {})", known.join(", ")),
+                );
+            }
+        }
+
+        problems.finish(source)?;
+        Ok(object.clone())
+    }
+
+    /// Validate a complete configuration against the cross-field checks
+    ///
+    /// * `config` - Complete configuration (defaults with merged files)
+    /// * `source` - Configuration name used in error messages
+    pub fn check(&self, config: &Map<String, Value>, source: &str) -> Result<()> {
+        let mut problems = Problems::default();
+        self.run_checks(config, "$", &mut problems);
+        problems.finish(source)
+    }
+
+    /// Evaluate the cross-field checks
+    fn run_checks(&self, config: &Map<String, Value>, path: &str, problems: &mut Problems) {
+        for check in &self.checks {
+            let value = |key: &str| config.get(key).and_then(Value::as_i64).unwrap_or_default();
+            let (left, right) = (value(&check.left), value(&check.right));
+            let holds = match check.op.as_str() {
+                "<" => left < right,
+                "<=" => left <= right,
+                ">" => left > right,
+                ">=" => left >= right,
+                "==" => left == right,
+                _ => left != right,
+            };
+            if !holds {
+                problems.push(
+                    &format!("{path}.{}", check.left),
+                    format!(
+                        "{} ({left}) must be {} {} ({right})",
+                        check.left, check.op, check.right
+                    ),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Little-endian payload encoding helpers for [`Record`](crate::Record) implementations
+
+/// Sequential writer into a payload buffer
+pub struct Writer<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// Writer constructor
+    ///
+    /// * `buffer` - Payload buffer provided by [`RecordLog::save`](crate::RecordLog::save)
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Append raw bytes. Returns `None` if the buffer is full
+    ///
+    /// * `bytes` - Bytes to append
+    pub fn bytes(&mut self, bytes: &[u8]) -> Option<&mut Self> {
+        let end = self.len.checked_add(bytes.len())?;
+        self.buffer.get_mut(self.len..end)?.copy_from_slice(bytes);
+        self.len = end;
+        Some(self)
+    }
+
+    /// Append a byte
+    pub fn u8(&mut self, value: u8) -> Option<&mut Self> {
+        self.bytes(&[value])
+    }
+
+    /// Append a boolean as a single byte
+    pub fn bool(&mut self, value: bool) -> Option<&mut Self> {
+        self.u8(u8::from(value))
+    }
+
+    /// Append a 16 bit value
+    pub fn u16(&mut self, value: u16) -> Option<&mut Self> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Append a 32 bit value
+    pub fn u32(&mut self, value: u32) -> Option<&mut Self> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// `true` if nothing has been written
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Sequential reader of a stored payload
+pub struct Reader<'a> {
+    payload: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Reader constructor
+    ///
+    /// * `payload` - Stored payload
+    pub fn new(payload: &'a [u8]) -> Self {
+        Self { payload }
+    }
+
+    /// Take the next `N` bytes. Returns `None` if the payload is too short
+    pub fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let (head, rest) = self.payload.split_at_checked(N)?;
+        self.payload = rest;
+        head.try_into().ok()
+    }
+
+    /// Read a byte
+    pub fn u8(&mut self) -> Option<u8> {
+        self.bytes::<1>().map(|[value]| value)
+    }
+
+    /// Read a boolean stored as a single byte
+    pub fn bool(&mut self) -> Option<bool> {
+        self.u8().map(|value| value != 0)
+    }
+
+    /// Read a 16 bit value
+    pub fn u16(&mut self) -> Option<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    /// Read a 32 bit value
+    pub fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    /// Bytes that have not been read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.payload
+    }
+}
@@ -0,0 +1,69 @@
+//! CRC-32 (IEEE 802.3, reflected, as used by zlib/Ethernet)
+
+/// Reflected polynomial
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Lookup table, computed at compile time
+#[allow(clippy::indexing_slicing, clippy::cast_possible_truncation)] // i < 256
+const TABLE: [u32; 256] = {
+    let mut table = [0_u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ POLYNOMIAL
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Incremental CRC-32 calculation
+pub struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    /// Start a new calculation
+    pub fn new() -> Self {
+        Self(!0)
+    }
+
+    /// Feed the next bytes
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            #[allow(clippy::cast_possible_truncation)] // low byte of the CRC is the table index
+            let index = usize::from((self.0 as u8) ^ byte);
+            self.0 = (self.0 >> 8) ^ TABLE.get(index).copied().unwrap_or_default();
+        }
+    }
+
+    /// Final CRC value
+    pub fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,28 @@
+//! Runtime configuration storage in NOR flash
+//!
+//! The configuration is stored as a sequence of records appended to a flash region made of two
+//! or more erase sectors. Every save appends a new record, a sector is erased only when the
+//! writer moves on to it, so the erase cycles are spread over the whole region and the latest
+//! complete record always survives a power loss in the middle of a write.
+//!
+//! ```text
+//! | magic (2) | version (2) | length (2) | reserved (2) | sequence (4) | CRC-32 (4) | payload |
+//! ```
+//!
+//! * `version` - Payload layout version, older layouts are migrated by [`Record::decode`]
+//! * `sequence` - Incremented on every save, the valid record with the highest one wins
+//! * `CRC-32` - Covers version, length, sequence and payload; torn records are skipped
+//!
+//! The crate is `no_std` and only depends on the `embedded-storage` NOR flash traits, so the
+//! same code runs on the device (`esp-storage`) and in host tests against a RAM flash.
+
+#![no_std]
+
+mod codec;
+mod crc;
+mod record_log;
+
+// Public re-export of specifics that are available outside of module
+pub use codec::{Reader, Writer};
+pub use crc::Crc32;
+pub use record_log::{Error, Loaded, MAX_PAYLOAD_LEN, Record, RecordLog};
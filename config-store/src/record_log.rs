@@ -0,0 +1,404 @@
+//! Append-only record log spread over NOR flash erase sectors
+
+use crate::codec::{Reader, Writer};
+use crate::crc::Crc32;
+use embedded_storage::nor_flash::NorFlash;
+
+/// Record marker
+const MAGIC: u16 = 0xC5F6;
+
+/// Header: magic (2) + version (2) + length (2) + reserved (2) + sequence (4) + CRC (4)
+const HEADER_LEN: usize = 16;
+
+/// Offset of the payload in the record
+#[allow(clippy::cast_possible_truncation)]
+const PAYLOAD_OFFSET: u32 = HEADER_LEN as u32;
+
+/// Largest payload, records are assembled on the stack
+pub const MAX_PAYLOAD_LEN: usize = 240;
+
+/// Largest record (header + payload)
+const MAX_RECORD_LEN: usize = HEADER_LEN + MAX_PAYLOAD_LEN;
+
+/// State of erased NOR flash
+const ERASED: u8 = 0xFF;
+
+/// Value that can be stored in the record log
+pub trait Record: Sized {
+    /// Current payload layout version, increment it on every layout change
+    const VERSION: u16;
+
+    /// Serialize the value, returns the payload length or `None` if it does not fit
+    ///
+    /// * `payload` - Payload buffer, [`MAX_PAYLOAD_LEN`] bytes
+    fn encode(&self, payload: &mut [u8]) -> Option<usize>;
+
+    /// Deserialize a payload written with the given layout version.
+    /// Older layouts have to be migrated (e.g. new values taken from the defaults)
+    ///
+    /// * `version` - Layout version of the stored record, never newer than [`Record::VERSION`]
+    /// * `payload` - Stored payload
+    fn decode(version: u16, payload: &[u8]) -> Option<Self>;
+}
+
+/// Record log errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// Flash driver error
+    Flash(E),
+    /// Region is not sector aligned, has less than two sectors or is out of the flash
+    Geometry,
+    /// Encoded value is larger than [`MAX_PAYLOAD_LEN`]
+    TooLarge,
+    /// Stored record was written by a newer firmware (layout version)
+    UnsupportedVersion(u16),
+    /// Stored record passed the CRC check but could not be decoded (layout version)
+    Corrupted(u16),
+    /// Written record did not read back intact
+    Verify,
+}
+
+/// Value restored from flash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Loaded<T> {
+    /// Decoded (and migrated) value
+    pub value: T,
+    /// Layout version of the stored record
+    pub version: u16,
+}
+
+impl<T: Record> Loaded<T> {
+    /// `true` if the record was written with an older layout and should be saved again
+    pub fn is_migrated(&self) -> bool {
+        self.version < T::VERSION
+    }
+}
+
+/// Record header
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    version: u16,
+    len: u16,
+    seq: u32,
+    crc: u32,
+}
+
+/// Content of a header sized slot in flash
+enum Slot {
+    /// Never written since the sector erase
+    Erased,
+    /// Record header
+    Record(Header),
+    /// Anything else (torn write, foreign data)
+    Garbage,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [ERASED; HEADER_LEN];
+        // The fields fill the header exactly
+        Writer::new(&mut bytes)
+            .u16(MAGIC)
+            .and_then(|writer| writer.u16(self.version))
+            .and_then(|writer| writer.u16(self.len))
+            .and_then(|writer| writer.u16(u16::MAX))
+            .and_then(|writer| writer.u32(self.seq))
+            .and_then(|writer| writer.u32(self.crc));
+        bytes
+    }
+
+    fn parse(bytes: &[u8; HEADER_LEN]) -> Slot {
+        if bytes.iter().all(|byte| *byte == ERASED) {
+            return Slot::Erased;
+        }
+        let mut reader = Reader::new(bytes);
+        match (
+            reader.u16(),
+            reader.u16(),
+            reader.u16(),
+            reader.u16(),
+            reader.u32(),
+            reader.u32(),
+        ) {
+            (Some(MAGIC), Some(version), Some(len), Some(_), Some(seq), Some(crc))
+                if usize::from(len) <= MAX_PAYLOAD_LEN =>
+            {
+                Slot::Record(Header {
+                    version,
+                    len,
+                    seq,
+                    crc,
+                })
+            }
+            _ => Slot::Garbage,
+        }
+    }
+
+    /// CRC over everything but the marker
+    fn checksum(version: u16, len: u16, seq: u32, payload: &[u8]) -> u32 {
+        let mut crc = Crc32::new();
+        crc.update(&version.to_le_bytes());
+        crc.update(&len.to_le_bytes());
+        crc.update(&seq.to_le_bytes());
+        crc.update(payload);
+        crc.finish()
+    }
+}
+
+/// Position of a valid record
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    addr: u32,
+    header: Header,
+}
+
+/// Record log in a flash region
+pub struct RecordLog<F> {
+    /// Flash driver
+    flash: F,
+    /// Absolute flash offset of the region
+    start: u32,
+    /// Erase sector size
+    sector_size: u32,
+    /// Number of sectors in the region
+    sectors: u32,
+    /// Sector the writer is in
+    sector: u32,
+    /// Next write position, `None` - the writer has to move on to an erased sector
+    cursor: Option<u32>,
+    /// Latest valid record
+    latest: Option<Location>,
+}
+
+impl<F: NorFlash> RecordLog<F> {
+    /// Record log constructor - scans the region for the latest valid record
+    ///
+    /// * `flash` - Flash driver
+    /// * `offset` - Absolute offset of the region, sector aligned
+    /// * `size` - Region size, at least two sectors
+    pub fn new(flash: F, offset: u32, size: u32) -> Result<Self, Error<F::Error>> {
+        let align = Self::align();
+        let sector_size = u32::try_from(F::ERASE_SIZE).map_err(|_| Error::Geometry)?;
+        let fits = offset
+            .checked_add(size)
+            .and_then(|end| usize::try_from(end).ok())
+            .is_some_and(|end| end <= flash.capacity());
+        if align == 0
+            || !HEADER_LEN.is_multiple_of(align)
+            || !MAX_RECORD_LEN.is_multiple_of(align)
+            || F::ERASE_SIZE < MAX_RECORD_LEN
+            || !offset.is_multiple_of(sector_size)
+            || !size.is_multiple_of(sector_size)
+            || size / sector_size < 2
+            || !fits
+        {
+            return Err(Error::Geometry);
+        }
+
+        let mut log = Self {
+            flash,
+            start: offset,
+            sector_size,
+            sectors: size / sector_size,
+            sector: 0,
+            cursor: None,
+            latest: None,
+        };
+        log.scan()?;
+        Ok(log)
+    }
+
+    /// Load the latest stored value. Returns `None` if nothing valid has been stored yet
+    pub fn load<T: Record>(&mut self) -> Result<Option<Loaded<T>>, Error<F::Error>> {
+        let Some(Location { addr, header }) = self.latest else {
+            return Ok(None);
+        };
+        if header.version > T::VERSION {
+            return Err(Error::UnsupportedVersion(header.version));
+        }
+
+        let mut buffer = [0_u8; MAX_PAYLOAD_LEN];
+        let payload = self.read_payload(addr, &header, &mut buffer)?;
+        T::decode(header.version, payload)
+            .map(|value| {
+                Some(Loaded {
+                    value,
+                    version: header.version,
+                })
+            })
+            .ok_or(Error::Corrupted(header.version))
+    }
+
+    /// Append the value as the new latest record
+    ///
+    /// * `value` - Value to store
+    pub fn save<T: Record>(&mut self, value: &T) -> Result<(), Error<F::Error>> {
+        let mut record = [ERASED; MAX_RECORD_LEN];
+        let (header_bytes, payload) = record.split_at_mut(HEADER_LEN);
+        let len = value
+            .encode(payload)
+            .filter(|len| *len <= MAX_PAYLOAD_LEN)
+            .ok_or(Error::TooLarge)?;
+        let (data, padding) = payload.split_at_mut(len);
+        // Padding keeps the erased state
+        padding.fill(ERASED);
+
+        let len = u16::try_from(len).map_err(|_| Error::TooLarge)?;
+        // 2^32 saves are far beyond the flash endurance, wrapping is not handled
+        let seq = self
+            .latest
+            .map_or(1, |latest| latest.header.seq.wrapping_add(1));
+        let header = Header {
+            version: T::VERSION,
+            len,
+            seq,
+            crc: Header::checksum(T::VERSION, len, seq, data),
+        };
+        header_bytes.copy_from_slice(&header.encode());
+
+        let size = Self::record_size(len);
+        let addr = self.write_position(size)?;
+        let bytes = record
+            .get(..Self::record_size_bytes(len))
+            .ok_or(Error::TooLarge)?;
+        self.flash.write(addr, bytes).map_err(Error::Flash)?;
+        self.cursor = Some(addr + size);
+
+        if !self.crc_matches(addr, &header)? {
+            return Err(Error::Verify);
+        }
+        self.latest = Some(Location { addr, header });
+        Ok(())
+    }
+
+    /// Give the flash driver back
+    pub fn release(self) -> F {
+        self.flash
+    }
+
+    /// Read/write alignment of the flash
+    fn align() -> usize {
+        F::READ_SIZE.max(F::WRITE_SIZE)
+    }
+
+    /// Record size in flash (header + payload + padding), bytes
+    fn record_size_bytes(len: u16) -> usize {
+        (HEADER_LEN + usize::from(len)).next_multiple_of(Self::align())
+    }
+
+    /// Record size in flash as a flash offset
+    #[allow(clippy::cast_possible_truncation)] // bounded by MAX_RECORD_LEN
+    fn record_size(len: u16) -> u32 {
+        Self::record_size_bytes(len) as u32
+    }
+
+    /// Absolute offset of the sector
+    fn sector_start(&self, sector: u32) -> u32 {
+        self.start + sector * self.sector_size
+    }
+
+    /// Find the latest valid record and the position after it
+    fn scan(&mut self) -> Result<(), Error<F::Error>> {
+        for sector in 0..self.sectors {
+            let sector_end = self.sector_start(sector) + self.sector_size;
+            let mut addr = self.sector_start(sector);
+            // `false` if the sector contains anything that is not a record
+            let mut clean = true;
+            let mut latest_here = false;
+
+            while addr + PAYLOAD_OFFSET <= sector_end {
+                let mut bytes = [0_u8; HEADER_LEN];
+                self.flash.read(addr, &mut bytes).map_err(Error::Flash)?;
+                let header = match Header::parse(&bytes) {
+                    Slot::Erased => break,
+                    Slot::Garbage => {
+                        clean = false;
+                        break;
+                    }
+                    Slot::Record(header) => header,
+                };
+                let size = Self::record_size(header.len);
+                if addr + size > sector_end {
+                    clean = false;
+                    break;
+                }
+
+                let newer = self
+                    .latest
+                    .is_none_or(|latest| header.seq > latest.header.seq);
+                // Torn records fail the CRC and are skipped
+                if newer && self.crc_matches(addr, &header)? {
+                    self.latest = Some(Location { addr, header });
+                    latest_here = true;
+                }
+                addr += size;
+            }
+
+            if latest_here {
+                self.sector = sector;
+                self.cursor = clean.then_some(addr);
+            }
+        }
+        Ok(())
+    }
+
+    /// Position for a record of the given size, moves on to the next (erased) sector if needed
+    fn write_position(&mut self, size: u32) -> Result<u32, Error<F::Error>> {
+        if let Some(cursor) = self.cursor {
+            let sector_end = self.sector_start(self.sector) + self.sector_size;
+            if cursor + size <= sector_end && self.is_erased(cursor, size)? {
+                return Ok(cursor);
+            }
+        }
+
+        // The sector holding the latest record is never erased
+        let next = if self.latest.is_some() {
+            (self.sector + 1) % self.sectors
+        } else {
+            self.sector
+        };
+        let start = self.sector_start(next);
+        self.flash
+            .erase(start, start + self.sector_size)
+            .map_err(Error::Flash)?;
+        self.sector = next;
+        self.cursor = Some(start);
+        Ok(start)
+    }
+
+    /// `true` if the flash range has not been written since the erase
+    fn is_erased(&mut self, addr: u32, size: u32) -> Result<bool, Error<F::Error>> {
+        let mut buffer = [0_u8; MAX_RECORD_LEN];
+        let Some(bytes) = usize::try_from(size)
+            .ok()
+            .and_then(|size| buffer.get_mut(..size))
+        else {
+            return Ok(false);
+        };
+        self.flash.read(addr, bytes).map_err(Error::Flash)?;
+        Ok(bytes.iter().all(|byte| *byte == ERASED))
+    }
+
+    /// Read the payload of the record into the buffer
+    fn read_payload<'b>(
+        &mut self,
+        addr: u32,
+        header: &Header,
+        buffer: &'b mut [u8; MAX_PAYLOAD_LEN],
+    ) -> Result<&'b [u8], Error<F::Error>> {
+        // Reads cover the padding to keep the alignment
+        let padded = Self::record_size_bytes(header.len) - HEADER_LEN;
+        let padded_bytes = buffer.get_mut(..padded).ok_or(Error::Geometry)?;
+        self.flash
+            .read(addr + PAYLOAD_OFFSET, padded_bytes)
+            .map_err(Error::Flash)?;
+        buffer.get(..usize::from(header.len)).ok_or(Error::Geometry)
+    }
+
+    /// `true` if the stored record matches its CRC
+    fn crc_matches(&mut self, addr: u32, header: &Header) -> Result<bool, Error<F::Error>> {
+        let mut buffer = [0_u8; MAX_PAYLOAD_LEN];
+        let payload = self.read_payload(addr, header, &mut buffer)?;
+        Ok(Header::checksum(header.version, header.len, header.seq, payload) == header.crc)
+    }
+}
@@ -0,0 +1,338 @@
+//! Record log tests against a RAM-backed NOR flash
+
+#![allow(clippy::indexing_slicing, clippy::cast_possible_truncation)]
+
+use config_store::{Error, Reader, Record, RecordLog, Writer};
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+
+const SECTOR: usize = 4096;
+
+/// RAM flash with NOR semantics: writes can only clear bits, erase sets whole sectors to `0xFF`
+struct RamFlash {
+    memory: Vec<u8>,
+    /// Erase count per sector
+    erases: Vec<u32>,
+    /// Simulated power loss: the write is cut after this many bytes
+    cut_write_after: Option<usize>,
+}
+
+impl RamFlash {
+    fn new(sectors: usize) -> Self {
+        Self {
+            memory: vec![0xFF; sectors * SECTOR],
+            erases: vec![0; sectors],
+            cut_write_after: None,
+        }
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let start = offset as usize;
+        if !start.is_multiple_of(Self::READ_SIZE) || !bytes.len().is_multiple_of(Self::READ_SIZE) {
+            return Err(NorFlashErrorKind::NotAligned);
+        }
+        let source = self
+            .memory
+            .get(start..start + bytes.len())
+            .ok_or(NorFlashErrorKind::OutOfBounds)?;
+        bytes.copy_from_slice(source);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.memory.len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let (from, to) = (from as usize, to as usize);
+        if !from.is_multiple_of(SECTOR) || !to.is_multiple_of(SECTOR) {
+            return Err(NorFlashErrorKind::NotAligned);
+        }
+        self.memory
+            .get_mut(from..to)
+            .ok_or(NorFlashErrorKind::OutOfBounds)?
+            .fill(0xFF);
+        for sector in from / SECTOR..to / SECTOR {
+            self.erases[sector] += 1;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let start = offset as usize;
+        if !start.is_multiple_of(Self::WRITE_SIZE) || !bytes.len().is_multiple_of(Self::WRITE_SIZE)
+        {
+            return Err(NorFlashErrorKind::NotAligned);
+        }
+        let written = self.cut_write_after.take().unwrap_or(bytes.len());
+        let target = self
+            .memory
+            .get_mut(start..start + bytes.len())
+            .ok_or(NorFlashErrorKind::OutOfBounds)?;
+        for (cell, byte) in target.iter_mut().zip(bytes).take(written) {
+            *cell &= byte;
+        }
+        if written < bytes.len() {
+            return Err(NorFlashErrorKind::Other);
+        }
+        Ok(())
+    }
+}
+
+/// First layout: thresholds only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SettingsV1 {
+    long_press_ms: u32,
+    verbose: bool,
+}
+
+impl Record for SettingsV1 {
+    const VERSION: u16 = 1;
+
+    fn encode(&self, payload: &mut [u8]) -> Option<usize> {
+        let mut writer = Writer::new(payload);
+        writer.u32(self.long_press_ms)?.bool(self.verbose)?;
+        Some(writer.len())
+    }
+
+    fn decode(_version: u16, payload: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(payload);
+        Some(Self {
+            long_press_ms: reader.u32()?,
+            verbose: reader.bool()?,
+        })
+    }
+}
+
+/// Second layout: brightness added
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SettingsV2 {
+    long_press_ms: u32,
+    verbose: bool,
+    brightness_pct: u8,
+}
+
+impl Record for SettingsV2 {
+    const VERSION: u16 = 2;
+
+    fn encode(&self, payload: &mut [u8]) -> Option<usize> {
+        let mut writer = Writer::new(payload);
+        writer
+            .u32(self.long_press_ms)?
+            .bool(self.verbose)?
+            .u8(self.brightness_pct)?;
+        Some(writer.len())
+    }
+
+    fn decode(version: u16, payload: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(payload);
+        Some(Self {
+            long_press_ms: reader.u32()?,
+            verbose: reader.bool()?,
+            // Not stored by version 1 - default
+            brightness_pct: if version >= 2 { reader.u8()? } else { 80 },
+        })
+    }
+}
+
+/// Value that does not fit into a record
+struct Oversized;
+
+impl Record for Oversized {
+    const VERSION: u16 = 1;
+
+    fn encode(&self, payload: &mut [u8]) -> Option<usize> {
+        Some(payload.len() + 1)
+    }
+
+    fn decode(_version: u16, _payload: &[u8]) -> Option<Self> {
+        Some(Self)
+    }
+}
+
+fn settings(long_press_ms: u32) -> SettingsV2 {
+    SettingsV2 {
+        long_press_ms,
+        verbose: false,
+        brightness_pct: 50,
+    }
+}
+
+/// Region of 3 sectors after one unrelated sector
+fn mount(flash: RamFlash) -> RecordLog<RamFlash> {
+    RecordLog::new(flash, SECTOR as u32, 3 * SECTOR as u32).expect("valid region")
+}
+
+fn load(log: &mut RecordLog<RamFlash>) -> Option<SettingsV2> {
+    log.load::<SettingsV2>()
+        .expect("readable")
+        .map(|loaded| loaded.value)
+}
+
+#[test]
+fn empty_flash_has_no_value() {
+    let mut log = mount(RamFlash::new(4));
+    assert_eq!(load(&mut log), None);
+}
+
+#[test]
+fn latest_value_survives_remount() {
+    let mut log = mount(RamFlash::new(4));
+    for long_press_ms in [300, 400, 500] {
+        log.save(&settings(long_press_ms)).expect("saved");
+        assert_eq!(load(&mut log), Some(settings(long_press_ms)));
+    }
+
+    let mut log = mount(log.release());
+    assert_eq!(load(&mut log), Some(settings(500)));
+}
+
+#[test]
+fn writes_stay_inside_the_region() {
+    let mut log = mount(RamFlash::new(5));
+    for long_press_ms in 0..2000 {
+        log.save(&settings(long_press_ms)).expect("saved");
+    }
+    let flash = log.release();
+    assert_eq!(flash.erases.first(), Some(&0));
+    assert_eq!(flash.erases.last(), Some(&0));
+    assert!(flash.memory[..SECTOR].iter().all(|byte| *byte == 0xFF));
+    assert!(flash.memory[4 * SECTOR..].iter().all(|byte| *byte == 0xFF));
+}
+
+#[test]
+fn erases_are_spread_over_all_sectors() {
+    let mut log = mount(RamFlash::new(4));
+    for long_press_ms in 0..5000 {
+        log.save(&settings(long_press_ms)).expect("saved");
+    }
+
+    let mut log = mount(log.release());
+    assert_eq!(load(&mut log), Some(settings(4999)));
+    let erases = &log.release().erases[1..];
+    let (min, max) = (erases.iter().min(), erases.iter().max());
+    assert!(
+        min.zip(max).is_some_and(|(min, max)| max - min <= 1),
+        "{erases:?}"
+    );
+    // 5000 records of 24 bytes, 170 records per sector
+    assert!(max.is_some_and(|max| *max <= 10), "{erases:?}");
+}
+
+#[test]
+fn torn_write_keeps_the_previous_value() {
+    let mut log = mount(RamFlash::new(4));
+    log.save(&settings(300)).expect("saved");
+
+    // Power loss in the middle of the payload and in the middle of the header
+    for cut in [20, 6] {
+        let mut flash = log.release();
+        flash.cut_write_after = Some(cut);
+        log = mount(flash);
+        assert_eq!(
+            log.save(&settings(999)),
+            Err(Error::Flash(NorFlashErrorKind::Other))
+        );
+
+        log = mount(log.release());
+        assert_eq!(load(&mut log), Some(settings(300)));
+        log.save(&settings(400))
+            .expect("saved after the power loss");
+        log = mount(log.release());
+        assert_eq!(load(&mut log), Some(settings(400)));
+        log.save(&settings(300)).expect("saved");
+    }
+}
+
+#[test]
+fn torn_write_at_sector_switch_keeps_the_previous_value() {
+    let mut log = mount(RamFlash::new(4));
+    // Fill the first sector: 170 records of 24 bytes
+    for long_press_ms in 0..170 {
+        log.save(&settings(long_press_ms)).expect("saved");
+    }
+
+    let mut flash = log.release();
+    flash.cut_write_after = Some(8);
+    log = mount(flash);
+    assert!(log.save(&settings(999)).is_err());
+
+    log = mount(log.release());
+    assert_eq!(load(&mut log), Some(settings(169)));
+}
+
+#[test]
+fn older_layout_is_migrated() {
+    let mut log = mount(RamFlash::new(4));
+    let old = SettingsV1 {
+        long_press_ms: 700,
+        verbose: true,
+    };
+    log.save(&old).expect("saved");
+
+    let mut log = mount(log.release());
+    let loaded = log.load::<SettingsV2>().expect("readable").expect("stored");
+    assert!(loaded.is_migrated());
+    assert_eq!(loaded.version, 1);
+    assert_eq!(
+        loaded.value,
+        SettingsV2 {
+            long_press_ms: 700,
+            verbose: true,
+            brightness_pct: 80,
+        }
+    );
+
+    log.save(&loaded.value).expect("saved");
+    let loaded = log.load::<SettingsV2>().expect("readable").expect("stored");
+    assert!(!loaded.is_migrated());
+}
+
+#[test]
+fn newer_layout_is_rejected() {
+    let mut log = mount(RamFlash::new(4));
+    log.save(&settings(300)).expect("saved");
+    assert_eq!(log.load::<SettingsV1>(), Err(Error::UnsupportedVersion(2)));
+}
+
+#[test]
+fn foreign_data_is_ignored_and_replaced() {
+    let mut flash = RamFlash::new(4);
+    // e.g. a record of the previous storage format at the region start
+    flash.memory[SECTOR..SECTOR + 13].copy_from_slice(b"1TES\x2c\x01\0\0\xd0\x07\0\0\x01");
+
+    let mut log = mount(flash);
+    assert_eq!(load(&mut log), None);
+    log.save(&settings(300)).expect("saved");
+
+    let mut log = mount(log.release());
+    assert_eq!(load(&mut log), Some(settings(300)));
+}
+
+#[test]
+fn invalid_region_and_value_are_rejected() {
+    let geometry = |offset: u32, size: u32| {
+        RecordLog::new(RamFlash::new(4), offset, size)
+            .err()
+            .is_some_and(|error| error == Error::Geometry)
+    };
+    assert!(geometry(100, 2 * SECTOR as u32));
+    assert!(geometry(0, SECTOR as u32));
+    assert!(geometry(0, 2 * SECTOR as u32 + 100));
+    assert!(geometry(2 * SECTOR as u32, 3 * SECTOR as u32));
+
+    let mut log = mount(RamFlash::new(4));
+    assert_eq!(log.save(&Oversized), Err(Error::TooLarge));
+}
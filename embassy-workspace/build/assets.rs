@@ -0,0 +1,459 @@
+//! Asset pipeline - converts `assets/` into `const` data for the firmware
+//!
+//! `assets/assets.json` lists the assets to convert:
+//!
+//! ```json
+//! {
+//!   "images": [{ "name": "ICON_GEAR", "file": "images/gear.png" }],
+//!   "fonts": [{ "name": "FONT_6X10_CYR", "file": "fonts/fixed_6x10_cyr.bdf", "charsets": ["ascii", "cyrillic"] }]
+//! }
+//! ```
+//!
+//! * PNG images become `ImageRawLE<Rgb565>` constants (alpha is blended over `background`)
+//! * BDF fonts become `MonoFont` constants with a `StrGlyphMapping` of all converted glyphs
+//!
+//! Pixel/glyph data is written next to the generated `assets.rs` and pulled in with `include_bytes!`.
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// Maximal image size - the display resolution
+const MAX_IMAGE_WIDTH: u32 = 240;
+const MAX_IMAGE_HEIGHT: u32 = 135;
+
+/// Maximal glyph cell size
+const MAX_GLYPH_WIDTH: u32 = 32;
+const MAX_GLYPH_HEIGHT: u32 = 32;
+
+/// Glyphs per row in the generated font image
+const GLYPHS_PER_ROW: u32 = 16;
+
+/// Character sets that fonts can be required to cover
+const CHARSETS: &[(&str, &[(char, char)])] = &[
+    ("ascii", &[(' ', '~')]),
+    ("cyrillic", &[('А', 'я'), ('Ё', 'Ё'), ('ё', 'ё')]),
+];
+
+/// Asset manifest - `assets/assets.json`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    images: Vec<ImageAsset>,
+    #[serde(default)]
+    fonts: Vec<FontAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageAsset {
+    /// Constant name
+    name: String,
+    /// PNG file, relative to `assets/`
+    file: String,
+    /// Colour that transparent pixels are blended over, `[r, g, b]`
+    #[serde(default)]
+    background: [u8; 3],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FontAsset {
+    /// Constant name
+    name: String,
+    /// BDF file, relative to `assets/`
+    file: String,
+    /// Character sets the font must cover completely
+    #[serde(default)]
+    charsets: Vec<String>,
+}
+
+/// Convert all assets listed in the manifest and write `$OUT_DIR/assets.rs`
+///
+/// * `assets_dir` - Assets directory
+/// * `out_path` - Output directory
+pub fn generate_assets(assets_dir: &Path, out_path: &Path) -> Result<()> {
+    println!("cargo:rerun-if-changed={}", assets_dir.display());
+
+    let mut code = String::from(
+        "// This file is auto-generated by build.rs from assets/ - DO NOT EDIT\n\n\
+         #[allow(unused_imports)]\n\
+         use embedded_graphics::{\n    \
+             geometry::Size,\n    \
+             image::{ImageRaw, ImageRawLE},\n    \
+             mono_font::{DecorationDimensions, MonoFont, mapping::StrGlyphMapping},\n    \
+             pixelcolor::Rgb565,\n\
+         };\n",
+    );
+
+    let manifest_path = assets_dir.join("assets.json");
+    if manifest_path.exists() {
+        let manifest: Manifest = serde_json::from_str(
+            &fs::read_to_string(&manifest_path)
+                .with_context(|| format!("Failed to read {}", manifest_path.display()))?,
+        )
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+        let mut names = BTreeMap::new();
+        let declared = manifest
+            .images
+            .iter()
+            .map(|image| (&image.name, &image.file))
+            .chain(manifest.fonts.iter().map(|font| (&font.name, &font.file)));
+        for (name, file) in declared {
+            ensure!(
+                is_const_name(name),
+                "{}: `{name}` is not a valid constant name (use UPPER_SNAKE_CASE)",
+                manifest_path.display()
+            );
+            if let Some(other) = names.insert(name, file) {
+                bail!(
+                    "{}: constant `{name}` is declared twice ({other} and {file})",
+                    manifest_path.display()
+                );
+            }
+        }
+
+        for image in &manifest.images {
+            let path = assets_dir.join(&image.file);
+            convert_image(image, &path, out_path, &mut code)
+                .with_context(|| format!("Image asset {}", path.display()))?;
+        }
+        for font in &manifest.fonts {
+            let path = assets_dir.join(&font.file);
+            convert_font(font, &path, out_path, &mut code)
+                .with_context(|| format!("Font asset {}", path.display()))?;
+        }
+    } else {
+        println!("cargo:warning=assets/assets.json not found, no assets generated");
+    }
+
+    fs::write(out_path.join("assets.rs"), code).context("Failed to write assets.rs")?;
+    Ok(())
+}
+
+/// `UPPER_SNAKE_CASE` identifier check
+fn is_const_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Convert a PNG image into little-endian RGB565 data
+fn convert_image(
+    image: &ImageAsset,
+    path: &Path,
+    out_path: &Path,
+    code: &mut String,
+) -> Result<()> {
+    let mut decoder = png::Decoder::new(File::open(path).context("Failed to open")?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().context("Invalid PNG")?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).context("Invalid PNG")?;
+
+    ensure!(
+        info.width <= MAX_IMAGE_WIDTH && info.height <= MAX_IMAGE_HEIGHT,
+        "image is {}x{}, larger than the display ({MAX_IMAGE_WIDTH}x{MAX_IMAGE_HEIGHT})",
+        info.width,
+        info.height
+    );
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => bail!("palette was not expanded"),
+    };
+
+    let [bg_r, bg_g, bg_b] = image.background.map(u32::from);
+    let mut data = Vec::with_capacity((info.width * info.height * 2) as usize);
+    let pixels = buffer
+        .get(..info.buffer_size())
+        .context("PNG frame is truncated")?;
+    for pixel in pixels.chunks_exact(channels) {
+        let (r, g, b, a) = match *pixel {
+            [l] => (l, l, l, 255),
+            [l, a] => (l, l, l, a),
+            [r, g, b] => (r, g, b, 255),
+            [r, g, b, a] => (r, g, b, a),
+            _ => bail!("unexpected pixel size"),
+        };
+        let blend =
+            |c: u8, bg: u32| (u32::from(c) * u32::from(a) + bg * (255 - u32::from(a))) / 255;
+        let (r, g, b) = (blend(r, bg_r), blend(g, bg_g), blend(b, bg_b));
+        let rgb565 = u16::try_from((r >> 3) << 11 | (g >> 2) << 5 | (b >> 3))?;
+        data.extend_from_slice(&rgb565.to_le_bytes());
+    }
+
+    let data_file = format!("{}.rgb565", image.name.to_lowercase());
+    fs::write(out_path.join(&data_file), &data).context("Failed to write image data")?;
+
+    writeln!(
+        code,
+        "\n/// Image `{}` ({}x{})\n\
+         pub const {}: ImageRawLE<'static, Rgb565> =\n    \
+         ImageRaw::new(include_bytes!(concat!(env!(\"OUT_DIR\"), {:?})), {});",
+        image.file,
+        info.width,
+        info.height,
+        image.name,
+        format!("/{data_file}"),
+        info.width
+    )?;
+    Ok(())
+}
+
+/// Glyph parsed from a BDF file
+struct Glyph {
+    /// Bitmap rows, MSB first, `width` bits used
+    rows: Vec<u32>,
+    /// Bounding box: width, height, x offset, y offset
+    bbx: (u32, u32, i32, i32),
+}
+
+/// Font parsed from a BDF file
+struct BdfFont {
+    ascent: i32,
+    descent: i32,
+    /// Advance width shared by all glyphs
+    advance: u32,
+    glyphs: BTreeMap<char, Glyph>,
+}
+
+/// Parse the subset of BDF 2.1 needed for monospace bitmap fonts
+fn parse_bdf(source: &str) -> Result<BdfFont> {
+    let mut ascent = None;
+    let mut descent = None;
+    let mut advance: Option<u32> = None;
+    let mut glyphs = BTreeMap::new();
+
+    let mut lines = source.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("FONT_ASCENT") => ascent = words.next().map(str::parse).transpose()?,
+            Some("FONT_DESCENT") => descent = words.next().map(str::parse).transpose()?,
+            Some("STARTCHAR") => {
+                let name = words.next().unwrap_or_default().to_owned();
+                let start = index + 1;
+                let mut encoding = None;
+                let mut bbx = None;
+                let mut rows = Vec::new();
+                let mut in_bitmap = false;
+                for (_, line) in lines.by_ref() {
+                    let mut words = line.split_whitespace();
+                    let keyword = words.next();
+                    if in_bitmap && keyword != Some("ENDCHAR") {
+                        rows.push(u32::from_str_radix(line.trim(), 16).with_context(|| {
+                            format!("line {start}: glyph `{name}` has a bad bitmap row")
+                        })?);
+                        continue;
+                    }
+                    let numbers = || -> Result<Vec<i32>> {
+                        line.split_whitespace()
+                            .skip(1)
+                            .map(|word| Ok(word.parse()?))
+                            .collect()
+                    };
+                    match keyword {
+                        Some("ENCODING") => encoding = numbers()?.first().copied(),
+                        Some("DWIDTH") => {
+                            let width = numbers()?.first().copied().unwrap_or_default();
+                            let width = u32::try_from(width)?;
+                            match advance {
+                                None => advance = Some(width),
+                                Some(advance) if advance != width => bail!(
+                                    "line {start}: glyph `{name}` is {width} px wide, the font is not monospace ({advance} px)"
+                                ),
+                                Some(_) => {}
+                            }
+                        }
+                        Some("BBX") => {
+                            let values = numbers()?;
+                            if let [w, h, x, y] = *values.as_slice() {
+                                let (w, h) = (u32::try_from(w)?, u32::try_from(h)?);
+                                ensure!(
+                                    w <= MAX_GLYPH_WIDTH && h <= MAX_GLYPH_HEIGHT,
+                                    "line {start}: glyph `{name}` is {w}x{h}, supported sizes are up to {MAX_GLYPH_WIDTH}x{MAX_GLYPH_HEIGHT}"
+                                );
+                                bbx = Some((w, h, x, y));
+                            }
+                        }
+                        Some("BITMAP") => in_bitmap = true,
+                        Some("ENDCHAR") => break,
+                        _ => {}
+                    }
+                }
+
+                // Glyphs without a Unicode encoding (-1) are skipped
+                let Some(encoding) = encoding.and_then(|e| u32::try_from(e).ok()) else {
+                    continue;
+                };
+                let Some(ch) = char::from_u32(encoding) else {
+                    bail!("line {start}: glyph `{name}` has invalid encoding {encoding}");
+                };
+                let bbx =
+                    bbx.with_context(|| format!("line {start}: glyph `{name}` has no BBX"))?;
+                ensure!(
+                    rows.len() == bbx.1 as usize,
+                    "line {start}: glyph `{name}` has {} bitmap rows, BBX says {}",
+                    rows.len(),
+                    bbx.1
+                );
+                // Bitmap rows are padded to whole bytes, align them to the MSB
+                let shift = 32 - bbx.0.div_ceil(8) * 8;
+                let rows = rows
+                    .into_iter()
+                    .map(|row| row.checked_shl(shift).unwrap_or_default())
+                    .collect();
+                glyphs.insert(ch, Glyph { rows, bbx });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(BdfFont {
+        ascent: ascent.context("FONT_ASCENT is missing")?,
+        descent: descent.context("FONT_DESCENT is missing")?,
+        advance: advance.context("font has no glyphs")?,
+        glyphs,
+    })
+}
+
+/// Encode the sorted characters as a `StrGlyphMapping` string (`\0` + start + end for ranges)
+fn glyph_mapping(chars: &[char]) -> String {
+    // Split into runs of consecutive characters
+    let mut runs: Vec<(char, char)> = Vec::new();
+    for &ch in chars {
+        match runs.last_mut() {
+            Some((_, end)) if u32::from(*end) + 1 == u32::from(ch) => *end = ch,
+            _ => runs.push((ch, ch)),
+        }
+    }
+
+    let mut mapping = String::new();
+    for (start, end) in runs {
+        if u32::from(end) - u32::from(start) >= 2 {
+            mapping.extend(['\0', start, end]);
+        } else {
+            mapping.extend(start..=end);
+        }
+    }
+    mapping
+}
+
+/// Convert a BDF font into a `MonoFont` glyph image
+fn convert_font(font: &FontAsset, path: &Path, out_path: &Path, code: &mut String) -> Result<()> {
+    let source = fs::read_to_string(path).context("Failed to read")?;
+    let bdf = parse_bdf(&source)?;
+
+    let width = bdf.advance;
+    let height = u32::try_from(bdf.ascent + bdf.descent).context("negative font height")?;
+    ensure!(
+        (1..=MAX_GLYPH_WIDTH).contains(&width) && (1..=MAX_GLYPH_HEIGHT).contains(&height),
+        "glyph cell is {width}x{height}, supported sizes are up to {MAX_GLYPH_WIDTH}x{MAX_GLYPH_HEIGHT}"
+    );
+
+    for charset in &font.charsets {
+        let Some((_, ranges)) = CHARSETS.iter().find(|(name, _)| name == charset) else {
+            let known: Vec<_> = CHARSETS.iter().map(|(name, _)| *name).collect();
+            bail!(
+                "unknown charset `{charset}`, known charsets: {}",
+                known.join(", ")
+            );
+        };
+        let missing: Vec<String> = ranges
+            .iter()
+            .flat_map(|&(start, end)| start..=end)
+            .filter(|c| !bdf.glyphs.contains_key(c))
+            .map(|c| format!("'{c}' (U+{:04X})", u32::from(c)))
+            .collect();
+        ensure!(
+            missing.is_empty(),
+            "charset `{charset}` is not covered, {} glyphs are missing: {}{}",
+            missing.len(),
+            missing
+                .iter()
+                .take(16)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+            if missing.len() > 16 { ", ..." } else { "" }
+        );
+    }
+    ensure!(
+        bdf.glyphs.contains_key(&'?'),
+        "font has no '?' glyph (used as replacement for unknown characters)"
+    );
+
+    // Glyph image: GLYPHS_PER_ROW glyphs per row, 1 bit per pixel, rows padded to whole bytes
+    let chars: Vec<char> = bdf.glyphs.keys().copied().collect();
+    let glyph_rows = u32::try_from(chars.len())?.div_ceil(GLYPHS_PER_ROW);
+    let image_width = width * GLYPHS_PER_ROW;
+    let stride = image_width.div_ceil(8) as usize;
+    let mut data = vec![0_u8; stride * (glyph_rows * height) as usize];
+
+    for (index, (ch, glyph)) in (0_u32..).zip(&bdf.glyphs) {
+        let (glyph_w, glyph_h, x_offset, y_offset) = glyph.bbx;
+        // Top-left corner of the glyph bitmap inside the cell (baseline is at `ascent`)
+        let left = x_offset;
+        let top = bdf.ascent - y_offset - i32::try_from(glyph_h)?;
+        ensure!(
+            left >= 0
+                && top >= 0
+                && left + i32::try_from(glyph_w)? <= i32::try_from(width)?
+                && top + i32::try_from(glyph_h)? <= i32::try_from(height)?,
+            "glyph '{ch}' (U+{:04X}) does not fit into the {width}x{height} cell",
+            u32::from(*ch)
+        );
+
+        let cell_x = (index % GLYPHS_PER_ROW) * width;
+        let cell_y = (index / GLYPHS_PER_ROW) * height;
+        for (row, bits) in (0_u32..).zip(&glyph.rows) {
+            for column in 0..glyph_w {
+                if bits & (1 << (31 - column)) == 0 {
+                    continue;
+                }
+                let x = (cell_x + left.unsigned_abs() + column) as usize;
+                let y = (cell_y + top.unsigned_abs() + row) as usize;
+                if let Some(byte) = data.get_mut(y * stride + x / 8) {
+                    *byte |= 0x80 >> (x % 8);
+                }
+            }
+        }
+    }
+
+    let data_file = format!("{}.raw", font.name.to_lowercase());
+    fs::write(out_path.join(&data_file), &data).context("Failed to write font data")?;
+
+    let replacement = chars.iter().position(|&c| c == '?').unwrap_or_default();
+    let baseline = bdf.ascent - 1;
+    writeln!(
+        code,
+        "\n/// Font `{file}` ({width}x{height}, {count} glyphs)\n\
+         pub const {name}: MonoFont<'static> = MonoFont {{\n    \
+             image: ImageRaw::new(include_bytes!(concat!(env!(\"OUT_DIR\"), {data:?})), {image_width}),\n    \
+             glyph_mapping: &StrGlyphMapping::new({mapping:?}, {replacement}),\n    \
+             character_size: Size::new({width}, {height}),\n    \
+             character_spacing: 0,\n    \
+             baseline: {baseline},\n    \
+             underline: DecorationDimensions::new({underline}, 1),\n    \
+             strikethrough: DecorationDimensions::new({strikethrough}, 1),\n\
+         }};",
+        file = font.file,
+        count = chars.len(),
+        name = font.name,
+        data = format!("/{data_file}"),
+        mapping = glyph_mapping(&chars),
+        underline = baseline + 2,
+        strikethrough = height / 2,
+    )?;
+    Ok(())
+}
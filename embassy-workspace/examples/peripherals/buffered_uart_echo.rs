@@ -0,0 +1,97 @@
+//! Buffered UART on the ESP32-S3 - the same `buffered-uart` driver as on the ESP32-C3
+//! (`wokwi-interaction/examples/serial/rx_idle_irq.rs`), built with the `esp32s3` feature.
+//!
+//! UART1 on the Grove port: TX - G2, RX - G1, 115200 8N1. Received bytes are echoed back,
+//! bytes lost to a full ring or to line errors are logged.
+
+#![no_std]
+#![no_main]
+#![deny(
+    clippy::mem_forget,
+    reason = "mem::forget is generally not safe to do with esp_hal types, especially those \
+    holding buffers for the duration of a data transfer."
+)]
+
+use buffered_uart::{BufferedRx, BufferedTx, UartState};
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_io_async::{Read, Write};
+use esp_hal::clock::CpuClock;
+use esp_hal::handler;
+use esp_hal::timer::timg::TimerGroup;
+use esp_hal::uart::{Config, RxConfig, Uart};
+use log::{info, warn};
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
+
+esp_bootloader_esp_idf::esp_app_desc!();
+
+/// Receive ring size, bytes
+const RX_SIZE: usize = 256;
+/// Transmit ring size, bytes
+const TX_SIZE: usize = 256;
+
+// Прерывание складывает принятые байты в кольцевой буфер, задача читает его асинхронно
+static UART: UartState<RX_SIZE, TX_SIZE> = UartState::new();
+
+#[handler]
+fn uart_irq_handler() {
+    // Чтение RX FIFO и дозапись TX FIFO
+    UART.on_interrupt();
+}
+
+#[esp_rtos::main]
+async fn main(spawner: Spawner) -> ! {
+    esp_println::logger::init_logger_from_env();
+
+    let config = esp_hal::Config::default().with_cpu_clock(CpuClock::max());
+    let peripherals = esp_hal::init(config);
+
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_rtos::start(timg0.timer0);
+
+    // Прерывание по заполнению FIFO на треть или по паузе в приёме
+    let uart_config = Config::default().with_baudrate(115_200).with_rx(
+        RxConfig::default()
+            .with_fifo_full_threshold(32)
+            .with_timeout(10),
+    );
+    let uart = Uart::new(peripherals.UART1, uart_config)
+        .expect("Failed to init UART1")
+        .with_tx(peripherals.GPIO2)
+        .with_rx(peripherals.GPIO1);
+    let (rx, tx) = UART
+        .init(uart, uart_irq_handler)
+        .expect("UART state is already in use");
+
+    spawner.spawn(echo_task(rx, tx)).unwrap();
+
+    info!("Buffered UART echo is running");
+    loop {
+        Timer::after(Duration::from_millis(1000)).await;
+    }
+}
+
+/// Async task - echo the received bytes and report the lost ones
+///
+/// * `rx` - Reading half of the UART
+/// * `tx` - Writing half of the UART
+#[embassy_executor::task]
+async fn echo_task(mut rx: BufferedRx<RX_SIZE, TX_SIZE>, mut tx: BufferedTx<RX_SIZE, TX_SIZE>) {
+    let mut buffer = [0_u8; 64];
+    let (mut overflows, mut rx_errors) = (0, 0);
+    loop {
+        // Ошибок чтения и записи нет: ошибки линии только считаются
+        let Ok(len) = rx.read(&mut buffer).await;
+        let Ok(()) = tx.write_all(buffer.get(..len).unwrap_or_default()).await;
+
+        // Счётчики растут только при потерях, сообщение - только об изменении
+        if rx.overflows() != overflows || rx.rx_errors() != rx_errors {
+            (overflows, rx_errors) = (rx.overflows(), rx.rx_errors());
+            warn!("UART lost bytes: {overflows} ring overflows, {rx_errors} line errors");
+        }
+    }
+}
@@ -0,0 +1,8 @@
+//! Assets module - fonts and images converted from `assets/` by the build script
+//!
+//! See `build/assets.rs` for the manifest format and the validation rules.
+
+#![allow(dead_code)] // not every asset is used by the firmware at any given time
+
+// Включаем сгенерированные ассеты
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
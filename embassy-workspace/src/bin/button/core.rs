@@ -1,7 +1,7 @@
 //! Button module
 
 use super::messaging::{BUTTON_PUBSUB_CHANNEL, ButtonMessage, PressType};
-use crate::AppConfig;
+use crate::settings;
 use embassy_futures::select::{Either, select};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::pubsub::{Error, Publisher};
@@ -126,11 +126,6 @@ impl UserButton<'_> {
             return;
         }
 
-        let button_long_press_release_threshold =
-            Duration::from_millis(AppConfig::BTN_LONG_PRESS_THRESHOLD_MS.into());
-        let button_long_press_hold_threshold =
-            Duration::from_millis(AppConfig::BTN_LONG_HOLD_THRESHOLD_MS.into());
-
         let mut button_down_press_timestamp: Instant;
         let mut button_up_release_timestamp: Instant;
 
@@ -140,6 +135,13 @@ impl UserButton<'_> {
             // Wait for button down press
             self.debounce_high_to_low(id).await;
 
+            // Thresholds can be changed at runtime (menu), re-read them for every press
+            let current_settings = settings::current();
+            let button_long_press_release_threshold =
+                Duration::from_millis(current_settings.btn_long_press_threshold_ms.into());
+            let button_long_press_hold_threshold =
+                Duration::from_millis(current_settings.btn_long_hold_threshold_ms.into());
+
             button_down_press_timestamp = Instant::now();
             warn!(
                 "Button ID {} down pressed! - Timestamp: {:?}ms",
@@ -0,0 +1,152 @@
+//! Display module - Backlight PWM driver
+//!
+//! The backlight LED is driven by the LEDC peripheral, the hardware fader takes care of
+//! smooth transitions between brightness levels.
+
+use crate::AppConfig;
+use crate::settings;
+use embassy_time::Timer;
+use esp_hal::gpio::interconnect::PeripheralOutput;
+use esp_hal::ledc::channel::{self, ChannelIFace};
+use esp_hal::ledc::timer::{self, TimerIFace};
+use esp_hal::ledc::{LSGlobalClkSource, Ledc, LowSpeed};
+use esp_hal::peripherals::LEDC;
+use esp_hal::time::Rate;
+use log::debug;
+use static_cell::StaticCell;
+
+/// PWM frequency, high enough to avoid visible flicker
+const PWM_FREQUENCY_KHZ: u32 = 24;
+
+/// Fade duration when the backlight is turned on (wake up is expected to be fast)
+pub const FADE_IN_MS: u16 = 250;
+
+/// Fade duration when the backlight is dimmed or turned off
+pub const FADE_OUT_MS: u16 = 1000;
+
+/// LEDC timer shared by the backlight channel, must outlive the channel
+static BACKLIGHT_TIMER: StaticCell<timer::Timer<'static, LowSpeed>> = StaticCell::new();
+
+/// Backlight brightness levels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacklightLevel {
+    /// Normal brightness
+    Full,
+    /// Reduced brightness after the idle timeout
+    Dim,
+    /// Backlight is turned off
+    Off,
+}
+
+impl BacklightLevel {
+    /// PWM duty cycle of the level, %
+    pub fn duty_pct(self) -> u8 {
+        // Brightness can be changed at runtime (menu)
+        let full = settings::current().backlight_brightness_pct;
+        match self {
+            BacklightLevel::Full => full,
+            BacklightLevel::Dim => AppConfig::BACKLIGHT_DIM_BRIGHTNESS_PCT.min(full),
+            BacklightLevel::Off => 0,
+        }
+    }
+}
+
+/// Backlight errors
+#[derive(Debug)]
+pub enum BacklightError {
+    /// LEDC timer configuration failed
+    Timer(timer::Error),
+    /// LEDC channel configuration or fade failed
+    Channel(channel::Error),
+}
+
+/// Backlight controller
+pub struct Backlight {
+    /// LEDC channel connected to the backlight pin
+    channel: channel::Channel<'static, LowSpeed>,
+    /// Current duty cycle, %
+    duty_pct: u8,
+}
+
+impl Backlight {
+    /// Backlight constructor - the backlight starts turned off
+    ///
+    /// * `ledc` - LEDC peripheral
+    /// * `pin` - Backlight pin
+    pub fn new(
+        ledc: LEDC<'static>,
+        pin: impl PeripheralOutput<'static>,
+    ) -> Result<Self, BacklightError> {
+        let mut ledc = Ledc::new(ledc);
+        ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+        let timer = BACKLIGHT_TIMER.init(ledc.timer::<LowSpeed>(timer::Number::Timer0));
+        timer
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty8Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: Rate::from_khz(PWM_FREQUENCY_KHZ),
+            })
+            .map_err(BacklightError::Timer)?;
+        let timer: &'static timer::Timer<'static, LowSpeed> = timer;
+
+        let mut channel = ledc.channel(channel::Number::Channel0, pin);
+        channel
+            .configure(channel::config::Config {
+                timer,
+                duty_pct: 0,
+                drive_mode: esp_hal::gpio::DriveMode::PushPull,
+            })
+            .map_err(BacklightError::Channel)?;
+
+        Ok(Self {
+            channel,
+            duty_pct: 0,
+        })
+    }
+
+    /// Switch to the brightness level immediately
+    ///
+    /// * `level` - Brightness level
+    pub fn set(&mut self, level: BacklightLevel) -> Result<(), BacklightError> {
+        let target = level.duty_pct();
+        self.channel
+            .set_duty(target)
+            .map_err(BacklightError::Channel)?;
+        self.duty_pct = target;
+        Ok(())
+    }
+
+    /// Fade to the brightness level and wait for the fade to finish
+    ///
+    /// * `level` - Brightness level
+    /// * `duration_ms` - Fade duration
+    pub async fn fade_to(
+        &mut self,
+        level: BacklightLevel,
+        duration_ms: u16,
+    ) -> Result<(), BacklightError> {
+        let target = level.duty_pct();
+        if target == self.duty_pct {
+            return Ok(());
+        }
+        debug!(
+            "Backlight fade {}% -> {}% in {} ms",
+            self.duty_pct, target, duration_ms
+        );
+
+        if let Err(e) = self
+            .channel
+            .start_duty_fade(self.duty_pct, target, duration_ms)
+        {
+            // Fade parameters out of the hardware range (e.g. tiny step) - jump directly
+            debug!("Backlight fade rejected ({e:?}), switching immediately");
+            return self.set(level);
+        }
+        while self.channel.is_duty_fade_running() {
+            Timer::after_millis(10).await;
+        }
+        self.duty_pct = target;
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! Display module - Text console (terminal emulator) widget
+//!
+//! Fixed-width text grid with line wrapping, scrollback and a subset of ANSI escape sequences:
+//!
+//!   - `ESC[<n>m` - SGR: `0` reset, `1`/`22` bold on/off, `30..=37`/`90..=97` foreground, `39` default
+//!   - `ESC[K` - clear to the end of the line
+//!   - `ESC[2J` - clear the whole console
+
+use super::core::FrameBuffer;
+use embedded_graphics::{
+    mono_font::{MonoTextStyle, iso_8859_5::FONT_6X10},
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use heapless::{String, Vec};
+
+/// Console width, characters (240px / 6px)
+pub const CONSOLE_COLS: usize = 40;
+/// Visible console height, lines (135px / 10px)
+pub const CONSOLE_ROWS: usize = 13;
+/// Number of lines kept in memory (including visible ones)
+const SCROLLBACK_LINES: usize = 48;
+/// Glyph size of [`FONT_6X10`], px
+const GLYPH_WIDTH: i32 = 6;
+const GLYPH_HEIGHT: i32 = 10;
+/// Tab stop width, characters
+const TAB_WIDTH: usize = 4;
+/// Index of the default foreground color in [`PALETTE`]
+const DEFAULT_COLOR: u8 = 16;
+
+/// ANSI palette: 8 normal, 8 bright colors and the default foreground
+const PALETTE: [Rgb565; 17] = [
+    Rgb565::BLACK,
+    Rgb565::new(22, 0, 0),
+    Rgb565::new(0, 44, 0),
+    Rgb565::new(22, 44, 0),
+    Rgb565::new(0, 0, 22),
+    Rgb565::new(22, 0, 22),
+    Rgb565::new(0, 44, 22),
+    Rgb565::new(24, 48, 24),
+    Rgb565::new(12, 24, 12),
+    Rgb565::RED,
+    Rgb565::GREEN,
+    Rgb565::YELLOW,
+    Rgb565::new(10, 20, 31),
+    Rgb565::MAGENTA,
+    Rgb565::CYAN,
+    Rgb565::WHITE,
+    Rgb565::new(26, 52, 26),
+];
+
+/// Escape sequence parser state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParserState {
+    /// Plain text
+    Text,
+    /// `ESC` received
+    Escape,
+    /// `ESC[` received, collecting numeric parameters
+    Csi,
+}
+
+/// Scrollback navigation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleScroll {
+    /// One line towards older output
+    LineUp,
+    /// One line towards newer output
+    LineDown,
+    /// One screen towards older output
+    PageUp,
+    /// Back to the newest output
+    Bottom,
+}
+
+/// Text console with scrollback
+pub struct Console {
+    /// Characters, ring buffer of lines
+    chars: [[char; CONSOLE_COLS]; SCROLLBACK_LINES],
+    /// Foreground color index of every character
+    colors: [[u8; CONSOLE_COLS]; SCROLLBACK_LINES],
+    /// Ring index of the line being written
+    head: usize,
+    /// Number of lines in use
+    filled: usize,
+    /// Cursor column in the line being written
+    column: usize,
+    /// Current foreground color index
+    color: u8,
+    /// Bold (bright) attribute
+    bold: bool,
+    /// Escape sequence parser state
+    state: ParserState,
+    /// Collected escape sequence parameters
+    params: Vec<u16, 4>,
+    /// Incomplete UTF-8 sequence
+    utf8: Vec<u8, 4>,
+    /// Number of lines the view is scrolled up from the bottom
+    scroll: usize,
+}
+
+impl Console {
+    /// Empty console constructor
+    pub const fn new() -> Self {
+        Self {
+            chars: [[' '; CONSOLE_COLS]; SCROLLBACK_LINES],
+            colors: [[DEFAULT_COLOR; CONSOLE_COLS]; SCROLLBACK_LINES],
+            head: 0,
+            filled: 1,
+            column: 0,
+            color: DEFAULT_COLOR,
+            bold: false,
+            state: ParserState::Text,
+            params: Vec::new(),
+            utf8: Vec::new(),
+            scroll: 0,
+        }
+    }
+
+    /// Feed raw (UTF-8, possibly split in the middle of a character) output into the console
+    ///
+    /// * `bytes` - Console output
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.utf8.is_empty() && byte.is_ascii() {
+                self.write_char(char::from(byte));
+                continue;
+            }
+
+            if self.utf8.push(byte).is_err() {
+                // Invalid sequence - drop it
+                self.utf8.clear();
+                continue;
+            }
+            match core::str::from_utf8(&self.utf8) {
+                Ok(decoded) => {
+                    let decoded = decoded.chars().next();
+                    self.utf8.clear();
+                    if let Some(ch) = decoded {
+                        self.write_char(ch);
+                    }
+                }
+                Err(e) if e.error_len().is_some() => self.utf8.clear(),
+                Err(_) => {} // Wait for the rest of the character
+            }
+        }
+    }
+
+    /// Feed a single character into the console
+    fn write_char(&mut self, ch: char) {
+        match self.state {
+            ParserState::Text => self.put_char(ch),
+            ParserState::Escape => {
+                self.state = if ch == '[' {
+                    self.params.clear();
+                    self.params.push(0).ok();
+                    ParserState::Csi
+                } else {
+                    ParserState::Text
+                };
+            }
+            ParserState::Csi => match ch {
+                '0'..='9' => {
+                    let digit = ch.to_digit(10).and_then(|d| u16::try_from(d).ok());
+                    if let (Some(param), Some(digit)) = (self.params.last_mut(), digit) {
+                        *param = param.saturating_mul(10).saturating_add(digit);
+                    }
+                }
+                ';' => {
+                    self.params.push(0).ok();
+                }
+                _ => {
+                    self.execute_csi(ch);
+                    self.state = ParserState::Text;
+                }
+            },
+        }
+    }
+
+    /// Execute a complete `ESC[...<final>` sequence
+    fn execute_csi(&mut self, final_char: char) {
+        match final_char {
+            'm' => {
+                for index in 0..self.params.len() {
+                    let Some(&param) = self.params.get(index) else {
+                        break;
+                    };
+                    self.apply_sgr(param);
+                }
+            }
+            'K' => {
+                let (column, line) = (self.column, self.head);
+                self.clear_line(line, column);
+            }
+            'J' if self.params.first() == Some(&2) => {
+                for line in 0..SCROLLBACK_LINES {
+                    self.clear_line(line, 0);
+                }
+                self.filled = 1;
+                self.column = 0;
+                self.scroll = 0;
+            }
+            _ => {} // Unsupported sequence
+        }
+    }
+
+    /// Apply a single SGR (Select Graphic Rendition) parameter
+    fn apply_sgr(&mut self, param: u16) {
+        match param {
+            0 => {
+                self.color = DEFAULT_COLOR;
+                self.bold = false;
+            }
+            1 => self.bold = true,
+            22 => self.bold = false,
+            30..=37 => self.color = u8::try_from(param - 30).unwrap_or(DEFAULT_COLOR),
+            39 => self.color = DEFAULT_COLOR,
+            90..=97 => self.color = u8::try_from(param - 90 + 8).unwrap_or(DEFAULT_COLOR),
+            _ => {}
+        }
+    }
+
+    /// Put a printable or control character at the cursor position
+    fn put_char(&mut self, ch: char) {
+        match ch {
+            '\x1b' => self.state = ParserState::Escape,
+            '\n' => self.new_line(),
+            '\r' => self.column = 0,
+            '\t' => {
+                let spaces = TAB_WIDTH - self.column % TAB_WIDTH;
+                for _ in 0..spaces {
+                    self.put_char(' ');
+                }
+            }
+            '\x08' => self.column = self.column.saturating_sub(1),
+            ch if ch.is_control() => {}
+            ch => {
+                if self.column >= CONSOLE_COLS {
+                    // Wrap long lines
+                    self.new_line();
+                }
+                let color = if self.bold && self.color < 8 {
+                    self.color + 8
+                } else {
+                    self.color
+                };
+                let (line, column) = (self.head, self.column);
+                if let Some(cell) = self.chars.get_mut(line).and_then(|l| l.get_mut(column)) {
+                    *cell = ch;
+                }
+                if let Some(cell) = self.colors.get_mut(line).and_then(|l| l.get_mut(column)) {
+                    *cell = color;
+                }
+                self.column += 1;
+            }
+        }
+    }
+
+    /// Start a new line, the oldest one is dropped when the scrollback is full
+    fn new_line(&mut self) {
+        self.head = (self.head + 1) % SCROLLBACK_LINES;
+        self.clear_line(self.head, 0);
+        self.filled = (self.filled + 1).min(SCROLLBACK_LINES);
+        self.column = 0;
+
+        // Keep the scrolled view in place while new output arrives
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.max_scroll());
+        }
+    }
+
+    /// Clear a line starting from the specified column
+    fn clear_line(&mut self, line: usize, from_column: usize) {
+        if let Some(chars) = self.chars.get_mut(line) {
+            chars.iter_mut().skip(from_column).for_each(|c| *c = ' ');
+        }
+        if let Some(colors) = self.colors.get_mut(line) {
+            colors
+                .iter_mut()
+                .skip(from_column)
+                .for_each(|c| *c = DEFAULT_COLOR);
+        }
+    }
+
+    /// Maximal scroll offset
+    fn max_scroll(&self) -> usize {
+        self.filled.saturating_sub(CONSOLE_ROWS)
+    }
+
+    /// Scroll the view through the scrollback
+    ///
+    /// * `scroll` - Scroll direction and amount
+    pub fn scroll(&mut self, scroll: ConsoleScroll) {
+        self.scroll = match scroll {
+            ConsoleScroll::LineUp => self.scroll + 1,
+            ConsoleScroll::LineDown => self.scroll.saturating_sub(1),
+            ConsoleScroll::PageUp => self.scroll + CONSOLE_ROWS,
+            ConsoleScroll::Bottom => 0,
+        }
+        .min(self.max_scroll());
+    }
+
+    /// Draw visible lines
+    ///
+    /// * `fb` - Framebuffer to draw on
+    pub fn draw(&self, fb: &mut FrameBuffer<'_>) -> Result<(), core::convert::Infallible> {
+        fb.clear(Rgb565::BLACK)?;
+
+        for row in 0..CONSOLE_ROWS {
+            // Distance (in lines) from the newest line
+            let age = CONSOLE_ROWS - 1 - row + self.scroll;
+            if age >= self.filled {
+                continue;
+            }
+            let line = (self.head + SCROLLBACK_LINES - age) % SCROLLBACK_LINES;
+            let (Some(chars), Some(colors)) = (self.chars.get(line), self.colors.get(line)) else {
+                continue;
+            };
+
+            // Draw runs of characters with the same color at once
+            let top = i32::try_from(row).unwrap_or_default() * GLYPH_HEIGHT;
+            let mut run: String<{ CONSOLE_COLS * 4 }> = String::new();
+            let mut run_start = 0;
+            let mut run_color = colors.first().copied().unwrap_or(DEFAULT_COLOR);
+
+            for (column, (&ch, &color)) in chars.iter().zip(colors.iter()).enumerate() {
+                if color != run_color {
+                    draw_run(fb, &run, run_start, top, run_color)?;
+                    run.clear();
+                    run_start = column;
+                    run_color = color;
+                }
+                run.push(ch).ok();
+            }
+            draw_run(fb, run.trim_end(), run_start, top, run_color)?;
+        }
+        Ok(())
+    }
+}
+
+/// Draw a run of characters of the same color
+fn draw_run(
+    fb: &mut FrameBuffer<'_>,
+    text: &str,
+    column: usize,
+    top: i32,
+    color: u8,
+) -> Result<(), core::convert::Infallible> {
+    if text.trim().is_empty() {
+        return Ok(());
+    }
+    let color = PALETTE
+        .get(usize::from(color))
+        .copied()
+        .unwrap_or(Rgb565::WHITE);
+    let left = i32::try_from(column).unwrap_or_default() * GLYPH_WIDTH;
+    Text::with_baseline(
+        text,
+        Point::new(left, top),
+        MonoTextStyle::new(&FONT_6X10, color),
+        Baseline::Top,
+    )
+    .draw(fb)?;
+    Ok(())
+}
@@ -1 +1,79 @@
-//TODO: RATATUI
+//! Display module - Task manager module
+use super::console::Console;
+use super::core::{DisplayController, DisplaySpiDevice};
+use super::messaging::{CONSOLE_PIPE, DISPLAY_CHANNEL, DISPLAY_READY_SIGNAL, DisplayCommand};
+use super::render;
+use super::screenshot;
+use embassy_futures::select::{Either, select};
+use esp_hal::gpio::Output;
+use log::{error, info};
+use static_cell::StaticCell;
+
+static CONSOLE: StaticCell<Console> = StaticCell::new();
+
+/// Async task - Display
+/// Render screens requested through [`DISPLAY_CHANNEL`] and collect console output
+///
+/// * `spi_device` - SPI device of the display
+/// * `data_pin` - Data/command select pin
+/// * `reset_pin` - Reset pin
+#[embassy_executor::task]
+pub async fn start_display(
+    spi_device: DisplaySpiDevice,
+    data_pin: Output<'static>,
+    reset_pin: Output<'static>,
+) {
+    let mut display = DisplayController::new(spi_device, data_pin, reset_pin)
+        .await
+        .expect("Failed to init display!");
+    let console = CONSOLE.init_with(Console::new);
+    info!("Running Display async task ...");
+
+    // Signal to system that display is ready to be used
+    DISPLAY_READY_SIGNAL.signal(true);
+
+    let mut screen = DisplayCommand::Idle;
+    let mut console_chunk = [0_u8; 128];
+    loop {
+        let mut fb = display.frame();
+        let Ok(()) = (match &screen {
+            DisplayCommand::Menu(view) => render::draw_menu(&mut fb, view),
+            DisplayCommand::Console | DisplayCommand::ConsoleScroll(_) => console.draw(&mut fb),
+            DisplayCommand::Idle | DisplayCommand::Screenshot => render::draw_idle(&mut fb),
+        });
+
+        if let Err(e) = display.flush().await {
+            error!("Failed to update the framebuffer: {e:?}");
+        }
+
+        // Wait for something that changes the shown screen
+        loop {
+            match select(
+                DISPLAY_CHANNEL.receive(),
+                CONSOLE_PIPE.read(&mut console_chunk),
+            )
+            .await
+            {
+                Either::First(DisplayCommand::Screenshot) => {
+                    // Framebuffer still holds the last flushed frame
+                    screenshot::dump_serial(display.frame_bytes());
+                }
+                Either::First(DisplayCommand::ConsoleScroll(scroll)) => {
+                    console.scroll(scroll);
+                    screen = DisplayCommand::Console;
+                    break;
+                }
+                Either::First(command) => {
+                    screen = command;
+                    break;
+                }
+                Either::Second(len) => {
+                    console.write_bytes(console_chunk.get(..len).unwrap_or_default());
+                    if matches!(screen, DisplayCommand::Console) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
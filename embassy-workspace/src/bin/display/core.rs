@@ -1,16 +1,113 @@
-use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex};
-use embassy_sync::mutex::Mutex;
-use embedded_graphics::{
-    pixelcolor::Rgb565,
-    prelude::*,
-    primitives::{Primitive, PrimitiveStyle},
-};
+//! Display module - ST7789 driver and framebuffer
+
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_graphics::pixelcolor::Rgb565;
 use esp_hal::Async;
-use esp_hal::dma::{DmaRxBuf, DmaTxBuf};
-use esp_hal::spi::{
-    Mode,
-    master::{Config, Spi, SpiDmaBus},
+use esp_hal::gpio::Output;
+use esp_hal::spi::master::SpiDmaBus;
+use lcd_async::{
+    Builder, interface,
+    models::ST7789,
+    options::{ColorInversion, Orientation, Rotation},
+    raw_framebuf::RawFrameBuf,
 };
 use static_cell::StaticCell;
 
-//TODO: (1) basic initialization
+/// Display width, px
+pub const WIDTH: u16 = 240;
+/// Display height, px
+pub const HEIGHT: u16 = 135;
+/// RGB565 = 2 bytes per pixel
+const PIXEL_SIZE: usize = 2;
+/// Framebuffer size, bytes
+pub const FRAME_SIZE: usize = (WIDTH as usize) * (HEIGHT as usize) * PIXEL_SIZE;
+
+// Custom type aliases
+pub type DisplaySpiDevice =
+    SpiDevice<'static, NoopRawMutex, SpiDmaBus<'static, Async>, Output<'static>>;
+type LcdDisplay = lcd_async::Display<
+    interface::SpiInterface<DisplaySpiDevice, Output<'static>>,
+    ST7789,
+    Output<'static>,
+>;
+/// Framebuffer used for drawing
+pub type FrameBuffer<'a> = RawFrameBuf<Rgb565, &'a mut [u8]>;
+
+static FRAME_BUFFER: StaticCell<[u8; FRAME_SIZE]> = StaticCell::new();
+
+/// Display errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayError {
+    /// Controller initialization failed
+    Init,
+    /// Framebuffer transfer failed
+    Transfer,
+}
+
+/// Handle for the ST7789 display with its framebuffer
+pub struct DisplayController {
+    /// Display driver
+    display: LcdDisplay,
+    /// Framebuffer memory
+    frame_buffer: &'static mut [u8; FRAME_SIZE],
+}
+
+impl DisplayController {
+    /// Display constructor - resets and initializes the controller.
+    /// Can be called only once (framebuffer is statically allocated)
+    ///
+    /// * `spi_device` - SPI device of the display
+    /// * `data_pin` - Data/command select pin
+    /// * `reset_pin` - Reset pin
+    pub async fn new(
+        spi_device: DisplaySpiDevice,
+        data_pin: Output<'static>,
+        reset_pin: Output<'static>,
+    ) -> Result<Self, DisplayError> {
+        let di = interface::SpiInterface::new(spi_device, data_pin);
+        let mut delay = embassy_time::Delay;
+
+        let display = Builder::new(ST7789, di)
+            .reset_pin(reset_pin)
+            .display_size(HEIGHT, WIDTH)
+            .orientation(Orientation {
+                rotation: Rotation::Deg90,
+                mirrored: false,
+            })
+            .display_offset(52, 40)
+            .invert_colors(ColorInversion::Inverted)
+            .init(&mut delay)
+            .await
+            .map_err(|_| DisplayError::Init)?;
+
+        let frame_buffer = FRAME_BUFFER.init_with(|| [0; FRAME_SIZE]);
+
+        Ok(Self {
+            display,
+            frame_buffer,
+        })
+    }
+
+    /// Framebuffer to draw the next frame on
+    pub fn frame(&mut self) -> FrameBuffer<'_> {
+        RawFrameBuf::<Rgb565, _>::new(
+            self.frame_buffer.as_mut_slice(),
+            WIDTH.into(),
+            HEIGHT.into(),
+        )
+    }
+
+    /// Raw framebuffer content (RGB565 big-endian, as sent to the display)
+    pub fn frame_bytes(&self) -> &[u8] {
+        self.frame_buffer.as_slice()
+    }
+
+    /// Send the framebuffer data to the display
+    pub async fn flush(&mut self) -> Result<(), DisplayError> {
+        self.display
+            .show_raw_data(0, 0, WIDTH, HEIGHT, self.frame_buffer)
+            .await
+            .map_err(|_| DisplayError::Transfer)
+    }
+}
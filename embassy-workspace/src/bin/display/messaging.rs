@@ -0,0 +1,34 @@
+//! Display module - Messaging - Channel
+
+use super::console::ConsoleScroll;
+use crate::menu::MenuView;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::pipe::Pipe;
+use embassy_sync::signal::Signal;
+
+/// Commands for the display task
+#[derive(Debug, Clone)]
+pub enum DisplayCommand {
+    /// Idle screen
+    Idle,
+    /// Menu screen
+    Menu(MenuView),
+    /// Text console screen
+    Console,
+    /// Scroll the text console
+    ConsoleScroll(ConsoleScroll),
+    /// Stream the shown screen over serial, the screen itself is not changed
+    Screenshot,
+}
+
+/// Display command channel
+/// 2 total capacity/messages, any number of senders
+pub static DISPLAY_CHANNEL: Channel<CriticalSectionRawMutex, DisplayCommand, 2> = Channel::new();
+
+/// Text console output (UTF-8 with ANSI escape sequences)
+/// Written by the logger, consumed by the display task even when the console is not shown
+pub static CONSOLE_PIPE: Pipe<CriticalSectionRawMutex, 1024> = Pipe::new();
+
+/// A signal / flag that signals that the display system ready
+pub static DISPLAY_READY_SIGNAL: Signal<CriticalSectionRawMutex, bool> = Signal::new();
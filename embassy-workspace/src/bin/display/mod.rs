@@ -1,2 +1,15 @@
+mod backlight;
+mod console;
 mod consumer_loop;
 mod core;
+mod messaging;
+mod power_loop;
+mod render;
+mod screenshot;
+
+// Public re-export of specifics that are available outside of module
+pub use backlight::Backlight;
+pub use console::ConsoleScroll;
+pub use consumer_loop::start_display;
+pub use messaging::{CONSOLE_PIPE, DISPLAY_CHANNEL, DISPLAY_READY_SIGNAL, DisplayCommand};
+pub use power_loop::start_backlight;
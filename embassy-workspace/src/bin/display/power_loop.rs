@@ -0,0 +1,80 @@
+//! Display module - Backlight power management task
+use super::backlight::{Backlight, BacklightLevel, FADE_IN_MS, FADE_OUT_MS};
+use crate::button::BUTTON_PUBSUB_CHANNEL;
+use crate::keyboard::KEYBOARD_PUBSUB_CHANNEL;
+use crate::manager::SYSTEM_READY_PUBSUB_CHANNEL;
+use crate::settings;
+use embassy_futures::select::{Either3, select3};
+use embassy_time::{Duration, Timer};
+use log::{error, info};
+
+/// Async task - Backlight
+/// Dim the backlight after the dim timeout without user activity, turn it off after the off
+/// timeout (both from the runtime settings) and wake it up on any button or keyboard event
+///
+/// * `backlight` - Backlight controller
+#[embassy_executor::task]
+pub async fn start_backlight(mut backlight: Backlight) {
+    let mut button_subscriber = BUTTON_PUBSUB_CHANNEL
+        .subscriber()
+        .expect("Backlight: Failed to subscribe to button channel!");
+    let mut keyboard_subscriber = KEYBOARD_PUBSUB_CHANNEL
+        .subscriber()
+        .expect("Backlight: Failed to subscribe to keyboard channel!");
+    info!("Running Backlight async task ...");
+
+    // Keep the backlight off until the first frame is on the screen
+    let mut system_ready_message = SYSTEM_READY_PUBSUB_CHANNEL
+        .subscriber()
+        .expect("Backlight: Failed to subscribe to system ready channel!");
+    system_ready_message.next_message_pure().await;
+
+    let mut level = BacklightLevel::Full;
+    if let Err(e) = backlight.fade_to(level, FADE_IN_MS).await {
+        error!("Failed to turn on the backlight: {e:?}");
+    }
+
+    loop {
+        // Time left in the current level before stepping down, `None` - stay until activity.
+        // Timeouts can be changed at runtime (menu), re-read them for every step
+        let current_settings = settings::current();
+        let timeout = match level {
+            BacklightLevel::Full => Some(current_settings.backlight_dim_timeout_ms),
+            BacklightLevel::Dim => Some(
+                current_settings
+                    .backlight_off_timeout_ms
+                    .saturating_sub(current_settings.backlight_dim_timeout_ms),
+            ),
+            BacklightLevel::Off => None,
+        };
+        let idle = async {
+            match timeout {
+                Some(ms) => Timer::after(Duration::from_millis(u64::from(ms))).await,
+                None => core::future::pending().await,
+            }
+        };
+
+        let (next, fade_ms) = match select3(
+            idle,
+            button_subscriber.next_message_pure(),
+            keyboard_subscriber.next_message_pure(),
+        )
+        .await
+        {
+            Either3::First(()) => match level {
+                BacklightLevel::Full => (BacklightLevel::Dim, FADE_OUT_MS),
+                BacklightLevel::Dim | BacklightLevel::Off => (BacklightLevel::Off, FADE_OUT_MS),
+            },
+            _ => (BacklightLevel::Full, FADE_IN_MS),
+        };
+
+        if next != level {
+            info!("Backlight: {level:?} -> {next:?}");
+        }
+        // Also applies a brightness changed in the menu (no-op if the duty is unchanged)
+        if let Err(e) = backlight.fade_to(next, fade_ms).await {
+            error!("Failed to change the backlight: {e:?}");
+        }
+        level = next;
+    }
+}
@@ -0,0 +1,119 @@
+//! Display module - Screen rendering
+
+use super::core::{FrameBuffer, HEIGHT, WIDTH};
+use crate::AppConfig;
+use crate::assets::{FONT_6X10_CYR, ICON_GEAR};
+use crate::menu::MenuView;
+use embedded_graphics::{
+    image::Image,
+    mono_font::{MonoTextStyle, iso_8859_5::FONT_9X15},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+
+/// Header (title bar) height, px
+const HEADER_HEIGHT: u32 = 19;
+/// Menu row height, px
+const ROW_HEIGHT: u32 = 19;
+/// Horizontal text padding, px
+const PADDING: i32 = 4;
+
+/// Draw the idle screen
+///
+/// * `fb` - Framebuffer to draw on
+pub fn draw_idle(fb: &mut FrameBuffer<'_>) -> Result<(), core::convert::Infallible> {
+    fb.clear(Rgb565::BLACK)?;
+    draw_header(fb, AppConfig::DEVICE_NAME)?;
+
+    Image::new(&ICON_GEAR, Point::new(PADDING, 60)).draw(fb)?;
+    let style = MonoTextStyle::new(&FONT_9X15, Rgb565::CSS_GRAY);
+    Text::with_baseline(
+        "Long press: menu",
+        Point::new(PADDING + 20, 60),
+        style,
+        Baseline::Top,
+    )
+    .draw(fb)?;
+
+    let style = MonoTextStyle::new(&FONT_6X10_CYR, Rgb565::CSS_DIM_GRAY);
+    Text::with_baseline(
+        "Долгое нажатие: меню",
+        Point::new(PADDING + 20, 80),
+        style,
+        Baseline::Top,
+    )
+    .draw(fb)?;
+    Ok(())
+}
+
+/// Draw the menu screen
+///
+/// * `fb` - Framebuffer to draw on
+/// * `view` - Visible part of the menu
+pub fn draw_menu(
+    fb: &mut FrameBuffer<'_>,
+    view: &MenuView,
+) -> Result<(), core::convert::Infallible> {
+    fb.clear(Rgb565::BLACK)?;
+    draw_header(fb, view.title)?;
+
+    let right_aligned = TextStyleBuilder::new()
+        .alignment(Alignment::Right)
+        .baseline(Baseline::Top)
+        .build();
+
+    let mut top = HEADER_HEIGHT.cast_signed();
+    for row in &view.rows {
+        let (foreground, background) = match (row.selected, view.editing) {
+            (true, true) => (Rgb565::BLACK, Rgb565::CSS_ORANGE),
+            (true, false) => (Rgb565::BLACK, Rgb565::WHITE),
+            (false, _) => (Rgb565::WHITE, Rgb565::BLACK),
+        };
+
+        Rectangle::new(Point::new(0, top), Size::new(WIDTH.into(), ROW_HEIGHT))
+            .into_styled(PrimitiveStyle::with_fill(background))
+            .draw(fb)?;
+
+        let style = MonoTextStyle::new(&FONT_9X15, foreground);
+        Text::with_baseline(
+            row.label,
+            Point::new(PADDING, top + 2),
+            style,
+            Baseline::Top,
+        )
+        .draw(fb)?;
+        Text::with_text_style(
+            &row.value,
+            Point::new(i32::from(WIDTH) - PADDING, top + 2),
+            style,
+            right_aligned,
+        )
+        .draw(fb)?;
+
+        top += ROW_HEIGHT.cast_signed();
+    }
+
+    // Ошибка поверх последней строки
+    if let Some(error) = view.error {
+        let top = i32::from(HEIGHT) - ROW_HEIGHT.cast_signed();
+        Rectangle::new(Point::new(0, top), Size::new(WIDTH.into(), ROW_HEIGHT))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_DARK_RED))
+            .draw(fb)?;
+        let style = MonoTextStyle::new(&FONT_9X15, Rgb565::WHITE);
+        Text::with_baseline(error, Point::new(PADDING, top + 2), style, Baseline::Top).draw(fb)?;
+    }
+    Ok(())
+}
+
+/// Draw the title bar
+fn draw_header(fb: &mut FrameBuffer<'_>, title: &str) -> Result<(), core::convert::Infallible> {
+    Rectangle::new(Point::zero(), Size::new(WIDTH.into(), HEADER_HEIGHT))
+        .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_DARK_SLATE_BLUE))
+        .draw(fb)?;
+
+    let style = MonoTextStyle::new(&FONT_9X15, Rgb565::WHITE);
+    Text::with_baseline(title, Point::new(PADDING, 2), style, Baseline::Top).draw(fb)?;
+    Ok(())
+}
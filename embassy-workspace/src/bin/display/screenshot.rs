@@ -0,0 +1,170 @@
+//! Display module - Screenshot capture
+//!
+//! The framebuffer is serialised into a 16 bpp BMP (`BI_BITFIELDS`, RGB565 masks) and streamed
+//! over the serial port base64-encoded between marker lines:
+//!
+//! ```text
+//! -----BEGIN SCREENSHOT 240x135 64866-----
+//! Qk1i/QAAAAAAAEIAAAAoAAAA8AAAAHn///8BABAAAwAAACD9AAATCwAAEwsAAAAAAAAAAAAAAPgA
+//! ...
+//! -----END SCREENSHOT-----
+//! ```
+//!
+//! `tools/screenshot.py` extracts the image from a captured serial log.
+
+use super::core::{HEIGHT, WIDTH};
+use log::info;
+
+/// BMP file header + BITMAPINFOHEADER + 3 colour masks
+const HEADER_LEN: usize = 14 + 40 + 12;
+
+/// Pixel data size (rows are 480 bytes, already 4-byte aligned)
+const PIXELS_LEN: usize = (WIDTH as usize) * (HEIGHT as usize) * 2;
+
+/// Base64 characters per output line
+const LINE_LEN: usize = 76;
+
+/// Base64 alphabet (RFC 4648)
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Build the BMP header for the display sized image
+#[allow(clippy::cast_possible_truncation)] // sizes are compile time constants far below u32::MAX
+fn bmp_header() -> [u8; HEADER_LEN] {
+    let mut header = [0_u8; HEADER_LEN];
+    let mut put = |offset: usize, bytes: &[u8]| {
+        if let Some(target) = header.get_mut(offset..offset + bytes.len()) {
+            target.copy_from_slice(bytes);
+        }
+    };
+
+    // BITMAPFILEHEADER
+    put(0, b"BM");
+    put(2, &((HEADER_LEN + PIXELS_LEN) as u32).to_le_bytes());
+    put(10, &(HEADER_LEN as u32).to_le_bytes());
+    // BITMAPINFOHEADER
+    put(14, &40_u32.to_le_bytes());
+    put(18, &i32::from(WIDTH).to_le_bytes());
+    // Negative height - rows are stored top-down, as in the framebuffer
+    put(22, &(-i32::from(HEIGHT)).to_le_bytes());
+    put(26, &1_u16.to_le_bytes()); // planes
+    put(28, &16_u16.to_le_bytes()); // bits per pixel
+    put(30, &3_u32.to_le_bytes()); // BI_BITFIELDS
+    put(34, &(PIXELS_LEN as u32).to_le_bytes());
+    put(38, &2835_u32.to_le_bytes()); // 72 DPI
+    put(42, &2835_u32.to_le_bytes());
+    // RGB565 colour masks
+    put(54, &0xF800_u32.to_le_bytes());
+    put(58, &0x07E0_u32.to_le_bytes());
+    put(62, &0x001F_u32.to_le_bytes());
+
+    header
+}
+
+/// Streaming base64 encoder that prints fixed length lines
+struct Base64Lines {
+    /// Pending input bytes (less than 3)
+    pending: [u8; 3],
+    /// Number of pending input bytes
+    pending_len: usize,
+    /// Output line being assembled
+    line: [u8; LINE_LEN],
+    /// Number of characters in the output line
+    line_len: usize,
+}
+
+impl Base64Lines {
+    fn new() -> Self {
+        Self {
+            pending: [0; 3],
+            pending_len: 0,
+            line: [0; LINE_LEN],
+            line_len: 0,
+        }
+    }
+
+    /// Encode the next input byte
+    fn push(&mut self, byte: u8) {
+        if let Some(slot) = self.pending.get_mut(self.pending_len) {
+            *slot = byte;
+            self.pending_len += 1;
+        }
+        if self.pending_len == 3 {
+            self.encode_pending();
+        }
+    }
+
+    /// Encode the pending bytes into 4 characters ('=' padded)
+    fn encode_pending(&mut self) {
+        let [a, b, c] = self.pending;
+        let chunk = (u32::from(a) << 16) | (u32::from(b) << 8) | u32::from(c);
+        for i in 0..4 {
+            let symbol = if i <= self.pending_len {
+                let index = (chunk >> (18 - 6 * i)) & 0x3F;
+                ALPHABET.get(index as usize).copied().unwrap_or(b'=')
+            } else {
+                b'='
+            };
+            self.emit(symbol);
+        }
+        self.pending = [0; 3];
+        self.pending_len = 0;
+    }
+
+    /// Append a character to the line, print the line when it is full
+    fn emit(&mut self, symbol: u8) {
+        if let Some(slot) = self.line.get_mut(self.line_len) {
+            *slot = symbol;
+            self.line_len += 1;
+        }
+        if self.line_len == LINE_LEN {
+            self.flush_line();
+        }
+    }
+
+    /// Print the assembled line
+    fn flush_line(&mut self) {
+        if self.line_len > 0 {
+            let line = self.line.get(..self.line_len).unwrap_or_default();
+            // Only ASCII characters from the alphabet are ever stored
+            esp_println::println!("{}", core::str::from_utf8(line).unwrap_or_default());
+            self.line_len = 0;
+        }
+    }
+
+    /// Encode the remaining bytes and print the last line
+    fn finish(mut self) {
+        if self.pending_len > 0 {
+            self.encode_pending();
+        }
+        self.flush_line();
+    }
+}
+
+/// Stream the framebuffer content over serial as a base64 encoded BMP
+///
+/// * `frame` - Raw framebuffer bytes (big-endian RGB565, as sent to the display)
+pub fn dump_serial(frame: &[u8]) {
+    info!("Capturing screenshot ...");
+    esp_println::println!(
+        "-----BEGIN SCREENSHOT {}x{} {}-----",
+        WIDTH,
+        HEIGHT,
+        HEADER_LEN + PIXELS_LEN
+    );
+
+    let mut encoder = Base64Lines::new();
+    for byte in bmp_header() {
+        encoder.push(byte);
+    }
+    // BMP stores 16 bit pixels little-endian
+    for pixel in frame.chunks_exact(2).take(PIXELS_LEN / 2) {
+        if let [high, low] = pixel {
+            encoder.push(*low);
+            encoder.push(*high);
+        }
+    }
+    encoder.finish();
+
+    esp_println::println!("-----END SCREENSHOT-----");
+    info!("Screenshot captured");
+}
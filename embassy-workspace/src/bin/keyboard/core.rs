@@ -141,7 +141,7 @@ impl<'a> Keyboard<'a> {
     }
 
     /// Returns the keys that were held when [scan][Self::scan] was last called.
-    pub fn _held_keys(&self) -> Key {
+    pub fn held_keys(&self) -> Key {
         self.last_state
     }
 
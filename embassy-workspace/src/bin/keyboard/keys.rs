@@ -185,6 +185,19 @@ impl KeyboardState {
     }
 
     pub fn handle_special_key(&self, key: Key) -> Option<SpecialKey> {
+        // Cardputer has no dedicated arrow keys: Fn + `;` `.` `,` `/` act as arrows, Fn + `` ` `` as Esc,
+        // Fn + `p` captures a screenshot
+        if self.fn_key {
+            return match key {
+                Key::SemiColon => Some(SpecialKey::Up),
+                Key::Period => Some(SpecialKey::Down),
+                Key::Comma => Some(SpecialKey::Left),
+                Key::Slash => Some(SpecialKey::Right),
+                Key::Backquote => Some(SpecialKey::Escape),
+                Key::P => Some(SpecialKey::PrintScreen),
+                _ => None,
+            };
+        }
         match key {
             Key::Enter => Some(SpecialKey::Enter),
             Key::Tab => Some(SpecialKey::Tab),
@@ -192,6 +205,11 @@ impl KeyboardState {
             _ => None,
         }
     }
+
+    /// Returns `true` if Fn modifier is held (printable characters are suppressed)
+    pub fn fn_held(&self) -> bool {
+        self.fn_key
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -199,4 +217,10 @@ pub enum SpecialKey {
     Enter,
     Tab,
     Backspace,
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+    PrintScreen,
 }
@@ -0,0 +1,29 @@
+//! Keyboard module - Messaging - Pub-Sub
+
+use super::keys::SpecialKey;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pubsub::PubSubChannel;
+use embassy_sync::signal::Signal;
+
+/// Keyboard pub-sub message item definition/structure that is passed via channel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum KeyboardMessage {
+    /// Printable character (layout and Shift already applied)
+    Char(char),
+    /// Special (non-printable) key
+    Special(SpecialKey),
+}
+
+/// Keyboard pub-sub topic channel
+/// Other program parts can listen to this topic to get key press events
+/// 4 total capacity/messages, 3 subscribers, and 1 publisher
+pub static KEYBOARD_PUBSUB_CHANNEL: PubSubChannel<
+    CriticalSectionRawMutex,
+    KeyboardMessage,
+    4,
+    3,
+    1,
+> = PubSubChannel::new();
+
+/// A signal / flag that signals that the keyboard system ready
+pub static KEYBOARD_READY_SIGNAL: Signal<CriticalSectionRawMutex, bool> = Signal::new();
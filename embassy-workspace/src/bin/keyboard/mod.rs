@@ -1,6 +1,9 @@
 mod core;
 mod keys;
+mod messaging;
 mod scan_loop;
 
 pub use core::Keyboard;
+pub use keys::SpecialKey;
+pub use messaging::{KEYBOARD_PUBSUB_CHANNEL, KEYBOARD_READY_SIGNAL, KeyboardMessage};
 pub use scan_loop::start_keyboard_scan;
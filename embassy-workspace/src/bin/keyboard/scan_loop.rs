@@ -1,10 +1,9 @@
 //! Keyboard module - Task manager module
-use super::core::Keyboard;
+use super::core::{Key, Keyboard};
+use super::messaging::{KEYBOARD_PUBSUB_CHANNEL, KEYBOARD_READY_SIGNAL, KeyboardMessage};
 use crate::{
-    keyboard::{
-        self,
-        keys::{KeyboardState, SpecialKey},
-    },
+    button::do_nothing_idle,
+    keyboard::keys::{KeyboardState, SpecialKey},
     manager::SYSTEM_READY_PUBSUB_CHANNEL,
 };
 use embassy_futures::select::select;
@@ -13,43 +12,79 @@ use embassy_time::{Duration, Timer};
 use heapless::String;
 use log::{error, info};
 
+/// Async task - Keyboard
+/// Continuously scan the key matrix and publish key presses
+///
+/// * `keyboard` - Cardputer's keyboard matrix
 #[embassy_executor::task]
-pub async fn start_keyboard_scan(mut keyboard: super::core::Keyboard<'static>) {
+pub async fn start_keyboard_scan(mut keyboard: Keyboard<'static>) {
     info!("Running Keyboard scan async task ...");
     let mut keymap = KeyboardState::new();
     let mut text_buffer: String<256> = String::new();
+    let publisher = KEYBOARD_PUBSUB_CHANNEL
+        .publisher()
+        .expect("Keyboard: Failed to create publisher!");
+
+    // Signal to system that keyboard is ready to be used
+    KEYBOARD_READY_SIGNAL.signal(true);
+
+    // Wait idle until the system manager sends a ready signal
+    let mut system_ready_message = SYSTEM_READY_PUBSUB_CHANNEL
+        .subscriber()
+        .expect("Keyboard: Failed to subscribe to channel!");
+    select(system_ready_message.next_message_pure(), do_nothing_idle()).await;
+
+    let modifiers = Key::LeftShift | Key::LeftCtrl | Key::LeftAlt | Key::LeftFn | Key::LeftOpt;
 
-    //TODO: (1) connect with manager
     loop {
         keyboard.scan();
-        let key_set = keyboard.pressed_keys();
 
-        keymap.update_modifiers(key_set);
+        // Модификаторы определяются по удерживаемым клавишам, а не по буферу нажатий
+        let held = keyboard.held_keys();
+        keymap.clear_modifiers(held);
+        keymap.update_modifiers(held);
 
-        match keymap.handle_special_key(key_set) {
-            Some(SpecialKey::Enter) => {
-                // Вывод текста, очистка строки
-                error!("Text: {text_buffer}");
-                text_buffer.clear();
-            }
-            Some(SpecialKey::Tab) => {
-                // Добавление табуляции
-                text_buffer.push('\t').ok();
-            }
-            Some(SpecialKey::Backspace) => {
-                // Удаление символа из строки
-                text_buffer.pop();
-            }
-            None => {
-                // Добавление символа в строку
-                if let Some(ch) = keymap.key_to_char(key_set) {
-                    text_buffer.push(ch).ok();
+        let key_set = keyboard.pressed_keys() & !modifiers;
+        keyboard.clear_pressed_keys();
+
+        if key_set.is_none() {
+            Timer::after(Duration::from_millis(10)).await;
+            continue;
+        }
+
+        let message = match keymap.handle_special_key(key_set) {
+            Some(special) => {
+                match special {
+                    SpecialKey::Enter => {
+                        // Вывод текста, очистка строки
+                        error!("Text: {text_buffer}");
+                        text_buffer.clear();
+                    }
+                    SpecialKey::Tab => {
+                        // Добавление табуляции
+                        text_buffer.push('\t').ok();
+                    }
+                    SpecialKey::Backspace => {
+                        // Удаление символа из строки
+                        text_buffer.pop();
+                    }
+                    _ => {}
                 }
+                Some(KeyboardMessage::Special(special))
             }
+            // Fn + <key> without special meaning is ignored
+            None if keymap.fn_held() => None,
+            None => keymap.key_to_char(key_set).map(|ch| {
+                // Добавление символа в строку
+                text_buffer.push(ch).ok();
+                KeyboardMessage::Char(ch)
+            }),
+        };
+
+        if let Some(message) = message {
+            publisher.publish_immediate(message);
         }
 
-        //TODO: (2) assemble word and send to display task
-        keyboard.clear_pressed_keys();
         Timer::after(Duration::from_millis(10)).await;
     }
 }
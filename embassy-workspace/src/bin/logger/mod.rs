@@ -0,0 +1,4 @@
+mod tee;
+
+// Public re-export of specifics that are available outside of module
+pub use tee::init;
@@ -0,0 +1,110 @@
+//! Logger module - `log` implementation that tees records into the serial port and the console
+
+use crate::display::CONSOLE_PIPE;
+use core::fmt::Write;
+use heapless::String;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Per-target maximal levels, the longest matching target prefix wins.
+/// Targets that do not match any prefix are limited by the global level only
+const TARGET_LEVELS: &[(&str, LevelFilter)] = &[
+    ("embassy_workspace::keyboard", LevelFilter::Info),
+    ("embassy_workspace::button", LevelFilter::Info),
+    ("esp_hal", LevelFilter::Warn),
+    ("esp_rtos", LevelFilter::Warn),
+];
+
+/// Maximal level of records mirrored to the on-screen console (the screen is small)
+const CONSOLE_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Maximal length of a single console line, longer records are truncated
+const CONSOLE_LINE_LEN: usize = 160;
+
+/// Logger writing into `esp_println` and [`CONSOLE_PIPE`]
+pub struct TeeLogger;
+
+impl TeeLogger {
+    /// Maximal level allowed for the target
+    fn target_level(target: &str) -> LevelFilter {
+        TARGET_LEVELS
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(LevelFilter::Trace, |(_, level)| *level)
+    }
+}
+
+impl Log for TeeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Self::target_level(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        const RESET: &str = "\u{001B}[0m";
+        let color = match record.level() {
+            Level::Error => "\u{001B}[31m",
+            Level::Warn => "\u{001B}[33m",
+            Level::Info => "\u{001B}[32m",
+            Level::Debug => "\u{001B}[0m",
+            Level::Trace => "\u{001B}[35m",
+        };
+
+        esp_println::println!("{}{} - {}{}", color, record.level(), record.args(), RESET);
+
+        if record.level() <= CONSOLE_LEVEL {
+            // Whole lines only: a record is dropped if it does not fit into the pipe
+            let mut line: String<CONSOLE_LINE_LEN> = String::new();
+            let mut text = Truncating {
+                line: &mut line,
+                limit: CONSOLE_LINE_LEN - RESET.len() - 1,
+            };
+            write!(text, "{}{:.1} {}", color, record.level(), record.args()).ok();
+            // Место под RESET и перевод строки зарезервировано
+            line.push_str(RESET).ok();
+            line.push('\n').ok();
+            if CONSOLE_PIPE.free_capacity() >= line.len() {
+                CONSOLE_PIPE.try_write(line.as_bytes()).ok();
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Writer that keeps the beginning of the text up to `limit` bytes and drops the rest,
+/// cutting at a char boundary
+struct Truncating<'a, const N: usize> {
+    /// Output line
+    line: &'a mut String<N>,
+    /// Maximal length of the line, bytes
+    limit: usize,
+}
+
+impl<const N: usize> Write for Truncating<'_, N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for ch in s.chars() {
+            if self.line.len() + ch.len_utf8() > self.limit {
+                // Остаток записи не поместится - прекращаем форматирование
+                return Err(core::fmt::Error);
+            }
+            self.line.push(ch).map_err(|_| core::fmt::Error)?;
+        }
+        Ok(())
+    }
+}
+
+static LOGGER: TeeLogger = TeeLogger;
+
+/// Install [`TeeLogger`] as the global logger
+///
+/// * `level` - Global maximal level
+pub fn init(level: LevelFilter) {
+    // Logger can be installed only once, repeated calls are ignored
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
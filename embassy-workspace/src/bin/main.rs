@@ -6,17 +6,34 @@
     holding buffers for the duration of a data transfer."
 )]
 
+mod assets;
 mod button;
+mod display;
 mod keyboard;
+mod logger;
 mod manager;
+mod menu;
+mod settings;
 mod state_machine;
 
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
 use embassy_executor::Spawner;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
 use embassy_time::{Duration, Timer};
 
+use esp_hal::Async;
 use esp_hal::clock::CpuClock;
+use esp_hal::dma::{DmaRxBuf, DmaTxBuf};
+use esp_hal::gpio::{Level, Output, OutputConfig};
+use esp_hal::spi::{
+    Mode,
+    master::{Config, Spi, SpiDmaBus},
+};
 use esp_hal::timer::timg::TimerGroup;
-use log::{info, warn};
+use esp_storage::FlashStorage;
+use log::{error, info, warn};
+use static_cell::StaticCell;
 
 #[panic_handler]
 fn panic(_: &core::panic::PanicInfo) -> ! {
@@ -30,7 +47,7 @@ esp_bootloader_esp_idf::esp_app_desc!();
 #[esp_rtos::main]
 async fn main(spawner: Spawner) -> ! {
     let config = esp_hal::Config::default().with_cpu_clock(CpuClock::max());
-    esp_println::logger::init_logger_from_env();
+    logger::init(settings::current().log_level());
     let peripherals = esp_hal::init(config);
 
     let timg0 = TimerGroup::new(peripherals.TIMG0);
@@ -54,6 +71,52 @@ async fn main(spawner: Spawner) -> ! {
         peripherals.GPIO7,
     );
 
+    // Display - SPI bus with DMA
+    #[allow(clippy::manual_div_ceil)]
+    let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = esp_hal::dma_buffers!(4, 32_000);
+    let dma_rx_buf =
+        DmaRxBuf::new(rx_descriptors, rx_buffer).expect("Failed to create DMA RX buffer");
+    let dma_tx_buf =
+        DmaTxBuf::new(tx_descriptors, tx_buffer).expect("Failed to create DMA TX buffer");
+
+    let spi = Spi::new(
+        peripherals.SPI2,
+        Config::default()
+            .with_frequency(esp_hal::time::Rate::from_mhz(60))
+            .with_mode(Mode::_0),
+    )
+    .expect("Failed to init display SPI")
+    .with_sck(peripherals.GPIO36)
+    .with_mosi(peripherals.GPIO35)
+    .with_dma(peripherals.DMA_CH0)
+    .with_buffers(dma_rx_buf, dma_tx_buf)
+    .into_async();
+
+    static SPI_BUS: StaticCell<Mutex<NoopRawMutex, SpiDmaBus<'static, Async>>> = StaticCell::new();
+    let spi_bus = SPI_BUS.init(Mutex::new(spi));
+
+    let display_cs = Output::new(peripherals.GPIO37, Level::High, OutputConfig::default());
+    let display_spi = SpiDevice::new(spi_bus, display_cs);
+    let display_rst = Output::new(peripherals.GPIO33, Level::Low, OutputConfig::default());
+    let display_dc = Output::new(peripherals.GPIO34, Level::Low, OutputConfig::default());
+    let backlight = display::Backlight::new(peripherals.LEDC, peripherals.GPIO38)
+        .expect("Failed to init display backlight");
+
+    // Settings - restore user edits from flash
+    let settings_store = match settings::SettingsStore::new(FlashStorage::new(peripherals.FLASH)) {
+        Ok(mut store) => {
+            if let Some(stored) = store.load() {
+                info!("Settings restored from flash: {stored:?}");
+                settings::replace(stored);
+            }
+            Some(store)
+        }
+        Err(e) => {
+            error!("Settings storage is not available: {e:?}");
+            None
+        }
+    };
+
     // Button - Define and spawn async task
     let button_info = [(0_u8, button)];
 
@@ -67,13 +130,31 @@ async fn main(spawner: Spawner) -> ! {
         "Button's long hold threshold: {}",
         AppConfig::BTN_LONG_HOLD_THRESHOLD_MS
     );
+    warn!(
+        "Backlight dim/off timeouts: {}/{} ms",
+        AppConfig::BACKLIGHT_DIM_TIMEOUT_MS,
+        AppConfig::BACKLIGHT_OFF_TIMEOUT_MS
+    );
     warn!("Log level: {}", AppConfig::LOG_LEVEL);
 
-    // TODO: connect with manager
     spawner
         .spawn(keyboard::start_keyboard_scan(keyboard))
         .expect("Failed to spawn keyboard scan task");
 
+    spawner
+        .spawn(display::start_display(display_spi, display_dc, display_rst))
+        .expect("Failed to spawn display task");
+
+    spawner
+        .spawn(display::start_backlight(backlight))
+        .expect("Failed to spawn backlight task");
+
+    if let Some(store) = settings_store {
+        spawner
+            .spawn(settings::start_settings_persist(store))
+            .expect("Failed to spawn settings persist task");
+    }
+
     // Task to check if all system components are ready to go
     spawner
         .spawn(manager::wait_for_system_ready())
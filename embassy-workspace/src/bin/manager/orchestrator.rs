@@ -3,6 +3,8 @@ use embassy_sync::pubsub::PubSubChannel;
 use log::info;
 
 use crate::button::BUTTON_READY_SIGNAL;
+use crate::display::DISPLAY_READY_SIGNAL;
+use crate::keyboard::KEYBOARD_READY_SIGNAL;
 /// System ready pub-sub topic channel
 /// 1 total capacity/messages, 4 subscribers, and 1 publisher
 pub static SYSTEM_READY_PUBSUB_CHANNEL: PubSubChannel<CriticalSectionRawMutex, (), 1, 4, 1> =
@@ -16,6 +18,8 @@ pub async fn wait_for_system_ready() {
 
     // Waiting for each system component to report back as ready
     BUTTON_READY_SIGNAL.wait().await;
+    KEYBOARD_READY_SIGNAL.wait().await;
+    DISPLAY_READY_SIGNAL.wait().await;
     // ... Add other ready signal here ...
 
     // Signal out that the system is ready
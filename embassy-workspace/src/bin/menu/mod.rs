@@ -0,0 +1,8 @@
+mod navigator;
+mod tree;
+mod view;
+
+// Public re-export of specifics that are available outside of module
+pub use navigator::{MenuInput, MenuNavigator, MenuOutcome};
+pub use tree::{MenuAction, ROOT_MENU};
+pub use view::MenuView;
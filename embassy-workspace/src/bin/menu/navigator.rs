@@ -0,0 +1,245 @@
+//! Menu module - Navigation state
+
+use super::tree::{Menu, MenuAction, MenuItem};
+use super::view::{MENU_VISIBLE_ROWS, MenuRow, MenuView};
+use crate::button::PressType;
+use crate::keyboard::SpecialKey;
+use crate::settings::{self, SettingValue};
+use core::fmt::Write;
+use heapless::{String, Vec};
+
+/// Maximal nesting of submenus
+const MENU_DEPTH: usize = 4;
+
+/// Navigation input, produced from button presses or keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInput {
+    /// Select next item / increase edited value
+    Next,
+    /// Select previous item / decrease edited value
+    Previous,
+    /// Open submenu, toggle, start or confirm editing, trigger action
+    Enter,
+    /// Cancel editing / leave submenu
+    Back,
+}
+
+impl From<PressType> for MenuInput {
+    fn from(press_type: PressType) -> Self {
+        match press_type {
+            PressType::ShortRelease => MenuInput::Next,
+            PressType::LongRelease => MenuInput::Enter,
+            PressType::LongHold => MenuInput::Back,
+        }
+    }
+}
+
+impl MenuInput {
+    /// Map keyboard special key to the menu input
+    ///
+    /// * `key` - Special key pressed
+    pub fn from_key(key: SpecialKey) -> Option<Self> {
+        match key {
+            SpecialKey::Up => Some(MenuInput::Previous),
+            SpecialKey::Down | SpecialKey::Tab => Some(MenuInput::Next),
+            SpecialKey::Enter | SpecialKey::Right => Some(MenuInput::Enter),
+            SpecialKey::Escape | SpecialKey::Left | SpecialKey::Backspace => Some(MenuInput::Back),
+            SpecialKey::PrintScreen => None,
+        }
+    }
+}
+
+/// Result of handling a menu input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuOutcome {
+    /// Menu state changed and has to be redrawn
+    Redraw,
+    /// Action has to be handled by the caller
+    Action(MenuAction),
+    /// Menu was left from the top level
+    Closed,
+}
+
+/// Menu navigation state: submenu stack, selection and the value being edited
+pub struct MenuNavigator {
+    /// Root menu
+    root: &'static Menu,
+    /// Opened menus with the selected item index, the last one is shown
+    stack: Vec<(&'static Menu, usize), MENU_DEPTH>,
+    /// Value of the numeric item being edited
+    editing: Option<u32>,
+    /// Reason the last edited value was rejected
+    error: Option<&'static str>,
+}
+
+impl MenuNavigator {
+    /// Constructor
+    ///
+    /// * `root` - Top level menu
+    pub fn new(root: &'static Menu) -> Self {
+        let mut navigator = Self {
+            root,
+            stack: Vec::new(),
+            editing: None,
+            error: None,
+        };
+        navigator.reset();
+        navigator
+    }
+
+    /// Return to the top level menu with the first item selected
+    pub fn reset(&mut self) {
+        self.stack.clear();
+        self.stack.push((self.root, 0)).ok();
+        self.editing = None;
+        self.error = None;
+    }
+
+    /// Handle navigation input
+    ///
+    /// * `input` - Navigation input
+    pub fn handle(&mut self, input: MenuInput) -> MenuOutcome {
+        self.error = None;
+        let Some((menu, selected)) = self.stack.last().copied() else {
+            self.reset();
+            return MenuOutcome::Redraw;
+        };
+        let Some(item) = menu.items.get(selected) else {
+            self.reset();
+            return MenuOutcome::Redraw;
+        };
+
+        if let Some(value) = self.editing {
+            return self.handle_editing(item, value, input);
+        }
+
+        match input {
+            MenuInput::Next => self.select((selected + 1) % menu.items.len().max(1)),
+            MenuInput::Previous => self.select(
+                selected
+                    .checked_sub(1)
+                    .unwrap_or(menu.items.len().saturating_sub(1)),
+            ),
+            MenuInput::Back => {
+                if self.stack.len() <= 1 {
+                    self.reset();
+                    return MenuOutcome::Closed;
+                }
+                self.stack.pop();
+            }
+            MenuInput::Enter => match item {
+                MenuItem::Submenu(submenu) => {
+                    self.stack.push((submenu, 0)).ok();
+                }
+                MenuItem::Toggle { setting, .. } => {
+                    if let SettingValue::Bool(enabled) = settings::current().get(*setting) {
+                        settings::update(*setting, SettingValue::Bool(!enabled)).ok();
+                    }
+                }
+                MenuItem::Number { setting, .. } => {
+                    if let SettingValue::U32(value) = settings::current().get(*setting) {
+                        self.editing = Some(value);
+                    }
+                }
+                MenuItem::Action { action, .. } => return MenuOutcome::Action(*action),
+            },
+        }
+        MenuOutcome::Redraw
+    }
+
+    /// Handle input while a numeric value is being edited
+    fn handle_editing(&mut self, item: &MenuItem, value: u32, input: MenuInput) -> MenuOutcome {
+        let MenuItem::Number { setting, step, .. } = item else {
+            self.editing = None;
+            return MenuOutcome::Redraw;
+        };
+        let (min, max) = (*setting.range().start(), *setting.range().end());
+
+        match input {
+            MenuInput::Next => {
+                let next = value.saturating_add(*step);
+                self.editing = Some(if next > max { min } else { next });
+            }
+            MenuInput::Previous => {
+                let previous = value.saturating_sub(*step);
+                self.editing = Some(if value <= min { max } else { previous.max(min) });
+            }
+            MenuInput::Enter => {
+                // Отклонённое значение остаётся в редакторе, чтобы его можно было исправить
+                match settings::update(*setting, SettingValue::U32(value.clamp(min, max))) {
+                    Ok(()) => self.editing = None,
+                    Err(e) => self.error = Some(e.message()),
+                }
+            }
+            MenuInput::Back => self.editing = None,
+        }
+        MenuOutcome::Redraw
+    }
+
+    /// Change the selected item of the current menu
+    fn select(&mut self, index: usize) {
+        if let Some((_, selected)) = self.stack.last_mut() {
+            *selected = index;
+        }
+    }
+
+    /// Snapshot of the visible part of the current menu
+    pub fn view(&self) -> MenuView {
+        let mut view = MenuView {
+            title: self.root.title,
+            rows: Vec::new(),
+            editing: self.editing.is_some(),
+            error: self.error,
+        };
+        let Some((menu, selected)) = self.stack.last().copied() else {
+            return view;
+        };
+        view.title = menu.title;
+
+        // Keep the selected item visible
+        let first = selected.saturating_sub(MENU_VISIBLE_ROWS - 1);
+        let current = settings::current();
+
+        for (index, item) in menu
+            .items
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(MENU_VISIBLE_ROWS)
+        {
+            let is_selected = index == selected;
+            let mut value: String<16> = String::new();
+            match item {
+                MenuItem::Submenu(_) => {
+                    value.push('>').ok();
+                }
+                MenuItem::Toggle { setting, .. } => {
+                    if let SettingValue::Bool(enabled) = current.get(*setting) {
+                        value.push_str(if enabled { "ON" } else { "OFF" }).ok();
+                    }
+                }
+                MenuItem::Number { setting, unit, .. } => {
+                    let shown = match (is_selected, self.editing, current.get(*setting)) {
+                        (true, Some(edited), _) | (_, _, SettingValue::U32(edited)) => edited,
+                        (_, _, SettingValue::Bool(_)) => 0,
+                    };
+                    if is_selected && self.editing.is_some() {
+                        write!(value, "<{shown} {unit}>").ok();
+                    } else {
+                        write!(value, "{shown} {unit}").ok();
+                    }
+                }
+                MenuItem::Action { .. } => {}
+            }
+
+            view.rows
+                .push(MenuRow {
+                    label: item.label(),
+                    value,
+                    selected: is_selected,
+                })
+                .ok();
+        }
+        view
+    }
+}
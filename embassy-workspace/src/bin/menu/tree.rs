@@ -0,0 +1,150 @@
+//! Menu module - Declarative menu tree
+
+use crate::settings::SettingId;
+
+/// Actions that can be triggered from the menu and are handled outside of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    /// Restore settings from the build-time configuration
+    RestoreDefaults,
+    /// Show the text console
+    OpenConsole,
+    /// Stream the shown screen over serial
+    Screenshot,
+    /// Close the menu
+    Exit,
+}
+
+/// Menu item definition
+#[derive(Debug)]
+pub enum MenuItem {
+    /// Nested menu
+    Submenu(&'static Menu),
+    /// On/off setting
+    Toggle {
+        /// Item label
+        label: &'static str,
+        /// Edited setting
+        setting: SettingId,
+    },
+    /// Numeric setting editor
+    Number {
+        /// Item label
+        label: &'static str,
+        /// Edited setting, the allowed values are [`SettingId::range`]
+        setting: SettingId,
+        /// Increment/decrement step
+        step: u32,
+        /// Unit shown after the value
+        unit: &'static str,
+    },
+    /// Action handled by the state machine
+    Action {
+        /// Item label
+        label: &'static str,
+        /// Triggered action
+        action: MenuAction,
+    },
+}
+
+impl MenuItem {
+    /// Label shown on the display
+    pub fn label(&self) -> &'static str {
+        match self {
+            MenuItem::Submenu(menu) => menu.title,
+            MenuItem::Toggle { label, .. }
+            | MenuItem::Number { label, .. }
+            | MenuItem::Action { label, .. } => label,
+        }
+    }
+}
+
+/// Menu (or submenu) definition
+#[derive(Debug)]
+pub struct Menu {
+    /// Title shown in the header
+    pub title: &'static str,
+    /// Menu items
+    pub items: &'static [MenuItem],
+}
+
+/// Top level menu
+pub static ROOT_MENU: Menu = Menu {
+    title: "Menu",
+    items: &[
+        MenuItem::Submenu(&BUTTON_MENU),
+        MenuItem::Submenu(&DISPLAY_MENU),
+        MenuItem::Submenu(&SYSTEM_MENU),
+        MenuItem::Action {
+            label: "Console",
+            action: MenuAction::OpenConsole,
+        },
+        MenuItem::Action {
+            label: "Exit",
+            action: MenuAction::Exit,
+        },
+    ],
+};
+
+/// Button settings
+static BUTTON_MENU: Menu = Menu {
+    title: "Button",
+    items: &[
+        MenuItem::Number {
+            label: "Long press",
+            setting: SettingId::BtnLongPressThresholdMs,
+            step: 50,
+            unit: "ms",
+        },
+        MenuItem::Number {
+            label: "Long hold",
+            setting: SettingId::BtnLongHoldThresholdMs,
+            step: 250,
+            unit: "ms",
+        },
+    ],
+};
+
+/// Display settings
+static DISPLAY_MENU: Menu = Menu {
+    title: "Display",
+    items: &[
+        MenuItem::Number {
+            label: "Brightness",
+            setting: SettingId::BacklightBrightnessPct,
+            step: 10,
+            unit: "%",
+        },
+        MenuItem::Number {
+            label: "Dim after",
+            setting: SettingId::BacklightDimTimeoutMs,
+            step: 5_000,
+            unit: "ms",
+        },
+        MenuItem::Number {
+            label: "Off after",
+            setting: SettingId::BacklightOffTimeoutMs,
+            step: 5_000,
+            unit: "ms",
+        },
+    ],
+};
+
+/// System settings
+static SYSTEM_MENU: Menu = Menu {
+    title: "System",
+    items: &[
+        MenuItem::Toggle {
+            label: "Verbose log",
+            setting: SettingId::VerboseLogging,
+        },
+        MenuItem::Action {
+            label: "Screenshot",
+            action: MenuAction::Screenshot,
+        },
+        MenuItem::Action {
+            label: "Restore defaults",
+            action: MenuAction::RestoreDefaults,
+        },
+    ],
+};
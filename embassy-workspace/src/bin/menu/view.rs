@@ -0,0 +1,30 @@
+//! Menu module - Snapshot of the menu state passed to the display
+
+use heapless::{String, Vec};
+
+/// Number of menu rows that fit on the screen
+pub const MENU_VISIBLE_ROWS: usize = 6;
+
+/// Single visible menu row
+#[derive(Debug, Clone)]
+pub struct MenuRow {
+    /// Item label
+    pub label: &'static str,
+    /// Formatted value (empty for actions)
+    pub value: String<16>,
+    /// Row is selected
+    pub selected: bool,
+}
+
+/// Visible part of the current menu
+#[derive(Debug, Clone)]
+pub struct MenuView {
+    /// Title of the current (sub)menu
+    pub title: &'static str,
+    /// Visible rows
+    pub rows: Vec<MenuRow, MENU_VISIBLE_ROWS>,
+    /// Numeric value of the selected row is being edited
+    pub editing: bool,
+    /// Edited value was rejected, the reason is shown until the next input
+    pub error: Option<&'static str>,
+}
@@ -0,0 +1,10 @@
+mod persist_loop;
+mod store;
+mod values;
+
+// Public re-export of specifics that are available outside of module
+pub use persist_loop::start_settings_persist;
+pub use store::SettingsStore;
+pub use values::{
+    SETTINGS_SAVE_SIGNAL, SettingError, SettingId, SettingValue, Settings, current, replace, update,
+};
@@ -0,0 +1,23 @@
+//! Settings module - Task manager module
+use super::store::SettingsStore;
+use super::values::{SETTINGS_SAVE_SIGNAL, current};
+use log::{error, info};
+
+/// Async task - Settings
+/// Write settings to flash every time they are changed
+///
+/// * `store` - Settings storage in flash
+#[embassy_executor::task]
+pub async fn start_settings_persist(mut store: SettingsStore<'static>) {
+    info!("Running Settings persist async task ...");
+
+    loop {
+        SETTINGS_SAVE_SIGNAL.wait().await;
+
+        let settings = current();
+        match store.save(&settings) {
+            Ok(()) => info!("Settings saved: {settings:?}"),
+            Err(e) => error!("Failed to save settings: {e:?}"),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Settings module - Flash persistence
+//!
+//! Settings are appended as versioned, CRC protected records to the `nvs` data partition (the
+//! firmware does not use ESP-IDF NVS, so the partition is free to reuse), see `config-store`.
+//! Records of older layouts are migrated on load and written back in the current layout.
+
+use super::values::Settings;
+use config_store::{Reader, Record, RecordLog, Writer};
+use embedded_storage::ReadStorage;
+use esp_bootloader_esp_idf::partitions::{self, DataPartitionSubType, PartitionType};
+use esp_storage::FlashStorage;
+use log::{info, warn};
+
+/// Marker of the single fixed record written by the first firmware versions: "SET1"
+const LEGACY_MAGIC: u32 = 0x5345_5431;
+
+/// Legacy record: magic (4) + version 1 payload (9)
+const LEGACY_RECORD_LEN: usize = 13;
+
+/// Settings storage errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreError {
+    /// Partition table could not be read
+    PartitionTable,
+    /// There is no `nvs` partition in the partition table
+    NoPartition,
+    /// Flash read/write failed or the partition layout is not usable
+    Flash(config_store::Error<esp_storage::FlashStorageError>),
+}
+
+/// Handle for the settings storage in flash
+pub struct SettingsStore<'d> {
+    /// Settings records in the `nvs` partition
+    log: RecordLog<FlashStorage<'d>>,
+    /// Settings found in the legacy record, used until the first save
+    legacy: Option<Settings>,
+}
+
+impl<'d> SettingsStore<'d> {
+    /// Settings storage constructor - locates the `nvs` partition
+    ///
+    /// * `flash` - Flash driver
+    pub fn new(mut flash: FlashStorage<'d>) -> Result<Self, StoreError> {
+        let mut table_buffer = [0_u8; partitions::PARTITION_TABLE_MAX_LEN];
+        let table = partitions::read_partition_table(&mut flash, &mut table_buffer)
+            .map_err(|_| StoreError::PartitionTable)?;
+        let partition = table
+            .find_partition(PartitionType::Data(DataPartitionSubType::Nvs))
+            .map_err(|_| StoreError::PartitionTable)?
+            .ok_or(StoreError::NoPartition)?;
+        let (offset, len) = (partition.offset(), partition.len());
+
+        let mut record = [0_u8; LEGACY_RECORD_LEN];
+        let legacy = ReadStorage::read(&mut flash, offset, &mut record)
+            .ok()
+            .and_then(|()| decode_legacy(&record));
+
+        let log = RecordLog::new(flash, offset, len).map_err(StoreError::Flash)?;
+        Ok(Self { log, legacy })
+    }
+
+    /// Load settings from flash. Returns `None` if nothing valid has been stored yet.
+    /// Values outside of the allowed ranges are replaced by the defaults
+    pub fn load(&mut self) -> Option<Settings> {
+        let loaded = match self.log.load::<Settings>() {
+            Ok(Some(loaded)) => loaded,
+            Ok(None) => {
+                let legacy = validate(self.legacy.take()?);
+                info!("Migrating settings from the legacy record");
+                self.migrate(&legacy);
+                return Some(legacy);
+            }
+            Err(e) => {
+                warn!("Stored settings are not usable ({e:?}), using defaults");
+                return None;
+            }
+        };
+
+        let settings = validate(loaded.value);
+        if loaded.is_migrated() {
+            info!(
+                "Migrating settings from version {} to {}",
+                loaded.version,
+                Settings::VERSION
+            );
+            self.migrate(&settings);
+        }
+        Some(settings)
+    }
+
+    /// Write settings to flash
+    ///
+    /// * `settings` - Settings to store
+    pub fn save(&mut self, settings: &Settings) -> Result<(), StoreError> {
+        self.log.save(settings).map_err(StoreError::Flash)
+    }
+
+    /// Store migrated settings in the current layout
+    fn migrate(&mut self, settings: &Settings) {
+        if let Err(e) = self.save(settings) {
+            warn!("Failed to store migrated settings: {e:?}");
+        }
+    }
+}
+
+impl Record for Settings {
+    /// 1 - button thresholds and verbose logging, 2 - backlight added
+    const VERSION: u16 = 2;
+
+    fn encode(&self, payload: &mut [u8]) -> Option<usize> {
+        let mut writer = Writer::new(payload);
+        writer
+            .u32(self.btn_long_press_threshold_ms)?
+            .u32(self.btn_long_hold_threshold_ms)?
+            .u8(u8::from(self.verbose_logging))?
+            .u8(self.backlight_brightness_pct)?
+            .u32(self.backlight_dim_timeout_ms)?
+            .u32(self.backlight_off_timeout_ms)?;
+        Some(writer.len())
+    }
+
+    fn decode(version: u16, payload: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(payload);
+        let mut settings = Settings {
+            btn_long_press_threshold_ms: reader.u32()?,
+            btn_long_hold_threshold_ms: reader.u32()?,
+            verbose_logging: reader.u8()? & 0x01 != 0,
+            ..Settings::defaults()
+        };
+        // Version 1 has no backlight settings - keep the build-time defaults
+        if version >= 2 {
+            settings.backlight_brightness_pct = reader.u8()?;
+            settings.backlight_dim_timeout_ms = reader.u32()?;
+            settings.backlight_off_timeout_ms = reader.u32()?;
+        }
+        Some(settings)
+    }
+}
+
+/// Check loaded settings, a record written by an older firmware (or a damaged one that still
+/// passed the CRC) must not lock the user out of the menu or turn the backlight off
+fn validate(settings: Settings) -> Settings {
+    let validated = settings.validated();
+    if validated != settings {
+        warn!("Stored settings are out of range, defaults are used for those: {settings:?}");
+    }
+    validated
+}
+
+/// Deserialize settings from the legacy record (version 1 payload after the marker)
+fn decode_legacy(record: &[u8; LEGACY_RECORD_LEN]) -> Option<Settings> {
+    let mut reader = Reader::new(record);
+    if reader.u32()? != LEGACY_MAGIC {
+        return None;
+    }
+    Settings::decode(1, reader.remaining())
+}
@@ -0,0 +1,280 @@
+//! Settings module - Runtime values
+//!
+//! Build-time [`AppConfig`] provides the defaults, user edits (menu) override them at runtime
+//! and are persisted in flash.
+
+use crate::{AppConfig, LogLevel};
+use core::cell::Cell;
+use core::ops::RangeInclusive;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use log::LevelFilter;
+
+/// Identifiers of user editable settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingId {
+    /// Button long press threshold, ms
+    BtnLongPressThresholdMs,
+    /// Button long hold threshold, ms
+    BtnLongHoldThresholdMs,
+    /// Debug level logging
+    VerboseLogging,
+    /// Backlight brightness, %
+    BacklightBrightnessPct,
+    /// Inactivity before the backlight is dimmed, ms
+    BacklightDimTimeoutMs,
+    /// Inactivity before the backlight is turned off, ms
+    BacklightOffTimeoutMs,
+}
+
+impl SettingId {
+    /// Allowed values, used by the menu editor and to check values loaded from flash. Taken
+    /// from the configuration schema, so the menu accepts the same values as the build
+    pub const fn range(self) -> RangeInclusive<u32> {
+        match self {
+            SettingId::BtnLongPressThresholdMs => AppConfig::BTN_LONG_PRESS_THRESHOLD_MS_RANGE,
+            SettingId::BtnLongHoldThresholdMs => AppConfig::BTN_LONG_HOLD_THRESHOLD_MS_RANGE,
+            SettingId::VerboseLogging => 0..=1,
+            SettingId::BacklightBrightnessPct => widen(AppConfig::BACKLIGHT_BRIGHTNESS_PCT_RANGE),
+            SettingId::BacklightDimTimeoutMs => AppConfig::BACKLIGHT_DIM_TIMEOUT_MS_RANGE,
+            SettingId::BacklightOffTimeoutMs => AppConfig::BACKLIGHT_OFF_TIMEOUT_MS_RANGE,
+        }
+    }
+}
+
+/// `u8` range of the schema as the `u32` range of the menu editor
+///
+/// * `range` - Range to convert
+const fn widen(range: RangeInclusive<u8>) -> RangeInclusive<u32> {
+    (*range.start() as u32)..=(*range.end() as u32)
+}
+
+/// Value of a single setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingValue {
+    /// On/off setting
+    Bool(bool),
+    /// Numeric setting
+    U32(u32),
+}
+
+/// Reasons a setting value is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingError {
+    /// Value type does not match the setting
+    Type,
+    /// Value is outside of [`SettingId::range`]
+    Range,
+    /// Long press threshold is not shorter than the long hold threshold, the long release
+    /// (menu enter) could never happen
+    PressNotBelowHold,
+    /// Backlight would be dimmed after it is turned off
+    DimAfterOff,
+}
+
+impl SettingError {
+    /// Short text shown on the display
+    pub fn message(self) -> &'static str {
+        match self {
+            SettingError::Type => "Wrong value type",
+            SettingError::Range => "Out of range",
+            SettingError::PressNotBelowHold => "Press must be < hold",
+            SettingError::DimAfterOff => "Dim must be <= off",
+        }
+    }
+}
+
+/// All user editable settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// Button long press threshold, ms
+    pub btn_long_press_threshold_ms: u32,
+    /// Button long hold threshold, ms
+    pub btn_long_hold_threshold_ms: u32,
+    /// Debug level logging
+    pub verbose_logging: bool,
+    /// Backlight brightness, %
+    pub backlight_brightness_pct: u8,
+    /// Inactivity before the backlight is dimmed, ms
+    pub backlight_dim_timeout_ms: u32,
+    /// Inactivity before the backlight is turned off, ms
+    pub backlight_off_timeout_ms: u32,
+}
+
+impl Settings {
+    /// Settings taken from the build-time configuration
+    pub const fn defaults() -> Self {
+        Self {
+            btn_long_press_threshold_ms: AppConfig::BTN_LONG_PRESS_THRESHOLD_MS,
+            btn_long_hold_threshold_ms: AppConfig::BTN_LONG_HOLD_THRESHOLD_MS,
+            verbose_logging: matches!(AppConfig::LOG_LEVEL, LogLevel::Debug | LogLevel::Trace),
+            backlight_brightness_pct: AppConfig::BACKLIGHT_BRIGHTNESS_PCT,
+            backlight_dim_timeout_ms: AppConfig::BACKLIGHT_DIM_TIMEOUT_MS,
+            backlight_off_timeout_ms: AppConfig::BACKLIGHT_OFF_TIMEOUT_MS,
+        }
+    }
+
+    /// Get the value of the specified setting
+    ///
+    /// * `id` - Setting identifier
+    pub fn get(&self, id: SettingId) -> SettingValue {
+        match id {
+            SettingId::BtnLongPressThresholdMs => {
+                SettingValue::U32(self.btn_long_press_threshold_ms)
+            }
+            SettingId::BtnLongHoldThresholdMs => SettingValue::U32(self.btn_long_hold_threshold_ms),
+            SettingId::VerboseLogging => SettingValue::Bool(self.verbose_logging),
+            SettingId::BacklightBrightnessPct => {
+                SettingValue::U32(self.backlight_brightness_pct.into())
+            }
+            SettingId::BacklightDimTimeoutMs => SettingValue::U32(self.backlight_dim_timeout_ms),
+            SettingId::BacklightOffTimeoutMs => SettingValue::U32(self.backlight_off_timeout_ms),
+        }
+    }
+
+    /// Set the value of the specified setting. The settings stay unchanged on error
+    ///
+    /// * `id` - Setting identifier
+    /// * `value` - New value
+    pub fn set(&mut self, id: SettingId, value: SettingValue) -> Result<(), SettingError> {
+        if let SettingValue::U32(number) = value
+            && !id.range().contains(&number)
+        {
+            return Err(SettingError::Range);
+        }
+        match (id, value) {
+            (SettingId::BtnLongPressThresholdMs, SettingValue::U32(ms)) => {
+                if ms >= self.btn_long_hold_threshold_ms {
+                    return Err(SettingError::PressNotBelowHold);
+                }
+                self.btn_long_press_threshold_ms = ms;
+            }
+            (SettingId::BtnLongHoldThresholdMs, SettingValue::U32(ms)) => {
+                if ms <= self.btn_long_press_threshold_ms {
+                    return Err(SettingError::PressNotBelowHold);
+                }
+                self.btn_long_hold_threshold_ms = ms;
+            }
+            (SettingId::VerboseLogging, SettingValue::Bool(enabled)) => {
+                self.verbose_logging = enabled;
+            }
+            (SettingId::BacklightBrightnessPct, SettingValue::U32(pct)) => {
+                let Ok(pct) = u8::try_from(pct) else {
+                    return Err(SettingError::Type);
+                };
+                self.backlight_brightness_pct = pct;
+            }
+            (SettingId::BacklightDimTimeoutMs, SettingValue::U32(ms)) => {
+                if ms > self.backlight_off_timeout_ms {
+                    return Err(SettingError::DimAfterOff);
+                }
+                self.backlight_dim_timeout_ms = ms;
+            }
+            (SettingId::BacklightOffTimeoutMs, SettingValue::U32(ms)) => {
+                if ms < self.backlight_dim_timeout_ms {
+                    return Err(SettingError::DimAfterOff);
+                }
+                self.backlight_off_timeout_ms = ms;
+            }
+            _ => return Err(SettingError::Type),
+        }
+        Ok(())
+    }
+
+    /// Settings with the values outside of [`SettingId::range`] and the conflicting pairs
+    /// (press / hold, dim / off) replaced by the defaults, field by field
+    pub fn validated(mut self) -> Self {
+        let defaults = Self::defaults();
+        let valid = |id: SettingId, value: u32| id.range().contains(&value);
+
+        if !valid(
+            SettingId::BtnLongPressThresholdMs,
+            self.btn_long_press_threshold_ms,
+        ) {
+            self.btn_long_press_threshold_ms = defaults.btn_long_press_threshold_ms;
+        }
+        if !valid(
+            SettingId::BtnLongHoldThresholdMs,
+            self.btn_long_hold_threshold_ms,
+        ) {
+            self.btn_long_hold_threshold_ms = defaults.btn_long_hold_threshold_ms;
+        }
+        if !valid(
+            SettingId::BacklightBrightnessPct,
+            self.backlight_brightness_pct.into(),
+        ) {
+            self.backlight_brightness_pct = defaults.backlight_brightness_pct;
+        }
+        if !valid(
+            SettingId::BacklightDimTimeoutMs,
+            self.backlight_dim_timeout_ms,
+        ) {
+            self.backlight_dim_timeout_ms = defaults.backlight_dim_timeout_ms;
+        }
+        if !valid(
+            SettingId::BacklightOffTimeoutMs,
+            self.backlight_off_timeout_ms,
+        ) {
+            self.backlight_off_timeout_ms = defaults.backlight_off_timeout_ms;
+        }
+
+        // Пары по умолчанию согласованы (проверка конфигурации при сборке)
+        if self.btn_long_press_threshold_ms >= self.btn_long_hold_threshold_ms {
+            self.btn_long_press_threshold_ms = defaults.btn_long_press_threshold_ms;
+            self.btn_long_hold_threshold_ms = defaults.btn_long_hold_threshold_ms;
+        }
+        if self.backlight_dim_timeout_ms > self.backlight_off_timeout_ms {
+            self.backlight_dim_timeout_ms = defaults.backlight_dim_timeout_ms;
+            self.backlight_off_timeout_ms = defaults.backlight_off_timeout_ms;
+        }
+        self
+    }
+
+    /// Global log level selected by the settings
+    pub fn log_level(&self) -> LevelFilter {
+        if self.verbose_logging {
+            LevelFilter::Debug
+        } else {
+            LevelFilter::Info
+        }
+    }
+}
+
+/// Current runtime settings
+static SETTINGS: Mutex<CriticalSectionRawMutex, Cell<Settings>> =
+    Mutex::new(Cell::new(Settings::defaults()));
+
+/// A signal / flag that requests the settings to be written to flash
+pub static SETTINGS_SAVE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Get a copy of the current settings
+pub fn current() -> Settings {
+    SETTINGS.lock(Cell::get)
+}
+
+/// Replace all settings at once (e.g. after loading them from flash) and apply side effects
+///
+/// * `settings` - New settings
+pub fn replace(settings: Settings) {
+    SETTINGS.lock(|cell| cell.set(settings));
+    apply(&settings);
+}
+
+/// Update a single setting, apply it and request persisting.
+/// Rejected values change nothing
+///
+/// * `id` - Setting identifier
+/// * `value` - New value
+pub fn update(id: SettingId, value: SettingValue) -> Result<(), SettingError> {
+    let mut settings = current();
+    settings.set(id, value)?;
+    replace(settings);
+    SETTINGS_SAVE_SIGNAL.signal(());
+    Ok(())
+}
+
+/// Apply settings that have an immediate effect on the system
+fn apply(settings: &Settings) {
+    log::set_max_level(settings.log_level());
+}
@@ -2,7 +2,11 @@
 #![allow(unused_variables)] // only used for development
 
 use crate::button::{BUTTON_PUBSUB_CHANNEL, ButtonMessage, PressType};
+use crate::display::{ConsoleScroll, DISPLAY_CHANNEL, DisplayCommand};
+use crate::keyboard::{KEYBOARD_PUBSUB_CHANNEL, KeyboardMessage, SpecialKey};
 use crate::manager::SYSTEM_READY_PUBSUB_CHANNEL;
+use crate::menu::{MenuAction, MenuInput, MenuNavigator, MenuOutcome, ROOT_MENU};
+use crate::settings::{self, SETTINGS_SAVE_SIGNAL, Settings};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::pubsub::Subscriber;
 use embassy_time::{Duration, Instant, Timer};
@@ -16,6 +20,10 @@ enum State {
     Idle,
     /// Device is processing something
     Processing,
+    /// Menu is shown and navigated
+    Menu,
+    /// Text console is shown
+    Console,
     /// Device is in an error state
     Error,
 }
@@ -37,6 +45,8 @@ enum Event {
     ButtonPressLongRelease,
     /// Button press event is a long hold
     ButtonPressLongHold,
+    /// Special key pressed on the keyboard
+    Key(SpecialKey),
     /// Error has occurred
     Error,
 }
@@ -48,6 +58,11 @@ struct StateMachine {
     latest_event: Event,
     /// Pub-sub subscriber: Listen for Button messages
     button_pubsub_subscriber: Subscriber<'static, CriticalSectionRawMutex, ButtonMessage, 2, 3, 1>,
+    /// Pub-sub subscriber: Listen for Keyboard messages
+    keyboard_pubsub_subscriber:
+        Subscriber<'static, CriticalSectionRawMutex, KeyboardMessage, 4, 3, 1>,
+    /// Menu navigation state
+    menu: MenuNavigator,
 }
 
 impl StateMachine {
@@ -56,10 +71,15 @@ impl StateMachine {
         let button_pubsub_subscriber = BUTTON_PUBSUB_CHANNEL
             .subscriber()
             .expect("Failed to subscribe");
+        let keyboard_pubsub_subscriber = KEYBOARD_PUBSUB_CHANNEL
+            .subscriber()
+            .expect("Failed to subscribe");
         StateMachine {
             current_state,
             latest_event,
             button_pubsub_subscriber,
+            keyboard_pubsub_subscriber,
+            menu: MenuNavigator::new(&ROOT_MENU),
         }
     }
 
@@ -77,6 +97,14 @@ impl StateMachine {
                 info!("[State: Startup - Event: Ready] Startup -> Idle");
                 self.current_state = State::Idle;
             }
+            (_, Event::Key(SpecialKey::PrintScreen)) => {
+                info!(
+                    "[State: {:?} - Event: {event:?}] Capture screenshot",
+                    self.current_state
+                );
+                DISPLAY_CHANNEL.send(DisplayCommand::Screenshot).await;
+            }
+
             (State::Idle, Event::ButtonPressShortRelease) => {
                 error!(
                     "[State: Idle - Event: ButtonPressShortRelease] Button Press SHORT RELEASE: Idle -> Processing"
@@ -84,11 +112,11 @@ impl StateMachine {
                 self.current_state = State::Processing;
             }
 
-            (State::Idle, Event::ButtonPressLongRelease) => {
-                error!(
-                    "[State: Idle - Event: ButtonPressLongRelease] Button Press LONG RELEASE: Idle -> Processing"
-                );
-                self.current_state = State::Processing;
+            (State::Idle, Event::ButtonPressLongRelease | Event::Key(SpecialKey::Enter)) => {
+                info!("[State: Idle - Event: {event:?}] Open menu: Idle -> Menu");
+                self.menu.reset();
+                self.current_state = State::Menu;
+                self.show_menu().await;
             }
 
             (State::Idle, Event::ButtonPressLongHold) => {
@@ -105,6 +133,51 @@ impl StateMachine {
                 self.current_state = State::Processing;
             }
 
+            (
+                State::Menu,
+                Event::ButtonPressShortRelease
+                | Event::ButtonPressLongRelease
+                | Event::ButtonPressLongHold
+                | Event::Key(_),
+            ) => {
+                let input = match event {
+                    Event::ButtonPressShortRelease => {
+                        Some(MenuInput::from(PressType::ShortRelease))
+                    }
+                    Event::ButtonPressLongRelease => Some(MenuInput::from(PressType::LongRelease)),
+                    Event::ButtonPressLongHold => Some(MenuInput::from(PressType::LongHold)),
+                    Event::Key(key) => MenuInput::from_key(key),
+                    _ => None,
+                };
+                if let Some(input) = input {
+                    self.handle_menu_input(input).await;
+                }
+            }
+
+            (State::Console, Event::ButtonPressLongHold | Event::Key(SpecialKey::Escape)) => {
+                info!("[State: Console - Event: {event:?}] Close console: Console -> Idle");
+                self.current_state = State::Idle;
+                DISPLAY_CHANNEL.send(DisplayCommand::Idle).await;
+            }
+
+            (
+                State::Console,
+                Event::ButtonPressShortRelease | Event::ButtonPressLongRelease | Event::Key(_),
+            ) => {
+                let scroll = match event {
+                    Event::ButtonPressShortRelease => Some(ConsoleScroll::PageUp),
+                    Event::ButtonPressLongRelease => Some(ConsoleScroll::Bottom),
+                    Event::Key(SpecialKey::Up) => Some(ConsoleScroll::LineUp),
+                    Event::Key(SpecialKey::Down) => Some(ConsoleScroll::LineDown),
+                    _ => None,
+                };
+                if let Some(scroll) = scroll {
+                    DISPLAY_CHANNEL
+                        .send(DisplayCommand::ConsoleScroll(scroll))
+                        .await;
+                }
+            }
+
             (State::Processing, Event::Nothing) => {
                 info!("[State: Processing - Event: Nothing] Nothing: Processing -> Idle");
                 self.current_state = State::Idle;
@@ -123,6 +196,44 @@ impl StateMachine {
         }
     }
 
+    /// Pass navigation input to the menu and react on the outcome
+    ///
+    /// * `input` - Menu navigation input
+    async fn handle_menu_input(&mut self, input: MenuInput) {
+        match self.menu.handle(input) {
+            MenuOutcome::Redraw => self.show_menu().await,
+            MenuOutcome::Action(MenuAction::RestoreDefaults) => {
+                warn!("[State: Menu] Restoring default settings");
+                settings::replace(Settings::defaults());
+                SETTINGS_SAVE_SIGNAL.signal(());
+                self.show_menu().await;
+            }
+            MenuOutcome::Action(MenuAction::Screenshot) => {
+                info!("[State: Menu] Capture screenshot");
+                DISPLAY_CHANNEL.send(DisplayCommand::Screenshot).await;
+            }
+            MenuOutcome::Action(MenuAction::OpenConsole) => {
+                info!("[State: Menu] Open console: Menu -> Console");
+                self.menu.reset();
+                self.current_state = State::Console;
+                DISPLAY_CHANNEL.send(DisplayCommand::Console).await;
+            }
+            MenuOutcome::Action(MenuAction::Exit) | MenuOutcome::Closed => {
+                info!("[State: Menu] Close menu: Menu -> Idle");
+                self.menu.reset();
+                self.current_state = State::Idle;
+                DISPLAY_CHANNEL.send(DisplayCommand::Idle).await;
+            }
+        }
+    }
+
+    /// Send the current menu state to the display
+    async fn show_menu(&self) {
+        DISPLAY_CHANNEL
+            .send(DisplayCommand::Menu(self.menu.view()))
+            .await;
+    }
+
     /// Get the current event - based on various conditions and inputs
     /// Return event to the state machine for determining the next state
     fn get_current_event(&mut self) -> Event {
@@ -142,6 +253,12 @@ impl StateMachine {
                 }
             },
         }
+        // Check keyboard event (printable characters are not used by the state machine)
+        while let Some(message) = self.keyboard_pubsub_subscriber.try_next_message_pure() {
+            if let KeyboardMessage::Special(key) = message {
+                return Event::Key(key);
+            }
+        }
         // No events occurred, return a nothing event
         Event::Nothing
     }
@@ -0,0 +1,85 @@
+#!/usr/bin/env python3
+"""Extract screenshots streamed by the firmware over serial.
+
+The firmware prints the framebuffer as a base64 encoded 16 bpp BMP between
+`-----BEGIN SCREENSHOT WxH SIZE-----` and `-----END SCREENSHOT-----` lines
+(menu "System > Screenshot" or Fn + P on the keyboard).
+
+Usage:
+    espflash monitor | tee serial.log
+    python3 tools/screenshot.py serial.log            # -> screenshot-1.bmp, ...
+    python3 tools/screenshot.py serial.log --png      # needs Pillow
+
+Log lines may carry ANSI colour codes or timestamps before the payload,
+only the base64 characters of each line are used.
+"""
+
+import argparse
+import base64
+import re
+import sys
+from pathlib import Path
+
+BEGIN = re.compile(r"-----BEGIN SCREENSHOT (\d+)x(\d+) (\d+)-----")
+END = "-----END SCREENSHOT-----"
+BASE64_LINE = re.compile(r"([A-Za-z0-9+/]+={0,2})\s*$")
+
+
+def extract(lines):
+    """Yield (width, height, bmp bytes) for every complete capture in the log."""
+    capture = None
+    for line in lines:
+        begin = BEGIN.search(line)
+        if begin:
+            width, height, size = (int(value) for value in begin.groups())
+            capture = (width, height, size, [])
+            continue
+        if capture is None:
+            continue
+        if END in line:
+            width, height, size, chunks = capture
+            capture = None
+            data = base64.b64decode("".join(chunks))
+            if len(data) != size:
+                print(
+                    f"warning: skipping truncated capture ({len(data)} of {size} bytes)",
+                    file=sys.stderr,
+                )
+                continue
+            yield width, height, data
+            continue
+        payload = BASE64_LINE.search(line)
+        if payload:
+            capture[3].append(payload.group(1))
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("log", nargs="?", help="serial log file (default: stdin)")
+    parser.add_argument("-o", "--output", default="screenshot", help="output file prefix")
+    parser.add_argument("--png", action="store_true", help="convert to PNG (requires Pillow)")
+    args = parser.parse_args()
+
+    source = open(args.log, encoding="utf-8", errors="replace") if args.log else sys.stdin
+    count = 0
+    with source:
+        for count, (width, height, data) in enumerate(extract(source), start=1):
+            path = Path(f"{args.output}-{count}.bmp")
+            path.write_bytes(data)
+            if args.png:
+                from PIL import Image
+
+                png = path.with_suffix(".png")
+                Image.open(path).convert("RGB").save(png)
+                path.unlink()
+                path = png
+            print(f"{path}: {width}x{height}")
+
+    if count == 0:
+        print("no screenshots found", file=sys.stderr)
+        return 1
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,2 @@
+// Placeholder application, the firmware itself is built with cargo
+void app_main(void) {}
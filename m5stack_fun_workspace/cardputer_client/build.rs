@@ -1,8 +1,22 @@
-fn main() {
+use anyhow::{Context, Result};
+use std::env;
+use std::path::Path;
+
+fn main() -> Result<()> {
     linker_be_nice();
     println!("cargo:rustc-link-arg=-Tdefmt.x");
     // make sure linkall.x is the last linker script (otherwise might cause problems with flip-link)
     println!("cargo:rustc-link-arg=-Tlinkall.x");
+
+    // Значения по умолчанию для конфигурации, изменяемой по BLE (AppConfig)
+    let out_dir = env::var("OUT_DIR").context("OUT_DIR not set")?;
+    config_codegen::ConfigBuilder::from_schema_file("config.schema.json")?
+        .merge_optional_file("config.json")?
+        .merge_profile("config.json")?
+        .merge_env()?
+        .write(Path::new(&out_dir).join("config.rs"))?;
+
+    Ok(())
 }
 
 fn linker_be_nice() {
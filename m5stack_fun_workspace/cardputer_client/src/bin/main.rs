@@ -7,17 +7,40 @@
 )]
 
 use bt_hci::controller::ExternalController;
-use defmt::info;
+use cardputer_client::ble::BondStore;
+use cardputer_client::flash::SharedFlash;
+use cardputer_client::keyboard::Keyboard;
+use defmt::{info, warn};
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
 use embassy_executor::Spawner;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
 use embassy_time::{Duration, Timer};
+use esp_hal::Async;
 use esp_hal::clock::CpuClock;
+use esp_hal::dma::{DmaRxBuf, DmaTxBuf};
+use esp_hal::gpio::{Level, Output, OutputConfig};
+use esp_hal::i2c::master::{Config as I2cConfig, I2c};
+use esp_hal::spi::{
+    Mode,
+    master::{Config as SpiConfig, Spi, SpiDmaBus},
+};
+use esp_hal::time::Rate;
 use esp_hal::timer::timg::TimerGroup;
 use esp_println as _;
 use esp_radio::ble::controller::BleConnector;
+use esp_storage::FlashStorage;
+use static_cell::StaticCell;
 
+/// Time for the log to flush before a reset, ms
+const RESET_DELAY_MS: u64 = 100;
+
+// Перезагрузка вместо зависания: непроверенный образ OTA откатывается загрузчиком
 #[panic_handler]
-fn panic(_: &core::panic::PanicInfo) -> ! {
-    loop {}
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    // esp-println пишет синхронно, сообщение уходит до перезагрузки
+    defmt::error!("panic: {}", defmt::Display2Format(info));
+    esp_hal::system::software_reset()
 }
 
 // This creates a default app-descriptor required by the esp-idf bootloader.
@@ -45,15 +68,101 @@ async fn main(spawner: Spawner) -> ! {
     // Инициализация HCI контроллера
     let ble_controller = ExternalController::<_, 20>::new(transport);
 
-    // TODO: Spawn some tasks
-    let _ = spawner;
+    // SHTC3 на Grove порту: SDA - G2, SCL - G1
+    let i2c = I2c::new(
+        peripherals.I2C0,
+        I2cConfig::default().with_frequency(Rate::from_khz(400)),
+    )
+    .expect("Failed to init sensor I2C")
+    .with_sda(peripherals.GPIO2)
+    .with_scl(peripherals.GPIO1)
+    .into_async();
+    let battery = cardputer_client::sensors::Battery::new(peripherals.ADC1, peripherals.GPIO10);
+
+    spawner
+        .spawn(cardputer_client::sensors::start_sensors(i2c, battery))
+        .expect("Failed to spawn sensors task");
+
+    // Экран: ключ сопряжения для DisplayOnly
+    #[allow(clippy::manual_div_ceil)]
+    let (rx_buffer, rx_descriptors, tx_buffer, tx_descriptors) = esp_hal::dma_buffers!(4, 32_000);
+    let dma_rx_buf =
+        DmaRxBuf::new(rx_descriptors, rx_buffer).expect("Failed to create DMA RX buffer");
+    let dma_tx_buf =
+        DmaTxBuf::new(tx_descriptors, tx_buffer).expect("Failed to create DMA TX buffer");
+
+    let spi = Spi::new(
+        peripherals.SPI2,
+        SpiConfig::default()
+            .with_frequency(Rate::from_mhz(60))
+            .with_mode(Mode::_0),
+    )
+    .expect("Failed to init display SPI")
+    .with_sck(peripherals.GPIO36)
+    .with_mosi(peripherals.GPIO35)
+    .with_dma(peripherals.DMA_CH0)
+    .with_buffers(dma_rx_buf, dma_tx_buf)
+    .into_async();
+
+    static SPI_BUS: StaticCell<Mutex<NoopRawMutex, SpiDmaBus<'static, Async>>> = StaticCell::new();
+    let spi_bus = SPI_BUS.init(Mutex::new(spi));
+
+    let display_cs = Output::new(peripherals.GPIO37, Level::High, OutputConfig::default());
+    let display_spi = SpiDevice::new(spi_bus, display_cs);
+    let display_rst = Output::new(peripherals.GPIO33, Level::Low, OutputConfig::default());
+    let display_dc = Output::new(peripherals.GPIO34, Level::Low, OutputConfig::default());
+    let backlight = Output::new(peripherals.GPIO38, Level::Low, OutputConfig::default());
+
+    spawner
+        .spawn(cardputer_client::display::start_display(
+            display_spi,
+            display_dc,
+            display_rst,
+            backlight,
+        ))
+        .expect("Failed to spawn display task");
+
+    // Клавиатура: список устройств центральной роли на экране
+    let keyboard = Keyboard::new(
+        peripherals.GPIO8,
+        peripherals.GPIO9,
+        peripherals.GPIO11,
+        peripherals.GPIO13,
+        peripherals.GPIO15,
+        peripherals.GPIO3,
+        peripherals.GPIO4,
+        peripherals.GPIO5,
+        peripherals.GPIO6,
+        peripherals.GPIO7,
+    );
+    spawner
+        .spawn(cardputer_client::keyboard::start_keyboard(keyboard))
+        .expect("Failed to spawn keyboard task");
+
+    // Flash общий для ключей сопряжения и обновления прошивки
+    cardputer_client::flash::init(FlashStorage::new(peripherals.FLASH));
+    spawner
+        .spawn(cardputer_client::ota::ota_task())
+        .expect("Failed to spawn OTA task");
+
+    // Ключи сопряженных устройств
+    let bonds = match BondStore::new(SharedFlash) {
+        Ok(store) => {
+            info!("Bonds restored from flash: {}", store.bonds().len());
+            Some(store)
+        }
+        Err(e) => {
+            warn!("Bond storage is not available: {:?}", e);
+            None
+        }
+    };
 
     // Запуск стэка BLE
-    cardputer_client::ble::run(ble_controller).await;
-    loop {
-        info!("Hello from Cardputer-Client!");
-        Timer::after(Duration::from_secs(5)).await;
-    }
+    let e = cardputer_client::ble::run(ble_controller, bonds, &ESP_APP_DESC).await;
+    // Стек не восстановился сам - перезагрузка поднимает радио с нуля
+    warn!("BLE stack failed: {:?}, resetting", e);
+    Timer::after(Duration::from_millis(RESET_DELAY_MS)).await;
 
     // for inspiration have a look at the examples at https://github.com/esp-rs/esp-hal/tree/esp-hal-v1.0.0/examples/src/bin
+    esp_hal::system::software_reset()
 }
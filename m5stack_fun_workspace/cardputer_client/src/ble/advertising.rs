@@ -0,0 +1,281 @@
+//! Advertising payload - advertising data and scan response built from the configuration
+//!
+//! AD structures in priority order:
+//! 1. Flags
+//! 2. Manufacturer specific data - sensor snapshot (beacon-style), see [`SNAPSHOT_FORMAT`]
+//! 3. 16-bit service UUIDs - Environmental Sensing, Battery
+//! 4. TX power level (`adv_tx_power` runtime configuration)
+//! 5. 128-bit service UUIDs - Nordic UART
+//! 6. Device name (`device_name` runtime configuration)
+//!
+//! Every structure goes into the advertising data if it fits, otherwise into the scan
+//! response. A name that fits nowhere is shortened. The payload is rebuilt on every
+//! measurement and configuration commit (see [`PayloadChanges`]).
+
+use super::{nus_service, sensor_service};
+use crate::config::{CONFIG_CHANGED, DeviceConfig};
+use crate::sensors::{MEASUREMENT, Measurement};
+use defmt::warn;
+use embassy_futures::select::select;
+use embassy_sync::watch::DynReceiver;
+use embassy_time::Duration;
+use shared_lib::SensorReading;
+use trouble_host::prelude::*;
+
+/// Maximal legacy advertising data (and scan response) length, bytes
+const AD_MAX: usize = 31;
+
+/// AD structure header: length (1) + type (1)
+const AD_HEADER_LEN: usize = 2;
+
+/// Company identifier of the manufacturer data: reserved for testing
+pub const COMPANY_ID: u16 = 0xFFFF;
+
+/// Manufacturer data format: version (1), temperature (i16, 0.01 °C), humidity (u16, 0.01 %),
+/// voltage (u16, 1/64 V); little-endian, unknown values as in the Environmental Sensing Service
+pub const SNAPSHOT_FORMAT: u8 = 1;
+
+/// AD type: TX power level
+const AD_TYPE_TX_POWER: u8 = 0x0A;
+
+/// Transmit power levels of the controller, ascending
+const TX_POWER_LEVELS: [TxPower; 20] = [
+    TxPower::Minus40dBm,
+    TxPower::Minus20dBm,
+    TxPower::Minus16dBm,
+    TxPower::Minus12dBm,
+    TxPower::Minus8dBm,
+    TxPower::Minus4dBm,
+    TxPower::ZerodBm,
+    TxPower::Plus2dBm,
+    TxPower::Plus3dBm,
+    TxPower::Plus4dBm,
+    TxPower::Plus5dBm,
+    TxPower::Plus6dBm,
+    TxPower::Plus7dBm,
+    TxPower::Plus8dBm,
+    TxPower::Plus10dBm,
+    TxPower::Plus12dBm,
+    TxPower::Plus14dBm,
+    TxPower::Plus16dBm,
+    TxPower::Plus18dBm,
+    TxPower::Plus20dBm,
+];
+
+/// 16-bit UUIDs of the advertised services, little-endian
+const SERVICE_UUIDS_16: [[u8; 2]; 2] = [
+    service::ENVIRONMENTAL_SENSING.to_le_bytes(),
+    service::BATTERY.to_le_bytes(),
+];
+
+/// 128-bit UUIDs of the advertised services, little-endian
+const SERVICE_UUIDS_128: [[u8; 16]; 1] = [nus_service::SERVICE_UUID.to_le_bytes()];
+
+/// Advertising data and scan response of one advertising round
+pub struct Payload {
+    /// Advertising data
+    adv: Section,
+    /// Scan response
+    scan: Section,
+    /// Advertising interval
+    interval: Duration,
+    /// Transmit power
+    tx_power: TxPower,
+}
+
+impl Payload {
+    /// Build the payload
+    ///
+    /// * `config` - Committed configuration
+    /// * `measurement` - Latest measurement, `None` - nothing measured yet
+    pub fn new(config: &DeviceConfig, measurement: Option<Measurement>) -> Self {
+        let (temperature, humidity, voltage) = measurement
+            .map(SensorReading::from)
+            .as_ref()
+            .map_or(sensor_service::UNKNOWN_VALUES, sensor_service::values);
+        let [t0, t1] = temperature.to_le_bytes();
+        let [h0, h1] = humidity.to_le_bytes();
+        let [v0, v1] = voltage.to_le_bytes();
+        let snapshot = [SNAPSHOT_FORMAT, t0, t1, h0, h1, v0, v1];
+        let tx_power = tx_power(config.adv_tx_power_dbm);
+        // AD содержит фактически установленный уровень, а не запрошенный
+        let tx_power_dbm = (tx_power as i8).to_le_bytes();
+
+        let mut payload = Self {
+            adv: Section::new(),
+            scan: Section::new(),
+            interval: Duration::from_millis(u64::from(config.adv_interval_ms)),
+            tx_power,
+        };
+        payload.place(&AdStructure::Flags(
+            LE_GENERAL_DISCOVERABLE | BR_EDR_NOT_SUPPORTED,
+        ));
+        payload.place(&AdStructure::ManufacturerSpecificData {
+            company_identifier: COMPANY_ID,
+            payload: &snapshot,
+        });
+        payload.place(&AdStructure::ServiceUuids16(&SERVICE_UUIDS_16));
+        payload.place(&AdStructure::Unknown {
+            ty: AD_TYPE_TX_POWER,
+            data: &tx_power_dbm,
+        });
+        payload.place(&AdStructure::ServiceUuids128(&SERVICE_UUIDS_128));
+        payload.place_name(config.device_name.as_bytes());
+        payload
+    }
+
+    /// Advertising parameters: interval and TX power
+    pub fn parameters(&self) -> AdvertisementParameters {
+        AdvertisementParameters {
+            interval_min: self.interval,
+            interval_max: self.interval,
+            tx_power: self.tx_power,
+            ..Default::default()
+        }
+    }
+
+    /// Connectable advertisement with the scan response
+    pub fn advertisement(&self) -> Advertisement<'_> {
+        Advertisement::ConnectableScannableUndirected {
+            adv_data: self.adv.data(),
+            scan_data: self.scan.data(),
+        }
+    }
+
+    /// Put a structure into the advertising data or the scan response, skipped if it fits
+    /// nowhere
+    ///
+    /// * `structure` - AD structure
+    fn place(&mut self, structure: &AdStructure<'_>) {
+        if !self.adv.push(structure) {
+            self.scan.push(structure);
+        }
+    }
+
+    /// Put the complete name, or the shortened name into the section with more free space
+    ///
+    /// * `name` - Device name
+    fn place_name(&mut self, name: &[u8]) {
+        if self.adv.push(&AdStructure::CompleteLocalName(name))
+            || self.scan.push(&AdStructure::CompleteLocalName(name))
+        {
+            return;
+        }
+        let section = if self.adv.free() >= self.scan.free() {
+            &mut self.adv
+        } else {
+            &mut self.scan
+        };
+        let len = section.free().saturating_sub(AD_HEADER_LEN).min(name.len());
+        if let Some(short) = name.get(..len)
+            && !short.is_empty()
+        {
+            section.push(&AdStructure::ShortenedLocalName(short));
+        }
+    }
+}
+
+/// Highest transmit power level not above the requested one, the lowest level if there is none
+///
+/// * `dbm` - Requested transmit power, dBm
+fn tx_power(dbm: i32) -> TxPower {
+    TX_POWER_LEVELS
+        .into_iter()
+        .rev()
+        .find(|level| i32::from(*level as i8) <= dbm)
+        .unwrap_or(TxPower::Minus40dBm)
+}
+
+/// Advertising data or scan response
+struct Section {
+    /// Encoded AD structures
+    buffer: [u8; AD_MAX],
+    /// Used length
+    len: usize,
+}
+
+impl Section {
+    /// Empty section
+    fn new() -> Self {
+        Self {
+            buffer: [0; AD_MAX],
+            len: 0,
+        }
+    }
+
+    /// Append a structure if it fits
+    ///
+    /// * `structure` - AD structure
+    fn push(&mut self, structure: &AdStructure<'_>) -> bool {
+        let Some(free) = self.buffer.get_mut(self.len..) else {
+            return false;
+        };
+        match AdStructure::encode_slice(core::slice::from_ref(structure), free) {
+            Ok(len) => {
+                self.len += len;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Free space, bytes
+    fn free(&self) -> usize {
+        AD_MAX - self.len
+    }
+
+    /// Encoded structures
+    fn data(&self) -> &[u8] {
+        self.buffer.get(..self.len).unwrap_or_default()
+    }
+}
+
+/// Changes that require a new payload: measurements and configuration commits
+pub struct PayloadChanges {
+    /// Measurement changes, `None` - no free receiver, the snapshot is not refreshed
+    measurement: Option<DynReceiver<'static, Measurement>>,
+    /// Configuration changes, `None` - no free receiver
+    config: Option<DynReceiver<'static, u32>>,
+}
+
+impl PayloadChanges {
+    /// Start observing the changes, the current values are considered seen
+    pub fn new() -> Self {
+        let mut measurement = MEASUREMENT.dyn_receiver();
+        let mut config = CONFIG_CHANGED.dyn_receiver();
+        if measurement.is_none() || config.is_none() {
+            warn!("[adv] no free receiver, the payload is not refreshed");
+        }
+        if let Some(receiver) = measurement.as_mut() {
+            receiver.try_changed();
+        }
+        if let Some(receiver) = config.as_mut() {
+            receiver.try_changed();
+        }
+        Self {
+            measurement,
+            config,
+        }
+    }
+
+    /// Wait for a new measurement or configuration
+    pub async fn changed(&mut self) {
+        let Self {
+            measurement,
+            config,
+        } = self;
+        let measurement = async {
+            match measurement {
+                Some(receiver) => drop(receiver.changed().await),
+                None => core::future::pending().await,
+            }
+        };
+        let config = async {
+            match config {
+                Some(receiver) => drop(receiver.changed().await),
+                None => core::future::pending().await,
+            }
+        };
+        select(measurement, config).await;
+    }
+}
@@ -0,0 +1,41 @@
+//! Battery Service (0x180F) - charge level for generic apps and OS battery indicators
+//!
+//! * `level` - Battery Level (0x2A19), uint8, %, estimated from the battery voltage
+//!
+//! The level is updated with every measurement by [`super::sensor_service::notify_task`].
+
+use super::Server;
+use crate::sensors::{self, Measurement};
+use trouble_host::prelude::*;
+
+/// Battery service
+#[gatt_service(uuid = service::BATTERY)]
+pub struct BatteryService {
+    #[characteristic(uuid = characteristic::BATTERY_LEVEL, read, notify)]
+    pub level: u8,
+}
+
+/// Update the battery level, a failed ADC reading keeps the previous level.
+/// Returns `false` if the connection could not be notified
+///
+/// * `server` - GATT server
+/// * `conn` - Connection to notify
+/// * `measurement` - Measurement
+/// * `notify` - Notify the central, `false` - only update the value
+pub async fn update<P: PacketPool>(
+    server: &Server<'_>,
+    conn: &GattConnection<'_, '_, P>,
+    measurement: &Measurement,
+    notify: bool,
+) -> bool {
+    let Some(mv) = measurement.battery_mv else {
+        return true;
+    };
+    let level = server.battery_service.level;
+    let percent = sensors::level_percent(mv);
+    if notify {
+        level.notify(conn, &percent).await.is_ok()
+    } else {
+        server.set(&level, &percent).is_ok()
+    }
+}
@@ -0,0 +1,149 @@
+//! Bond storage - keys of the paired centrals in the `nvs` data partition
+//!
+//! The firmware does not use ESP-IDF NVS, the partition holds a `config-store` record log with
+//! up to [`BONDS_MAX`] bonds. When a new central bonds and the list is full, the oldest bond is
+//! forgotten.
+
+use crate::flash::SharedFlash;
+use config_store::{Reader, Record, RecordLog, Writer};
+use defmt::warn;
+use esp_bootloader_esp_idf::partitions::{self, DataPartitionSubType, PartitionType};
+use heapless::Vec;
+use trouble_host::prelude::*;
+
+/// Maximal number of stored bonds
+pub const BONDS_MAX: usize = 4;
+
+/// Bond storage errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BondStoreError {
+    /// Partition table could not be read
+    PartitionTable,
+    /// There is no `nvs` partition in the partition table
+    NoPartition,
+    /// Flash read/write failed or the partition layout is not usable
+    Flash,
+}
+
+/// Bonds in flash
+pub struct BondStore {
+    /// Bond records in the `nvs` partition
+    log: RecordLog<SharedFlash>,
+    /// Stored bonds, the oldest first
+    bonds: Bonds,
+}
+
+impl BondStore {
+    /// Bond storage constructor - locates the `nvs` partition and loads the bonds
+    ///
+    /// * `flash` - Shared flash
+    pub fn new(mut flash: SharedFlash) -> Result<Self, BondStoreError> {
+        let mut table_buffer = [0_u8; partitions::PARTITION_TABLE_MAX_LEN];
+        let table = partitions::read_partition_table(&mut flash, &mut table_buffer)
+            .map_err(|_| BondStoreError::PartitionTable)?;
+        let partition = table
+            .find_partition(PartitionType::Data(DataPartitionSubType::Nvs))
+            .map_err(|_| BondStoreError::PartitionTable)?
+            .ok_or(BondStoreError::NoPartition)?;
+
+        let mut log = RecordLog::new(flash, partition.offset(), partition.len())
+            .map_err(|_| BondStoreError::Flash)?;
+        let bonds = match log.load::<Bonds>() {
+            Ok(loaded) => loaded.map(|loaded| loaded.value).unwrap_or_default(),
+            Err(e) => {
+                warn!(
+                    "[bonds] stored bonds are not usable: {:?}",
+                    defmt::Debug2Format(&e)
+                );
+                Bonds::default()
+            }
+        };
+        Ok(Self { log, bonds })
+    }
+
+    /// Stored bonds
+    pub fn bonds(&self) -> &[BondInformation] {
+        &self.bonds.0
+    }
+
+    /// Store a new bond, replaces the previous bond of the same central
+    ///
+    /// * `bond` - Keys from the completed pairing
+    pub fn save(&mut self, bond: BondInformation) -> Result<(), BondStoreError> {
+        let bonds = &mut self.bonds.0;
+        bonds.retain(|stored| stored.identity.bd_addr != bond.identity.bd_addr);
+        if bonds.is_full() {
+            bonds.remove(0);
+        }
+        // Место освобождено выше
+        let _ = bonds.push(bond);
+        self.log
+            .save(&self.bonds)
+            .map_err(|_| BondStoreError::Flash)
+    }
+}
+
+/// Bond list record
+#[derive(Default)]
+struct Bonds(Vec<BondInformation, BONDS_MAX>);
+
+/// Bond flags: authenticated (MITM protected) keys
+const FLAG_AUTHENTICATED: u8 = 0x01;
+
+/// Bond flags: the central distributed its identity resolving key
+const FLAG_IRK: u8 = 0x02;
+
+impl Record for Bonds {
+    /// 1 - address, flags, LTK and IRK per bond
+    const VERSION: u16 = 1;
+
+    fn encode(&self, payload: &mut [u8]) -> Option<usize> {
+        let mut writer = Writer::new(payload);
+        writer.u8(u8::try_from(self.0.len()).ok()?)?;
+        for bond in &self.0 {
+            let mut flags = 0;
+            if bond.security_level == SecurityLevel::EncryptedAuthenticated {
+                flags |= FLAG_AUTHENTICATED;
+            }
+            if bond.identity.irk.is_some() {
+                flags |= FLAG_IRK;
+            }
+            let irk = bond
+                .identity
+                .irk
+                .map(|irk| irk.to_le_bytes())
+                .unwrap_or_default();
+            writer
+                .bytes(bond.identity.bd_addr.raw())?
+                .u8(flags)?
+                .bytes(&bond.ltk.to_le_bytes())?
+                .bytes(&irk)?;
+        }
+        Some(writer.len())
+    }
+
+    fn decode(_version: u16, payload: &[u8]) -> Option<Self> {
+        let mut reader = Reader::new(payload);
+        let mut bonds = Vec::new();
+        for _ in 0..reader.u8()? {
+            let address = BdAddr::new(reader.bytes::<6>()?);
+            let flags = reader.u8()?;
+            let ltk = LongTermKey::from_le_bytes(reader.bytes::<16>()?);
+            let irk = IdentityResolvingKey::from_le_bytes(reader.bytes::<16>()?);
+
+            let security_level = if flags & FLAG_AUTHENTICATED != 0 {
+                SecurityLevel::EncryptedAuthenticated
+            } else {
+                SecurityLevel::Encrypted
+            };
+            let identity = Identity {
+                bd_addr: address,
+                irk: (flags & FLAG_IRK != 0).then_some(irk),
+            };
+            bonds
+                .push(BondInformation::new(identity, ltk, security_level, true))
+                .ok()?;
+        }
+        Some(Self(bonds))
+    }
+}
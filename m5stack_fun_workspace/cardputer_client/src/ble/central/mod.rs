@@ -0,0 +1,376 @@
+//! Central role - scanning and a GATT client for debugging other BLE devices
+//!
+//! Commands come from the [`crate::shell`] and the keyboard of the device scan screen
+//! ([`crate::display`]) through [`CENTRAL_COMMANDS`], results are written as log lines and
+//! shown on the screen ([`state`], [`for_each_device`]). One peripheral at a time:
+//! * `Scan` collects advertising reports matching the filter for [`SCAN_MS`]
+//! * `Connect` connects to a device of the list, then `Discover` selects a service by UUID and
+//!   `Read`, `Write`, `Subscribe` address its characteristics by UUID
+//!
+//! The central connection shares the controller with the peripheral connections.
+
+mod scan;
+
+use crate::shell;
+use core::cell::Cell;
+use core::fmt;
+use defmt::{info, warn};
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer, with_timeout};
+use heapless::Vec;
+use trouble_host::prelude::*;
+
+// Public re-export of specifics that are available outside of module
+pub use scan::{DEVICES_MAX, Device, NAME_MAX, Name, ScanFilter, ScanHandler, for_each_device};
+
+/// Scan duration, ms
+pub const SCAN_MS: u64 = 5_000;
+
+/// Maximal written value length, bytes
+pub const VALUE_MAX: usize = 64;
+
+/// Connection attempt timeout, ms
+const CONNECT_TIMEOUT_MS: u64 = 5_000;
+
+/// Maximal number of services of the same UUID
+const SERVICES_MAX: usize = 4;
+
+/// Read buffer length, bytes
+const READ_MAX: usize = 128;
+
+/// Commands waiting for the central
+const PENDING_COMMANDS: usize = 2;
+
+/// Central commands
+#[derive(Debug, Clone)]
+pub enum CentralCommand {
+    /// Scan for [`SCAN_MS`]
+    Scan(ScanFilter),
+    /// Connect to a device of the latest scan
+    Connect(usize),
+    /// Disconnect from the device
+    Disconnect,
+    /// Select a service of the connected device
+    Discover(Uuid),
+    /// Read a characteristic of the selected service
+    Read(Uuid),
+    /// Write a characteristic of the selected service
+    Write(Uuid, Vec<u8, VALUE_MAX>),
+    /// Log notifications of a characteristic of the selected service
+    Subscribe(Uuid),
+}
+
+/// Activity of the central
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum CentralState {
+    /// Waiting for a command
+    Idle,
+    /// Scan is running
+    Scanning,
+    /// Connection attempt is running
+    Connecting,
+    /// Connected to a device of the list
+    Connected,
+}
+
+/// Commands for the central
+pub static CENTRAL_COMMANDS: Channel<CriticalSectionRawMutex, CentralCommand, PENDING_COMMANDS> =
+    Channel::new();
+
+/// The state or the device list has changed, waited for by the display
+pub static CENTRAL_CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Current activity
+static STATE: Mutex<CriticalSectionRawMutex, Cell<CentralState>> =
+    Mutex::new(Cell::new(CentralState::Idle));
+
+/// Current activity of the central
+pub fn state() -> CentralState {
+    STATE.lock(Cell::get)
+}
+
+/// Change the activity and wake the display
+///
+/// * `state` - New activity
+fn set_state(state: CentralState) {
+    STATE.lock(|current| current.set(state));
+    CENTRAL_CHANGED.signal(());
+}
+
+/// Execute the central commands
+///
+/// * `central` - Central role of the host
+/// * `stack` - BLE stack
+pub async fn run<C: Controller>(
+    mut central: Central<'_, C, DefaultPacketPool>,
+    stack: &Stack<'_, C, DefaultPacketPool>,
+) {
+    loop {
+        match CENTRAL_COMMANDS.receive().await {
+            CentralCommand::Scan(filter) => {
+                let mut scanner = Scanner::new(central);
+                scan(&mut scanner, filter).await;
+                central = scanner.into_inner();
+            }
+            CentralCommand::Connect(index) => {
+                let Some(device) = scan::device(index) else {
+                    shell::log(format_args!("[central] no device {index}, scan first"));
+                    continue;
+                };
+                connect(&mut central, stack, &device).await;
+                // Неудачная попытка и отключение одинаково возвращают роль в покой
+                set_state(CentralState::Idle);
+            }
+            _ => shell::log(format_args!("[central] not connected")),
+        }
+    }
+}
+
+/// Collect advertising reports for [`SCAN_MS`]
+///
+/// * `scanner` - Scanner
+/// * `filter` - Scan filter
+async fn scan<C: Controller>(scanner: &mut Scanner<'_, C, DefaultPacketPool>, filter: ScanFilter) {
+    let config = ScanConfig {
+        active: true,
+        ..Default::default()
+    };
+    scan::start(filter);
+    set_state(CentralState::Scanning);
+    match scanner.scan(&config).await {
+        Ok(_session) => {
+            shell::log(format_args!("[central] scanning"));
+            // Сканирование идёт, пока существует сессия
+            Timer::after_millis(SCAN_MS).await;
+        }
+        Err(e) => warn!("[central] scan error: {:?}", defmt::Debug2Format(&e)),
+    }
+    scan::stop();
+    set_state(CentralState::Idle);
+
+    let mut count = 0;
+    for_each_device(|_, _| count += 1);
+    shell::log(format_args!(
+        "[central] scan done, {count} devices, see `devices`"
+    ));
+}
+
+/// Connect to a device and serve the commands until it disconnects
+///
+/// * `central` - Central role of the host
+/// * `stack` - BLE stack
+/// * `device` - Found device
+async fn connect<C: Controller>(
+    central: &mut Central<'_, C, DefaultPacketPool>,
+    stack: &Stack<'_, C, DefaultPacketPool>,
+    device: &Device,
+) {
+    let filter_accept_list = [(device.kind, &device.addr)];
+    let config = ConnectConfig {
+        connect_params: Default::default(),
+        scan_config: ScanConfig {
+            filter_accept_list: &filter_accept_list,
+            ..Default::default()
+        },
+    };
+    shell::log(format_args!("[central] connecting to {device}"));
+    set_state(CentralState::Connecting);
+    let conn = match with_timeout(
+        Duration::from_millis(CONNECT_TIMEOUT_MS),
+        central.connect(&config),
+    )
+    .await
+    {
+        Ok(Ok(conn)) => conn,
+        Ok(Err(e)) => {
+            warn!("[central] connect error: {:?}", defmt::Debug2Format(&e));
+            shell::log(format_args!("[central] connection failed"));
+            return;
+        }
+        Err(_) => {
+            shell::log(format_args!("[central] connection timed out"));
+            return;
+        }
+    };
+    let client = match GattClient::<C, DefaultPacketPool, SERVICES_MAX>::new(stack, &conn).await {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("[central] GATT client error: {:?}", defmt::Debug2Format(&e));
+            shell::log(format_args!("[central] GATT client failed"));
+            return;
+        }
+    };
+    shell::log(format_args!("[central] connected"));
+    info!("[central] connected");
+    set_state(CentralState::Connected);
+
+    // Клиент обрабатывает ответы, пока подключение активно
+    select(client.task(), session(&client)).await;
+    conn.disconnect();
+    shell::log(format_args!("[central] disconnected"));
+}
+
+/// Serve the commands of a connected device, returns on `Disconnect`
+///
+/// * `client` - GATT client of the connection
+async fn session<C: Controller>(client: &GattClient<'_, C, DefaultPacketPool, SERVICES_MAX>) {
+    let mut service = None;
+    let mut listener = None;
+    loop {
+        let command = match listener.as_mut() {
+            Some(listener) => match select(CENTRAL_COMMANDS.receive(), listener.next()).await {
+                Either::First(command) => command,
+                Either::Second(notification) => {
+                    shell::log(format_args!(
+                        "[central] notification: {}",
+                        Hex(notification.as_ref())
+                    ));
+                    continue;
+                }
+            },
+            None => CENTRAL_COMMANDS.receive().await,
+        };
+
+        match command {
+            CentralCommand::Disconnect => return,
+            CentralCommand::Scan(_) | CentralCommand::Connect(_) => {
+                shell::log(format_args!("[central] disconnect first"));
+            }
+            CentralCommand::Discover(uuid) => {
+                service = match client.services_by_uuid(&uuid).await {
+                    Ok(services) => services.first().cloned(),
+                    Err(e) => {
+                        warn!("[central] discovery error: {:?}", defmt::Debug2Format(&e));
+                        None
+                    }
+                };
+                let found = if service.is_some() {
+                    "found"
+                } else {
+                    "not found"
+                };
+                shell::log(format_args!("[central] service {found}"));
+            }
+            CentralCommand::Read(uuid) => {
+                let Some(characteristic) = find(client, service.as_ref(), &uuid).await else {
+                    continue;
+                };
+                let mut buffer = [0_u8; READ_MAX];
+                match client
+                    .read_characteristic(&characteristic, &mut buffer)
+                    .await
+                {
+                    Ok(len) => shell::log(format_args!(
+                        "[central] value: {}",
+                        Hex(buffer.get(..len).unwrap_or_default())
+                    )),
+                    Err(e) => report("read", &e),
+                }
+            }
+            CentralCommand::Write(uuid, value) => {
+                let Some(characteristic) = find(client, service.as_ref(), &uuid).await else {
+                    continue;
+                };
+                match client.write_characteristic(&characteristic, &value).await {
+                    Ok(()) => shell::log(format_args!("[central] written")),
+                    Err(e) => report("write", &e),
+                }
+            }
+            CentralCommand::Subscribe(uuid) => {
+                let Some(characteristic) = find(client, service.as_ref(), &uuid).await else {
+                    continue;
+                };
+                // Новая подписка заменяет предыдущую
+                match client.subscribe(&characteristic, false).await {
+                    Ok(subscription) => {
+                        listener = Some(subscription);
+                        shell::log(format_args!("[central] subscribed"));
+                    }
+                    Err(e) => report("subscribe", &e),
+                }
+            }
+        }
+    }
+}
+
+/// Find a characteristic of the selected service
+///
+/// * `client` - GATT client of the connection
+/// * `service` - Selected service
+/// * `uuid` - Characteristic UUID
+async fn find<C: Controller>(
+    client: &GattClient<'_, C, DefaultPacketPool, SERVICES_MAX>,
+    service: Option<&ServiceHandle>,
+    uuid: &Uuid,
+) -> Option<Characteristic<[u8]>> {
+    let Some(service) = service else {
+        shell::log(format_args!("[central] discover a service first"));
+        return None;
+    };
+    match client.characteristic_by_uuid(service, uuid).await {
+        Ok(characteristic) => Some(characteristic),
+        Err(e) => {
+            report("characteristic lookup", &e);
+            None
+        }
+    }
+}
+
+/// Report a failed GATT operation
+///
+/// * `operation` - Operation name
+/// * `e` - Error
+fn report<E: fmt::Debug>(operation: &str, e: &E) {
+    warn!(
+        "[central] {} error: {:?}",
+        operation,
+        defmt::Debug2Format(e)
+    );
+    shell::log(format_args!("[central] {operation} failed: {e:?}"));
+}
+
+/// Parse a UUID: 16-bit (`180f`) or 128-bit (`6e400001-b5a3-f393-e0a9-e50e24dcca9e`)
+///
+/// * `text` - UUID text
+pub fn parse_uuid(text: &str) -> Option<Uuid> {
+    let mut bytes = [0_u8; 16];
+    let mut len = 0;
+    let digits = text.bytes().filter(|byte| *byte != b'-');
+    // Текст записан старшим байтом вперёд, UUID хранится младшим
+    let mut high = None;
+    for digit in digits {
+        let value = char::from(digit).to_digit(16)?;
+        match high.take() {
+            None => high = Some(value),
+            Some(high) => {
+                *bytes.get_mut(len)? = u8::try_from(high << 4 | value).ok()?;
+                len += 1;
+            }
+        }
+    }
+    if high.is_some() {
+        return None;
+    }
+    let bytes = bytes.get_mut(..len)?;
+    bytes.reverse();
+    match *bytes {
+        [low, high] => Some(Uuid::new_short(u16::from_le_bytes([low, high]))),
+        _ => Some(Uuid::new_long(bytes.try_into().ok()?)),
+    }
+}
+
+/// Bytes as hex text
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! Scanning - advertising reports filtered into the device list
+
+use super::CENTRAL_CHANGED;
+use bt_hci::param::LeAdvReportsIter;
+use core::cell::RefCell;
+use core::fmt;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use heapless::{String, Vec};
+use trouble_host::prelude::*;
+
+/// Maximal number of devices in the list
+pub const DEVICES_MAX: usize = 8;
+
+/// Maximal stored device name length, bytes
+pub const NAME_MAX: usize = 20;
+
+/// Device name
+pub type Name = String<NAME_MAX>;
+
+/// Scan filter, every set condition must match
+#[derive(Debug, Clone, Default)]
+pub struct ScanFilter {
+    /// Name starts with the text
+    pub name: Option<Name>,
+    /// Service UUID is advertised
+    pub service: Option<Uuid>,
+    /// Minimal signal strength, dBm
+    pub rssi_min: Option<i8>,
+}
+
+impl ScanFilter {
+    /// Check an advertising report
+    ///
+    /// * `name` - Advertised name, empty if there is none
+    /// * `services` - Advertised service UUIDs match the filter
+    /// * `rssi` - Signal strength, dBm
+    fn matches(&self, name: &str, services: bool, rssi: i8) -> bool {
+        self.name
+            .as_ref()
+            .is_none_or(|prefix| name.starts_with(prefix.as_str()))
+            && (self.service.is_none() || services)
+            && self.rssi_min.is_none_or(|min| rssi >= min)
+    }
+}
+
+/// Found device
+#[derive(Debug, Clone)]
+pub struct Device {
+    /// Address type
+    pub kind: AddrKind,
+    /// Address
+    pub addr: BdAddr,
+    /// Signal strength of the latest report, dBm
+    pub rssi: i8,
+    /// Advertised name, empty if there is none
+    pub name: Name,
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Адрес хранится в обратном порядке байт
+        let [a0, a1, a2, a3, a4, a5] = *self.addr.raw();
+        write!(
+            f,
+            "{a5:02X}:{a4:02X}:{a3:02X}:{a2:02X}:{a1:02X}:{a0:02X} {:4} dBm {}",
+            self.rssi, self.name
+        )
+    }
+}
+
+/// Scan filter and the devices found since the scan started
+struct ScanState {
+    /// Filter of the running scan, `None` - reports are ignored
+    filter: Option<ScanFilter>,
+    /// Found devices in the order of discovery
+    devices: Vec<Device, DEVICES_MAX>,
+}
+
+/// Devices of the latest scan
+static SCAN_STATE: Mutex<CriticalSectionRawMutex, RefCell<ScanState>> =
+    Mutex::new(RefCell::new(ScanState {
+        filter: None,
+        devices: Vec::new(),
+    }));
+
+/// Start collecting reports, the previous list is cleared
+///
+/// * `filter` - Scan filter
+pub(super) fn start(filter: ScanFilter) {
+    SCAN_STATE.lock(|state| {
+        let mut state = state.borrow_mut();
+        state.filter = Some(filter);
+        state.devices.clear();
+    });
+    CENTRAL_CHANGED.signal(());
+}
+
+/// Stop collecting reports, the list is kept
+pub(super) fn stop() {
+    SCAN_STATE.lock(|state| state.borrow_mut().filter = None);
+}
+
+/// Call a function for every found device
+///
+/// * `f` - Function of the list index and the device
+pub fn for_each_device(mut f: impl FnMut(usize, &Device)) {
+    SCAN_STATE.lock(|state| {
+        for (index, device) in state.borrow().devices.iter().enumerate() {
+            f(index, device);
+        }
+    });
+}
+
+/// Found device by its list index
+///
+/// * `index` - Index in the list
+pub(super) fn device(index: usize) -> Option<Device> {
+    SCAN_STATE.lock(|state| state.borrow().devices.get(index).cloned())
+}
+
+/// Runner event handler collecting the advertising reports
+pub struct ScanHandler;
+
+impl EventHandler for ScanHandler {
+    fn on_adv_reports(&self, mut reports: LeAdvReportsIter<'_>) {
+        while let Some(Ok(report)) = reports.next() {
+            // Экран перерисовывается только при изменении списка, а не на каждый пакет
+            let changed = SCAN_STATE.lock(|state| {
+                let mut state = state.borrow_mut();
+                let Some(filter) = state.filter.as_ref() else {
+                    return false;
+                };
+                let (name, services) = parse(report.data, filter.service.as_ref());
+                let matches = filter.matches(&name, services, report.rssi);
+                // Найденное устройство обновляется любым пакетом: scan response несёт имя,
+                // но не список сервисов и не проходит фильтр сам по себе
+                if let Some(device) = state
+                    .devices
+                    .iter_mut()
+                    .find(|device| device.addr == report.addr)
+                {
+                    device.rssi = report.rssi;
+                    if name.is_empty() || device.name == name {
+                        return false;
+                    }
+                    device.name = name;
+                    return true;
+                }
+                // Полный список - новые устройства не попадают
+                matches
+                    && state
+                        .devices
+                        .push(Device {
+                            kind: report.addr_kind,
+                            addr: report.addr,
+                            rssi: report.rssi,
+                            name,
+                        })
+                        .is_ok()
+            });
+            if changed {
+                CENTRAL_CHANGED.signal(());
+            }
+        }
+    }
+}
+
+/// Name and service match of advertising data
+///
+/// * `data` - Advertising data
+/// * `service` - Service UUID to look for
+fn parse(data: &[u8], service: Option<&Uuid>) -> (Name, bool) {
+    let mut name = Name::new();
+    let mut found = false;
+    for structure in AdStructure::decode(data).flatten() {
+        match structure {
+            AdStructure::CompleteLocalName(text) | AdStructure::ShortenedLocalName(text) => {
+                name.clear();
+                for byte in text.iter().take(NAME_MAX) {
+                    let _ = name.push(char::from(*byte));
+                }
+            }
+            AdStructure::ServiceUuids16(uuids) => {
+                found |= uuids
+                    .iter()
+                    .any(|uuid| service.is_some_and(|service| service.as_raw() == uuid));
+            }
+            AdStructure::ServiceUuids128(uuids) => {
+                found |= uuids
+                    .iter()
+                    .any(|uuid| service.is_some_and(|service| service.as_raw() == uuid));
+            }
+            _ => {}
+        }
+    }
+    (name, found)
+}
@@ -0,0 +1,197 @@
+//! Configuration service - export/import of the device configuration
+//!
+//! * `config` - committed configuration blob (see [`crate::config`]), notified on every commit
+//! * `staging` - write entries to stage them (only changed entries are required), read back the
+//!   staged configuration. Invalid writes are rejected with an ATT error and change nothing
+//! * `control` - write [`CONTROL_COMMIT`], [`CONTROL_ROLLBACK`] or [`CONTROL_DEFAULTS`]
+//! * `generation` - generation of the committed configuration, notified on every commit
+//!
+//! Staged changes belong to the connection and are dropped on disconnect.
+//! Blobs longer than 20 bytes need a larger ATT MTU (or a long write).
+
+use super::{Server, security};
+use crate::config::{self, CONFIG_BLOB_LEN, CONFIG_CHANGED, ConfigError, ConfigTransaction};
+use crate::shell;
+use defmt::{info, warn};
+use trouble_host::prelude::*;
+
+/// Commit the staged configuration
+pub const CONTROL_COMMIT: u8 = 0x01;
+
+/// Drop the staged changes
+pub const CONTROL_ROLLBACK: u8 = 0x02;
+
+/// Stage the build-time defaults (commit is still required)
+pub const CONTROL_DEFAULTS: u8 = 0x03;
+
+/// Configuration service
+#[gatt_service(uuid = "5d1a0a20-6a3e-4c1e-9b7e-3f0c2b9d7a10")]
+pub struct ConfigService {
+    #[characteristic(uuid = "5d1a0a21-6a3e-4c1e-9b7e-3f0c2b9d7a10", read, notify)]
+    pub config: [u8; CONFIG_BLOB_LEN],
+
+    #[characteristic(uuid = "5d1a0a22-6a3e-4c1e-9b7e-3f0c2b9d7a10", read, write)]
+    pub staging: [u8; CONFIG_BLOB_LEN],
+
+    #[characteristic(uuid = "5d1a0a23-6a3e-4c1e-9b7e-3f0c2b9d7a10", write)]
+    pub control: u8,
+
+    #[characteristic(uuid = "5d1a0a24-6a3e-4c1e-9b7e-3f0c2b9d7a10", read, notify)]
+    pub generation: u32,
+}
+
+impl ConfigService {
+    /// Check whether the handle belongs to the service (all of it requires encryption,
+    /// subscriptions included)
+    ///
+    /// * `handle` - Attribute handle
+    pub fn protects(&self, handle: u16) -> bool {
+        [
+            self.config.handle,
+            self.staging.handle,
+            self.control.handle,
+            self.generation.handle,
+        ]
+        .contains(&handle)
+            || Some(handle) == self.config.cccd_handle
+            || Some(handle) == self.generation.cccd_handle
+    }
+}
+
+/// Set the characteristic values from the committed configuration
+///
+/// * `server` - GATT server
+pub fn init(server: &Server<'_>) {
+    let service = &server.config_service;
+    let blob = config::current().encode();
+    for (characteristic, value) in [(&service.config, &blob), (&service.staging, &blob)] {
+        if server.set(characteristic, value).is_err() {
+            warn!("[config] failed to set the initial value");
+        }
+    }
+    if server
+        .set(&service.generation, &config::generation())
+        .is_err()
+    {
+        warn!("[config] failed to set the initial generation");
+    }
+}
+
+/// Configuration changes of one connection
+pub struct ConfigSession {
+    /// Staged, not yet committed changes
+    transaction: ConfigTransaction,
+}
+
+impl ConfigSession {
+    /// Start a session from the committed configuration
+    pub fn begin() -> Self {
+        Self {
+            transaction: ConfigTransaction::begin(),
+        }
+    }
+
+    /// Check whether the handle belongs to a writable characteristic of the service
+    ///
+    /// * `server` - GATT server
+    /// * `handle` - Attribute handle of the write
+    pub fn handles(server: &Server<'_>, handle: u16) -> bool {
+        let service = &server.config_service;
+        handle == service.staging.handle || handle == service.control.handle
+    }
+
+    /// Validate and apply a write before it is accepted
+    ///
+    /// * `server` - GATT server
+    /// * `handle` - Attribute handle of the write
+    /// * `data` - Written value
+    pub fn on_write(
+        &mut self,
+        server: &Server<'_>,
+        handle: u16,
+        data: &[u8],
+    ) -> Result<(), AttErrorCode> {
+        let service = &server.config_service;
+        let result = if handle == service.staging.handle {
+            self.transaction.stage(data)
+        } else if handle == service.control.handle {
+            match data {
+                [CONTROL_COMMIT] => self.transaction.commit().map(|generation| {
+                    info!("[config] committed, generation {}", generation);
+                    shell::log(format_args!("[config] committed, generation {generation}"));
+                }),
+                [CONTROL_ROLLBACK] => {
+                    self.transaction.rollback();
+                    Ok(())
+                }
+                [CONTROL_DEFAULTS] => {
+                    self.transaction.stage_defaults();
+                    Ok(())
+                }
+                _ => return Err(AttErrorCode::VALUE_NOT_ALLOWED),
+            }
+        } else {
+            return Ok(());
+        };
+
+        result.map_err(att_error)
+    }
+
+    /// Show the staged configuration in the `staging` characteristic.
+    /// Must be called after the write has been accepted (accepting stores the raw value)
+    ///
+    /// * `server` - GATT server
+    pub fn refresh(&self, server: &Server<'_>) {
+        let blob = self.transaction.staged().encode();
+        if server.set(&server.config_service.staging, &blob).is_err() {
+            warn!("[config] failed to update the staged value");
+        }
+    }
+}
+
+/// ATT error reported for a rejected configuration write
+///
+/// * `e` - Validation error
+pub fn att_error(e: ConfigError) -> AttErrorCode {
+    warn!("[config] write rejected: {:?}", e);
+    match e {
+        ConfigError::Format | ConfigError::UnknownKey(_) => AttErrorCode::VALUE_NOT_ALLOWED,
+        ConfigError::Length(_) => AttErrorCode::INVALID_ATTRIBUTE_VALUE_LENGTH,
+        ConfigError::OutOfRange(_) => AttErrorCode::OUT_OF_RANGE,
+        // Client must roll back, review the new configuration and stage again
+        ConfigError::Conflict => AttErrorCode::WRITE_REQUEST_REJECTED,
+    }
+}
+
+/// Notify the connected central of every committed configuration
+///
+/// * `server` - GATT server
+/// * `conn` - Connection to notify
+pub async fn notify_task<P: PacketPool>(server: &Server<'_>, conn: &GattConnection<'_, '_, P>) {
+    let Some(mut receiver) = CONFIG_CHANGED.receiver() else {
+        warn!("[config] no free change receiver, notifications disabled");
+        return core::future::pending().await;
+    };
+    let service = &server.config_service;
+    // The first value is the generation the connection started with
+    let mut known = receiver.get().await;
+    loop {
+        let generation = receiver.changed().await;
+        if generation == known {
+            continue;
+        }
+        known = generation;
+        // Подписка могла остаться с прошлого сеанса, конфигурация только по шифрованному каналу
+        if !security::is_encrypted(conn) {
+            continue;
+        }
+
+        let blob = config::current().encode();
+        if service.config.notify(conn, &blob).await.is_err()
+            || service.generation.notify(conn, &generation).await.is_err()
+        {
+            info!("[config] error notifying connection");
+            break;
+        }
+    }
+}
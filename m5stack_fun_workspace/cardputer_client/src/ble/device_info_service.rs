@@ -0,0 +1,75 @@
+//! Device Information Service (0x180A) - read-only identification strings
+//!
+//! * `manufacturer` - Manufacturer Name String (0x2A29)
+//! * `model` - Model Number String (0x2A24)
+//! * `serial` - Serial Number String (0x2A25), eFuse MAC in hex
+//! * `firmware` - Firmware Revision String (0x2A26), application version
+//! * `software` - Software Revision String (0x2A28), project name and build date
+//!
+//! Versions are taken from the ESP-IDF application descriptor.
+
+use super::Server;
+use core::fmt::Write;
+use defmt::warn;
+use esp_bootloader_esp_idf::EspAppDesc;
+use esp_hal::efuse::Efuse;
+use heapless::{String, Vec};
+use trouble_host::prelude::*;
+
+/// Maximal string length, bytes
+pub const INFO_MAX: usize = 32;
+
+/// Manufacturer of the hardware
+const MANUFACTURER: &str = "M5Stack";
+
+/// Model of the hardware
+const MODEL: &str = "Cardputer";
+
+/// Device information service
+#[gatt_service(uuid = service::DEVICE_INFORMATION)]
+pub struct DeviceInfoService {
+    #[characteristic(uuid = characteristic::MANUFACTURER_NAME_STRING, read)]
+    pub manufacturer: Vec<u8, INFO_MAX>,
+
+    #[characteristic(uuid = characteristic::MODEL_NUMBER_STRING, read)]
+    pub model: Vec<u8, INFO_MAX>,
+
+    #[characteristic(uuid = characteristic::SERIAL_NUMBER_STRING, read)]
+    pub serial: Vec<u8, INFO_MAX>,
+
+    #[characteristic(uuid = characteristic::FIRMWARE_REVISION_STRING, read)]
+    pub firmware: Vec<u8, INFO_MAX>,
+
+    #[characteristic(uuid = characteristic::SOFTWARE_REVISION_STRING, read)]
+    pub software: Vec<u8, INFO_MAX>,
+}
+
+/// Set the identification strings
+///
+/// * `server` - GATT server
+/// * `app` - Application descriptor
+pub fn init(server: &Server<'_>, app: &EspAppDesc) {
+    let mut serial = String::<INFO_MAX>::new();
+    for byte in Efuse::mac_address() {
+        let _ = write!(serial, "{byte:02X}");
+    }
+    let mut software = String::<INFO_MAX>::new();
+    // Длинные строки обрезаются
+    let _ = write!(software, "{} {}", app.project_name(), app.date());
+
+    let service = &server.device_info_service;
+    for (characteristic, text) in [
+        (&service.manufacturer, MANUFACTURER),
+        (&service.model, MODEL),
+        (&service.serial, serial.as_str()),
+        (&service.firmware, app.version()),
+        (&service.software, software.as_str()),
+    ] {
+        let bytes = text.as_bytes();
+        let value = Vec::from_slice(bytes.get(..INFO_MAX.min(bytes.len())).unwrap_or_default())
+            .unwrap_or_default();
+        if server.set(characteristic, &value).is_err() {
+            warn!("[device_info] failed to set the value");
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! BLE errors and retry policy
+//!
+//! Controller and host errors are retried with an exponential backoff. When the retries are
+//! exhausted the error is returned from [`super::run`] and the firmware decides how to recover
+//! (usually by a reset).
+
+use defmt::warn;
+use embassy_time::{Duration, Instant, Timer};
+
+/// First retry delay, ms
+const BACKOFF_START_MS: u64 = 100;
+
+/// Longest retry delay, ms
+const BACKOFF_MAX_MS: u64 = 10_000;
+
+/// Number of failed attempts in a row before giving up
+const RETRIES_MAX: u8 = 8;
+
+/// Time without errors after which the operation is considered healthy again, ms
+const STABLE_MS: u64 = 60_000;
+
+/// BLE errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BleError {
+    /// GATT attribute table could not be built
+    GattServer,
+    /// Advertising could not be started
+    Advertise,
+    /// Host runner stopped, the controller does not respond
+    Host,
+}
+
+/// Exponential backoff of one operation
+pub(crate) struct Backoff {
+    /// Operation name for the log
+    name: &'static str,
+    /// Failed attempts in a row
+    failures: u8,
+    /// Start of the current attempt
+    started: Instant,
+}
+
+impl Backoff {
+    /// Backoff constructor
+    ///
+    /// * `name` - Operation name for the log
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            failures: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Mark the start of an attempt
+    pub(crate) fn attempt(&mut self) {
+        self.started = Instant::now();
+    }
+
+    /// The attempt succeeded, the next failure is retried without delay
+    pub(crate) fn succeeded(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Wait before the next attempt
+    ///
+    /// * `error` - Error of the failed attempt, returned when the retries are exhausted
+    pub(crate) async fn retry(&mut self, error: BleError) -> Result<(), BleError> {
+        // Долгая работа без ошибок - начинаем отсчёт заново
+        if self.started.elapsed() > Duration::from_millis(STABLE_MS) {
+            self.failures = 0;
+        }
+        if self.failures >= RETRIES_MAX {
+            return Err(error);
+        }
+        let delay_ms = BACKOFF_START_MS
+            .saturating_mul(1 << self.failures)
+            .min(BACKOFF_MAX_MS);
+        self.failures += 1;
+        warn!(
+            "[{}] {:?}, retry {}/{} in {} ms",
+            self.name, error, self.failures, RETRIES_MAX, delay_ms
+        );
+        Timer::after_millis(delay_ms).await;
+        Ok(())
+    }
+}
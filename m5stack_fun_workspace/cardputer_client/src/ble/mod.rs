@@ -0,0 +1,381 @@
+use core::cell::RefCell;
+use defmt::{info, warn};
+use embassy_futures::join::{join, join_array};
+use embassy_futures::select::{Either, Either3, Either4, select, select3, select4};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::channel::Channel;
+
+use crate::config;
+use crate::sensors::MEASUREMENT;
+
+use advertising::{Payload, PayloadChanges};
+use battery_service::BatteryService;
+use config_service::{ConfigService, ConfigSession};
+use device_info_service::DeviceInfoService;
+use error::Backoff;
+use esp_bootloader_esp_idf::EspAppDesc;
+use nus_service::{NusService, NusSession};
+use ota_service::OtaService;
+use security::RadioRng;
+use sensor_service::SensorService;
+
+mod advertising;
+mod battery_service;
+mod bonds;
+pub mod central;
+mod config_service;
+mod device_info_service;
+mod error;
+mod nus_service;
+mod ota_service;
+mod security;
+mod sensor_service;
+mod state;
+
+// Public re-export of specifics that are available outside of module
+pub use bonds::{BONDS_MAX, BondStore, BondStoreError};
+pub use error::BleError;
+pub use security::PAIRING_PASSKEY;
+pub use state::{BLE_EVENTS, BLE_STATE, BleEvent, BleState};
+
+// BLE:
+use trouble_host::prelude::*;
+
+/// Max number of simultaneous connections (e.g. a phone and a gateway)
+pub const CONNECTIONS_MAX: usize = 2;
+
+/// Max number of connections to other peripherals (central role)
+const CENTRAL_CONNECTIONS_MAX: usize = 1;
+
+/// Max number of L2CAP channels.
+const L2CAP_CHANNELS_MAX: usize = 1;
+
+/// Accepted connection waiting for a free connection slot
+type Accepted<'values, 'server> =
+    Channel<NoopRawMutex, GattConnection<'values, 'server, DefaultPacketPool>, 1>;
+
+/// Free connection slots, one token per slot
+type FreeSlots = Channel<NoopRawMutex, (), CONNECTIONS_MAX>;
+
+// GATT Server definition, subscriptions (CCCD) are kept per connection
+#[gatt_server(connections_max = CONNECTIONS_MAX)]
+struct Server {
+    sensor_service: SensorService,
+    battery_service: BatteryService,
+    device_info_service: DeviceInfoService,
+    config_service: ConfigService,
+    nus_service: NusService,
+    ota_service: OtaService,
+}
+
+impl Server<'_> {
+    /// Attributes available only over an encrypted link
+    ///
+    /// * `handle` - Attribute handle
+    fn is_protected(&self, handle: u16) -> bool {
+        self.sensor_service.handles(handle)
+            || self.config_service.protects(handle)
+            || self.nus_service.protects(handle)
+            || self.ota_service.protects(handle)
+    }
+}
+
+/// Run the BLE stack. Returns only when the stack can not recover from an error
+///
+/// * `controller` - HCI controller
+/// * `bonds` - Bond storage, `None` - bonds are kept until reset only
+/// * `app` - Application descriptor for the Device Information Service
+pub async fn run<C>(controller: C, bonds: Option<BondStore>, app: &EspAppDesc) -> BleError
+where
+    C: Controller,
+{
+    let address = security::static_address();
+    info!("Our address = {:?}", address);
+
+    let mut resources: HostResources<
+        DefaultPacketPool,
+        { CONNECTIONS_MAX + CENTRAL_CONNECTIONS_MAX },
+        L2CAP_CHANNELS_MAX,
+    > = HostResources::new();
+    let stack = trouble_host::new(controller, &mut resources)
+        .set_random_address(address)
+        .set_random_generator_seed(&mut RadioRng::new());
+    stack.set_io_capabilities(IoCapabilities::DisplayOnly);
+    for bond in bonds.iter().flat_map(BondStore::bonds) {
+        if let Err(e) = stack.add_bond_information(bond.clone()) {
+            warn!("[ble] failed to restore bond: {:?}", e);
+        }
+    }
+    let Host {
+        central,
+        mut peripheral,
+        runner,
+        ..
+    } = stack.build();
+
+    info!("Starting advertising and GATT service");
+    // The GAP name is set once, renames reach the advertising payload right away
+    let device_name = config::current().device_name;
+    // Инициализация GATT server
+    let server = match Server::new_with_config(GapConfig::Peripheral(PeripheralConfig {
+        name: &device_name,
+        appearance: &appearance::power_device::GENERIC_POWER_DEVICE,
+    })) {
+        Ok(server) => server,
+        Err(e) => {
+            warn!("[ble] failed to create the GATT server: {}", e);
+            return BleError::GattServer;
+        }
+    };
+    sensor_service::init(&server);
+    device_info_service::init(&server, app);
+    ota_service::init(&server);
+    config_service::init(&server);
+
+    // Связь между рекламой и слотами подключений
+    let accepted: Accepted = Channel::new();
+    let free_slots: FreeSlots = Channel::new();
+    for _ in 0..CONNECTIONS_MAX {
+        // Capacity is CONNECTIONS_MAX, never full here
+        let _ = free_slots.try_send(());
+    }
+    let bonds = RefCell::new(bonds);
+
+    let slots: [_; CONNECTIONS_MAX] =
+        core::array::from_fn(|_| connection_slot(&server, &stack, &accepted, &free_slots, &bonds));
+    let e = match select3(
+        ble_task(runner),
+        advertise_task(&mut peripheral, &server, &accepted, &free_slots),
+        join(join_array(slots), central::run(central, &stack)),
+    )
+    .await
+    {
+        Either3::First(e) | Either3::Second(e) => e,
+        // Слоты подключений и центральная роль работают бесконечно
+        Either3::Third(_) => BleError::Host,
+    };
+    warn!("[ble] stopped: {:?}", e);
+    e
+}
+
+/// Advertise while there is a free connection slot and hand accepted connections to the slots.
+/// Returns when advertising keeps failing
+///
+/// * `peripheral` - Peripheral role of the host
+/// * `server` - GATT server
+/// * `accepted` - Accepted connections
+/// * `free_slots` - Free slot tokens
+async fn advertise_task<'values, 'server, C: Controller>(
+    peripheral: &mut Peripheral<'values, C, DefaultPacketPool>,
+    server: &'server Server<'values>,
+    accepted: &Accepted<'values, 'server>,
+    free_slots: &FreeSlots,
+) -> BleError {
+    let mut backoff = Backoff::new("adv");
+    let mut changes = PayloadChanges::new();
+    loop {
+        // Все слоты заняты - реклама не нужна, пока кто-нибудь не отключится
+        free_slots.receive().await;
+        let conn = loop {
+            backoff.attempt();
+            match advertise(peripheral, server, &mut changes).await {
+                Ok(conn) => break conn,
+                Err(e) => {
+                    warn!("[adv] error: {:?}", defmt::Debug2Format(&e));
+                    state::advertising_stopped();
+                    if let Err(e) = backoff.retry(BleError::Advertise).await {
+                        return e;
+                    }
+                }
+            }
+        };
+        backoff.succeeded();
+        accepted.send(conn).await;
+    }
+}
+
+/// Serve connections one after another, each with its own tasks and state
+///
+/// * `server` - GATT server
+/// * `stack` - BLE stack
+/// * `accepted` - Accepted connections
+/// * `free_slots` - Free slot tokens, returned when the connection closes
+/// * `bonds` - Bond storage
+async fn connection_slot<'values, 'server, C: Controller>(
+    server: &'server Server<'values>,
+    stack: &Stack<'_, C, DefaultPacketPool>,
+    accepted: &Accepted<'values, 'server>,
+    free_slots: &FreeSlots,
+    bonds: &RefCell<Option<BondStore>>,
+) {
+    loop {
+        let conn = accepted.receive().await;
+        // set up tasks when the connection is established to a central, so they don't run when no one is connected.
+        let a = gatt_events_task(server, &conn, bonds);
+        let b = sensor_service::notify_task(server, &conn, stack);
+        let c = config_service::notify_task(server, &conn);
+        let d = nus_service::run(server, &conn);
+        // run until any task ends (usually because the connection has been closed),
+        // then free the slot.
+        let reason = match select4(a, b, c, d).await {
+            Either4::First(Ok(reason)) => reason,
+            // Остальные задачи завершаются при ошибке, соединение закрываем мы
+            _ => bt_hci::param::Status::CONN_TERMINATED_BY_LOCAL_HOST,
+        };
+        drop(conn);
+        // Слот освобождается при любом завершении, не только по событию отключения
+        state::disconnected(reason);
+        free_slots.send(()).await;
+    }
+}
+
+// Фоновая работа BLE хоста (должна работать в любом случае)
+// Every run starts with an HCI reset, so a restart also resets the controller.
+async fn ble_task<C: Controller, P: PacketPool>(mut runner: Runner<'_, C, P>) -> BleError {
+    let mut backoff = Backoff::new("ble_task");
+    loop {
+        backoff.attempt();
+        // Отчёты сканирования центральной роли
+        if let Err(e) = runner.run_with_handler(&central::ScanHandler).await {
+            warn!("[ble_task] error: {:?}", defmt::Debug2Format(&e));
+            if let Err(e) = backoff.retry(BleError::Host).await {
+                return e;
+            }
+        }
+    }
+}
+
+/// Create an advertiser to use to connect to a BLE Central, and wait for it to connect.
+/// The advertising payload is rebuilt whenever it changes.
+///
+/// * `peripheral` - Peripheral role of the host
+/// * `server` - GATT server
+/// * `changes` - Payload changes
+async fn advertise<'values, 'server, C: Controller>(
+    peripheral: &mut Peripheral<'values, C, DefaultPacketPool>,
+    server: &'server Server<'values>,
+    changes: &mut PayloadChanges,
+) -> Result<GattConnection<'values, 'server, DefaultPacketPool>, BleHostError<C::Error>> {
+    let conn = loop {
+        let payload = Payload::new(&config::current(), MEASUREMENT.try_get());
+        let advertiser = peripheral
+            .advertise(&payload.parameters(), payload.advertisement())
+            .await?;
+        info!("[adv] advertising");
+        state::advertising_started();
+        // Реклама останавливается вместе с advertiser
+        match select(advertiser.accept(), changes.changed()).await {
+            Either::First(conn) => break conn?,
+            Either::Second(()) => continue,
+        }
+    };
+    // Ключи сопряжения сохраняются во flash
+    conn.set_bondable(true)?;
+    let conn = conn.with_attribute_server(server)?;
+    info!("[adv] connection established");
+    state::connected();
+    Ok(conn)
+}
+
+/// Stream Events until the connection closes.
+///
+/// This function will handle the GATT events and process them.
+/// This is how we interact with read and write requests.
+/// Pairing results are stored in `bonds`.
+async fn gatt_events_task<P: PacketPool>(
+    server: &Server<'_>,
+    conn: &GattConnection<'_, '_, P>,
+    bonds: &RefCell<Option<BondStore>>,
+) -> Result<bt_hci::param::Status, Error> {
+    let temperature = server.sensor_service.temperature;
+    // Незакоммиченные изменения конфигурации теряются при отключении
+    let mut config_session = ConfigSession::begin();
+    let mut nus_session = NusSession::begin();
+
+    let reason = loop {
+        match conn.next().await {
+            GattConnectionEvent::Disconnected { reason } => break reason,
+            GattConnectionEvent::Gatt { event } => {
+                let mut config_written = false;
+                let mut result = Ok(());
+                let handle = match &event {
+                    GattEvent::Read(event) => Some(event.handle()),
+                    GattEvent::Write(event) => Some(event.handle()),
+                    _ => None,
+                };
+                if handle.is_some_and(|handle| server.is_protected(handle))
+                    && !security::is_encrypted(conn)
+                {
+                    // Центральное устройство должно начать сопряжение и повторить запрос
+                    result = Err(AttErrorCode::INSUFFICIENT_AUTHENTICATION);
+                } else {
+                    match &event {
+                        GattEvent::Read(event) => {
+                            ota_service::on_read(server, event.handle());
+                            if event.handle() == temperature.handle {
+                                let value = server.get(&temperature);
+                                info!(
+                                    "[gatt] Read Event to Temperature Characteristic: {:?}",
+                                    value
+                                );
+                            }
+                        }
+                        GattEvent::Write(event) => {
+                            if server.sensor_service.handles(event.handle()) {
+                                result =
+                                    server.sensor_service.on_write(event.handle(), event.data());
+                            } else if ConfigSession::handles(server, event.handle()) {
+                                config_written = true;
+                                result =
+                                    config_session.on_write(server, event.handle(), event.data());
+                            } else if NusSession::handles(server, event.handle()) {
+                                result = nus_session.on_write(conn, event.data());
+                            } else if server.ota_service.handles(event.handle()) {
+                                result =
+                                    ota_service::on_write(server, event.handle(), event.data());
+                            }
+                        }
+                        _ => {}
+                    };
+                }
+                // This step is also performed at drop(), but writing it explicitly is necessary
+                // in order to ensure reply is sent.
+                let reply = match result {
+                    Ok(()) => event.accept(),
+                    Err(code) => event.reject(code),
+                };
+                match reply {
+                    Ok(reply) => reply.send().await,
+                    Err(e) => warn!("[gatt] error sending response: {:?}", e),
+                };
+                if config_written {
+                    config_session.refresh(server);
+                }
+            }
+            GattConnectionEvent::PassKeyDisplay(key) => {
+                info!("[gatt] pairing passkey: {:06}", key.value());
+                PAIRING_PASSKEY.signal(Some(key.value()));
+            }
+            GattConnectionEvent::PairingComplete {
+                security_level,
+                bond,
+            } => {
+                info!("[gatt] pairing complete: {:?}", security_level);
+                PAIRING_PASSKEY.signal(None);
+                if let (Some(bond), Some(store)) = (bond, bonds.borrow_mut().as_mut())
+                    && let Err(e) = store.save(bond)
+                {
+                    warn!("[gatt] failed to store the bond: {:?}", e);
+                }
+            }
+            GattConnectionEvent::PairingFailed(e) => {
+                warn!("[gatt] pairing failed: {:?}", e);
+                PAIRING_PASSKEY.signal(None);
+            }
+            _ => {} // ignore other Gatt Connection Events
+        }
+    };
+
+    info!("[gatt] disconnected: {:?}", reason);
+    Ok(reason)
+}
@@ -0,0 +1,237 @@
+//! Nordic UART Service - terminal access to the [`crate::shell`]
+//!
+//! * `rx` - the central writes command text, lines end with `\r` or `\n`
+//! * `tx` - responses and log lines, notified in chunks of the negotiated ATT MTU
+//!
+//! The service requires an encrypted link, the output is held back until the central pairs.
+//! There is one shell: the first connection owns the terminal, other connections get it when
+//! the owner disconnects (their writes are rejected until then).
+//! Notifications wait for free buffers in the BLE stack. While the central does not read,
+//! [`SHELL_OUTPUT`] fills up: the shell waits with its responses, log lines are dropped.
+
+use super::{Server, security};
+use crate::shell::{self, LINE_MAX, Line, Response, SHELL_OUTPUT};
+use core::cell::Cell;
+use core::fmt::Write;
+use defmt::{info, warn};
+use embassy_futures::select::select;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+use heapless::Vec;
+use trouble_host::prelude::*;
+
+/// Maximal characteristic value: ATT MTU of the default packet pool (247) - 3
+pub const CHUNK_MAX: usize = 244;
+
+/// ATT notification header: opcode (1) + handle (2)
+const NOTIFY_HEADER_LEN: usize = 3;
+
+/// Link encryption check period while the output is held back, ms
+const ENCRYPTION_POLL_MS: u64 = 200;
+
+/// Command lines waiting for the shell
+const PENDING_LINES: usize = 2;
+
+/// Complete command lines received from the central
+static SHELL_INPUT: Channel<CriticalSectionRawMutex, Line, PENDING_LINES> = Channel::new();
+
+/// Connection that owns the terminal
+static TERMINAL_OWNER: Mutex<CriticalSectionRawMutex, Cell<Option<ConnHandle>>> =
+    Mutex::new(Cell::new(None));
+
+/// The owner has disconnected, the terminal is free
+static TERMINAL_RELEASED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Nordic UART Service UUID (6e400001-b5a3-f393-e0a9-e50e24dcca9e)
+pub const SERVICE_UUID: u128 = 0x6e40_0001_b5a3_f393_e0a9_e50e_24dc_ca9e;
+
+/// Nordic UART service
+#[gatt_service(uuid = SERVICE_UUID)]
+pub struct NusService {
+    #[characteristic(
+        uuid = "6e400002-b5a3-f393-e0a9-e50e24dcca9e",
+        write,
+        write_without_response
+    )]
+    pub rx: Vec<u8, CHUNK_MAX>,
+
+    #[characteristic(uuid = "6e400003-b5a3-f393-e0a9-e50e24dcca9e", notify)]
+    pub tx: Vec<u8, CHUNK_MAX>,
+}
+
+impl NusService {
+    /// Check whether the handle belongs to the service (the shell requires encryption)
+    ///
+    /// * `handle` - Attribute handle
+    pub fn protects(&self, handle: u16) -> bool {
+        handle == self.rx.handle || Some(handle) == self.tx.cccd_handle
+    }
+}
+
+/// Line assembly of one connection
+pub struct NusSession {
+    /// Received part of the current line
+    line: Line,
+    /// The current line is too long and is being skipped
+    overflow: bool,
+}
+
+impl NusSession {
+    /// Start a session with an empty line
+    pub fn begin() -> Self {
+        Self {
+            line: Line::new(),
+            overflow: false,
+        }
+    }
+
+    /// Check whether the handle belongs to a writable characteristic of the service
+    ///
+    /// * `server` - GATT server
+    /// * `handle` - Attribute handle of the write
+    pub fn handles(server: &Server<'_>, handle: u16) -> bool {
+        handle == server.nus_service.rx.handle
+    }
+
+    /// Split received text into lines and pass them to the shell
+    ///
+    /// * `conn` - Connection of the write
+    /// * `data` - Written text
+    pub fn on_write<P: PacketPool>(
+        &mut self,
+        conn: &GattConnection<'_, '_, P>,
+        data: &[u8],
+    ) -> Result<(), AttErrorCode> {
+        if !Terminal::is_owner(conn.raw().handle()) {
+            return Err(AttErrorCode::WRITE_REQUEST_REJECTED);
+        }
+        for byte in data {
+            match byte {
+                b'\r' | b'\n' => self.complete_line()?,
+                // Only printable ASCII, the shell does not know anything else
+                0x20..=0x7E if !self.overflow => {
+                    if self.line.push(char::from(*byte)).is_err() {
+                        warn!("[nus] line longer than {} bytes, skipped", LINE_MAX);
+                        self.overflow = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Pass the received line to the shell
+    fn complete_line(&mut self) -> Result<(), AttErrorCode> {
+        let line = core::mem::take(&mut self.line);
+        if core::mem::take(&mut self.overflow) {
+            shell::log(format_args!("error: line too long"));
+            return Ok(());
+        }
+        if line.is_empty() {
+            return Ok(());
+        }
+        // The shell is still busy with the previous lines
+        SHELL_INPUT
+            .try_send(line)
+            .map_err(|_| AttErrorCode::INSUFFICIENT_RESOURCES)
+    }
+}
+
+/// Terminal ownership of a connection, released on drop
+struct Terminal;
+
+impl Terminal {
+    /// Take the terminal if it is free
+    ///
+    /// * `handle` - Connection handle
+    fn claim(handle: ConnHandle) -> Option<Self> {
+        TERMINAL_OWNER.lock(|owner| {
+            if owner.get().is_some() {
+                return None;
+            }
+            owner.set(Some(handle));
+            Some(Self)
+        })
+    }
+
+    /// Check whether the connection owns the terminal
+    ///
+    /// * `handle` - Connection handle
+    fn is_owner(handle: ConnHandle) -> bool {
+        TERMINAL_OWNER.lock(|owner| owner.get() == Some(handle))
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        TERMINAL_OWNER.lock(|owner| owner.set(None));
+        TERMINAL_RELEASED.signal(());
+    }
+}
+
+/// Wait for the terminal, then execute the received lines and stream the output while the
+/// connection is up
+///
+/// * `server` - GATT server
+/// * `conn` - Connection to notify
+pub async fn run<P: PacketPool>(server: &Server<'_>, conn: &GattConnection<'_, '_, P>) {
+    let _terminal = loop {
+        if let Some(terminal) = Terminal::claim(conn.raw().handle()) {
+            break terminal;
+        }
+        TERMINAL_RELEASED.wait().await;
+    };
+    // Ответы предыдущему владельцу больше не нужны
+    SHELL_OUTPUT.clear();
+    SHELL_INPUT.clear();
+
+    select(shell_task(), tx_task(server, conn)).await;
+}
+
+/// Execute the received lines, responses wait for free space in the output
+async fn shell_task() {
+    loop {
+        let line = SHELL_INPUT.receive().await;
+        info!("[nus] command: {}", line.as_str());
+
+        let mut response = Response::new();
+        if shell::execute(&line, &mut response).is_err() {
+            // Обрезанный ответ - лучше, чем никакой
+            let _ = response.write_str("...\r\n");
+        }
+        SHELL_OUTPUT.write_all(response.as_bytes()).await;
+    }
+}
+
+/// Notify the output in chunks of the negotiated MTU
+///
+/// * `server` - GATT server
+/// * `conn` - Connection to notify
+async fn tx_task<P: PacketPool>(server: &Server<'_>, conn: &GattConnection<'_, '_, P>) {
+    let tx = server.nus_service.tx;
+    let mut buffer = [0_u8; CHUNK_MAX];
+    loop {
+        // Вывод оболочки только по зашифрованному каналу, до тех пор он копится в буфере
+        if !security::is_encrypted(conn) {
+            Timer::after_millis(ENCRYPTION_POLL_MS).await;
+            continue;
+        }
+        // MTU can be renegotiated at any time
+        let mtu = usize::from(conn.raw().att_mtu());
+        let chunk_len = mtu.saturating_sub(NOTIFY_HEADER_LEN).clamp(1, CHUNK_MAX);
+        let Some(chunk) = buffer.get_mut(..chunk_len) else {
+            return;
+        };
+        let len = SHELL_OUTPUT.read(chunk).await;
+
+        let value = Vec::from_slice(chunk.get(..len).unwrap_or_default()).unwrap_or_default();
+        if tx.notify(conn, &value).await.is_err() {
+            info!("[nus] error notifying connection");
+            break;
+        }
+    }
+}
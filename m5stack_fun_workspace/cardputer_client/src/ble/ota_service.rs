@@ -0,0 +1,225 @@
+//! Firmware update service - BLE transport of [`crate::ota`]
+//!
+//! * `control` - commands:
+//!   * [`CONTROL_BEGIN`], image size (u32), SHA-256 (32 bytes) - start or resume an update
+//!   * [`CONTROL_FINISH`] - verify and activate the image in the background, the result is
+//!     reported in `status`
+//!   * [`CONTROL_ABORT`] - drop the update
+//!   * [`CONTROL_REBOOT`] - restart into the activated image
+//! * `data` - chunk offset (u32), CRC-32 of the chunk (u32), chunk (up to
+//!   [`ota::CHUNK_MAX`] bytes). Write without response is allowed, errors are then reported
+//!   only in `status`
+//! * `status` - update state (0 - idle, 1 - receiving, 2 - verifying, 3 - activated), received
+//!   bytes (u32), image size (u32), error of the last command, chunk or verification (see
+//!   [`error_code`]). The client polls it after `finish` until the state is no longer
+//!   verifying. After a reconnect the client repeats `begin` and continues from the received
+//!   bytes
+//!
+//! Numbers are little-endian. The whole service requires an encrypted link.
+
+use super::Server;
+use crate::ota::{self, DIGEST_LEN, OtaError, OtaPhase};
+use core::cell::Cell;
+use defmt::warn;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use heapless::Vec;
+use trouble_host::prelude::*;
+
+/// Start or resume an update
+pub const CONTROL_BEGIN: u8 = 0x01;
+
+/// Verify and activate the received image
+pub const CONTROL_FINISH: u8 = 0x02;
+
+/// Drop the update in progress
+pub const CONTROL_ABORT: u8 = 0x03;
+
+/// Restart into the activated image
+pub const CONTROL_REBOOT: u8 = 0x04;
+
+/// Longest command: begin (1) + image size (4) + SHA-256
+const CONTROL_MAX: usize = 1 + 4 + DIGEST_LEN;
+
+/// Chunk header: offset (4) + CRC-32 (4)
+const CHUNK_HEADER_LEN: usize = 8;
+
+/// Status: state (1) + received bytes (4) + image size (4) + error (1)
+const STATUS_LEN: usize = 10;
+
+/// Error of the last command or chunk, shown in `status` until the next write
+static LAST_ERROR: Mutex<CriticalSectionRawMutex, Cell<Option<OtaError>>> =
+    Mutex::new(Cell::new(None));
+
+/// Firmware update service
+#[gatt_service(uuid = "7f3e1c40-2b8d-4e61-a5f9-0c4d8e2b6a50")]
+pub struct OtaService {
+    #[characteristic(uuid = "7f3e1c41-2b8d-4e61-a5f9-0c4d8e2b6a50", write)]
+    pub control: Vec<u8, CONTROL_MAX>,
+
+    #[characteristic(
+        uuid = "7f3e1c42-2b8d-4e61-a5f9-0c4d8e2b6a50",
+        write,
+        write_without_response
+    )]
+    pub data: Vec<u8, { CHUNK_HEADER_LEN + ota::CHUNK_MAX }>,
+
+    #[characteristic(uuid = "7f3e1c43-2b8d-4e61-a5f9-0c4d8e2b6a50", read)]
+    pub status: [u8; STATUS_LEN],
+}
+
+impl OtaService {
+    /// Check whether the handle belongs to the service (all of it requires encryption)
+    ///
+    /// * `handle` - Attribute handle
+    pub fn protects(&self, handle: u16) -> bool {
+        [self.control.handle, self.data.handle, self.status.handle].contains(&handle)
+    }
+
+    /// Check whether the handle belongs to a writable characteristic of the service
+    ///
+    /// * `handle` - Attribute handle of the write
+    pub fn handles(&self, handle: u16) -> bool {
+        handle == self.control.handle || handle == self.data.handle
+    }
+}
+
+/// Set the initial status
+///
+/// * `server` - GATT server
+pub fn init(server: &Server<'_>) {
+    refresh(server);
+}
+
+/// Update `status` before it is read, the verification finishes in the background
+///
+/// * `server` - GATT server
+/// * `handle` - Attribute handle of the read
+pub fn on_read(server: &Server<'_>, handle: u16) {
+    if handle == server.ota_service.status.handle {
+        refresh(server);
+    }
+}
+
+/// Execute a command or write a chunk
+///
+/// * `server` - GATT server
+/// * `handle` - Attribute handle of the write
+/// * `data` - Written value
+pub fn on_write(server: &Server<'_>, handle: u16, data: &[u8]) -> Result<(), AttErrorCode> {
+    let service = &server.ota_service;
+    let result = if handle == service.control.handle {
+        control(data)
+    } else if handle == service.data.handle {
+        chunk(data)
+    } else {
+        return Ok(());
+    };
+    LAST_ERROR.lock(|error| error.set(result.err()));
+    refresh(server);
+    result.map_err(att_error)
+}
+
+/// Execute a control command
+///
+/// * `data` - Command
+fn control(data: &[u8]) -> Result<(), OtaError> {
+    match data {
+        [CONTROL_BEGIN, rest @ ..] => {
+            let (size, digest) = rest.split_at_checked(4).ok_or(OtaError::Length)?;
+            let size = u32::from_le_bytes(size.try_into().map_err(|_| OtaError::Length)?);
+            let digest = digest.try_into().map_err(|_| OtaError::Length)?;
+            ota::begin(size, digest).map(|_| ())
+        }
+        [CONTROL_FINISH] => ota::finish(),
+        [CONTROL_ABORT] => {
+            ota::abort();
+            Ok(())
+        }
+        [CONTROL_REBOOT] => {
+            ota::request_reboot();
+            Ok(())
+        }
+        _ => Err(OtaError::State),
+    }
+}
+
+/// Write a chunk
+///
+/// * `data` - Chunk header and data
+fn chunk(data: &[u8]) -> Result<(), OtaError> {
+    let (header, chunk) = data
+        .split_at_checked(CHUNK_HEADER_LEN)
+        .ok_or(OtaError::Length)?;
+    let (offset, crc) = header.split_at(4);
+    let offset = u32::from_le_bytes(offset.try_into().map_err(|_| OtaError::Length)?);
+    let crc = u32::from_le_bytes(crc.try_into().map_err(|_| OtaError::Length)?);
+    ota::write(offset, crc, chunk).map(|_| ())
+}
+
+/// Show the progress and the last error in the `status` characteristic
+///
+/// * `server` - GATT server
+fn refresh(server: &Server<'_>) {
+    let (state, size, written) = match ota::progress() {
+        None => (0, 0, 0),
+        Some(progress) => {
+            let state = match progress.phase {
+                OtaPhase::Receiving => 1,
+                OtaPhase::Verifying => 2,
+                OtaPhase::Activated => 3,
+            };
+            (state, progress.size, progress.written)
+        }
+    };
+    // Ошибка проверки новее ошибки записи, которая её запустила
+    let error = ota::verify_error().or_else(|| LAST_ERROR.lock(Cell::get));
+    let [w0, w1, w2, w3] = written.to_le_bytes();
+    let [s0, s1, s2, s3] = size.to_le_bytes();
+    let status = [
+        state,
+        w0,
+        w1,
+        w2,
+        w3,
+        s0,
+        s1,
+        s2,
+        s3,
+        error.map_or(0, error_code),
+    ];
+    if server.set(&server.ota_service.status, &status).is_err() {
+        warn!("[ota] failed to update the status");
+    }
+}
+
+/// Error code in `status`, 0 - no error
+///
+/// * `e` - Update error
+pub fn error_code(e: OtaError) -> u8 {
+    match e {
+        OtaError::Partition => 1,
+        OtaError::Flash => 2,
+        OtaError::State => 3,
+        OtaError::Offset(_) => 4,
+        OtaError::Crc => 5,
+        OtaError::Length => 6,
+        OtaError::TooLarge => 7,
+        OtaError::Digest => 8,
+    }
+}
+
+/// ATT error reported for a rejected write
+///
+/// * `e` - Update error
+fn att_error(e: OtaError) -> AttErrorCode {
+    warn!("[ota] write rejected: {:?}", e);
+    match e {
+        OtaError::Partition | OtaError::Flash => AttErrorCode::UNLIKELY_ERROR,
+        OtaError::State => AttErrorCode::WRITE_REQUEST_REJECTED,
+        OtaError::Offset(_) => AttErrorCode::INVALID_OFFSET,
+        OtaError::Crc | OtaError::Digest => AttErrorCode::VALUE_NOT_ALLOWED,
+        OtaError::Length => AttErrorCode::INVALID_ATTRIBUTE_VALUE_LENGTH,
+        OtaError::TooLarge => AttErrorCode::INSUFFICIENT_RESOURCES,
+    }
+}
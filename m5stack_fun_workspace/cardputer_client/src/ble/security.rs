@@ -0,0 +1,71 @@
+//! Security - device address, pairing randomness and access checks
+//!
+//! Pairing uses LE Secure Connections with a passkey displayed by the device
+//! (`DisplayOnly`): the passkey is logged and published by [`PAIRING_PASSKEY`], the display
+//! task shows it on the screen while pairing is in progress.
+//! Configuration, shell and sensor control characteristics require an encrypted link, the
+//! central is asked to pair by the `Insufficient Authentication` error.
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use esp_hal::efuse::Efuse;
+use esp_hal::rng::Rng;
+use rand_core::{CryptoRng, RngCore};
+use trouble_host::prelude::*;
+
+/// Passkey to show while pairing, `None` - pairing finished
+pub static PAIRING_PASSKEY: Signal<CriticalSectionRawMutex, Option<u32>> = Signal::new();
+
+/// Static random address derived from the eFuse MAC - stable across resets, so bonded
+/// centrals recognise the device
+pub fn static_address() -> Address {
+    // MAC is big-endian, BLE addresses are little-endian
+    let mut address = Efuse::mac_address();
+    address.reverse();
+    // Static random address: two most significant bits are set
+    if let Some(msb) = address.last_mut() {
+        *msb |= 0xC0;
+    }
+    Address::random(address)
+}
+
+/// Check whether the link is encrypted
+///
+/// * `conn` - Connection
+pub fn is_encrypted<P: PacketPool>(conn: &GattConnection<'_, '_, P>) -> bool {
+    conn.raw()
+        .security_level()
+        .is_ok_and(|level| level.encrypted())
+}
+
+/// Hardware RNG for the pairing keys
+pub struct RadioRng(Rng);
+
+impl RadioRng {
+    /// RNG constructor, the radio must be running
+    pub fn new() -> Self {
+        Self(Rng::new())
+    }
+}
+
+impl RngCore for RadioRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.random()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (u64::from(self.next_u32()) << 32) | u64::from(self.next_u32())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.read(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// С включенным радио RNG выдает истинно случайные числа (шум RF тракта)
+impl CryptoRng for RadioRng {}
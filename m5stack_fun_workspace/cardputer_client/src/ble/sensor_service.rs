@@ -0,0 +1,200 @@
+//! Environmental Sensing Service (0x181A) - measurements published by [`crate::sensors`]
+//!
+//! * `temperature` - Temperature (0x2A6E), sint16, 0.01 °C
+//! * `humidity` - Humidity (0x2A6F), uint16, 0.01 %
+//! * `voltage` - Voltage (0x2B18), uint16, 1/64 V - battery
+//! * `interval_ms` - measurement and notification interval, uint32, ms. Writes change the
+//!   `sensor_interval_ms` runtime configuration
+//! * `sensor_settings` - sampling enabled, uint8 0/1. Writes are published by
+//!   [`SENSOR_SETTINGS`]
+//!
+//! Unavailable values are reported with the "value is not known" codes of the GATT
+//! Specification Supplement.
+
+use super::config_service::att_error;
+use super::{Server, battery_service};
+use crate::config::{self, ConfigKey, ConfigTransaction};
+use crate::sensors::{self, MEASUREMENT, SENSOR_SETTINGS, SensorSettings};
+use defmt::{info, warn};
+use shared_lib::SensorReading;
+use trouble_host::prelude::*;
+
+/// Temperature is not known
+const TEMPERATURE_UNKNOWN: i16 = i16::MIN;
+
+/// Humidity is not known
+const HUMIDITY_UNKNOWN: u16 = u16::MAX;
+
+/// Voltage is not known
+const VOLTAGE_UNKNOWN: u16 = u16::MAX;
+
+/// Characteristic values before the first measurement
+pub(super) const UNKNOWN_VALUES: (i16, u16, u16) =
+    (TEMPERATURE_UNKNOWN, HUMIDITY_UNKNOWN, VOLTAGE_UNKNOWN);
+
+/// Environmental sensing service
+#[gatt_service(uuid = service::ENVIRONMENTAL_SENSING)]
+pub struct SensorService {
+    #[characteristic(uuid = characteristic::TEMPERATURE, read, notify, value = TEMPERATURE_UNKNOWN)]
+    pub temperature: i16,
+
+    #[characteristic(uuid = characteristic::HUMIDITY, read, notify, value = HUMIDITY_UNKNOWN)]
+    pub humidity: u16,
+
+    #[characteristic(uuid = characteristic::VOLTAGE, read, notify, value = VOLTAGE_UNKNOWN)]
+    pub voltage: u16,
+
+    #[characteristic(uuid = "a9c81b73-0f7a-4c59-b0a8-425e3bcf0a0e", read, write)]
+    pub interval_ms: u32,
+
+    #[characteristic(uuid = "c79b2ca7-f39d-4060-8168-816fa26737b7", write, read)]
+    pub sensor_settings: bool,
+}
+
+impl SensorService {
+    /// Check whether the handle belongs to a writable characteristic of the service
+    ///
+    /// * `handle` - Attribute handle of the write
+    pub fn handles(&self, handle: u16) -> bool {
+        handle == self.interval_ms.handle || handle == self.sensor_settings.handle
+    }
+
+    /// Validate and apply a write before it is accepted
+    ///
+    /// * `handle` - Attribute handle of the write
+    /// * `data` - Written value
+    pub fn on_write(&self, handle: u16, data: &[u8]) -> Result<(), AttErrorCode> {
+        if handle == self.interval_ms.handle {
+            Self::write_interval(data)
+        } else if handle == self.sensor_settings.handle {
+            Self::write_settings(data)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Commit a new measurement interval
+    ///
+    /// * `data` - Interval, ms (u32)
+    fn write_interval(data: &[u8]) -> Result<(), AttErrorCode> {
+        let mut transaction = ConfigTransaction::begin();
+        transaction
+            .stage_entry(ConfigKey::SensorIntervalMs, data)
+            .and_then(|()| transaction.commit())
+            .map_err(att_error)?;
+        info!(
+            "[sensor] interval set to {} ms",
+            transaction.staged().sensor_interval_ms
+        );
+        Ok(())
+    }
+
+    /// Apply and publish new sensor settings
+    ///
+    /// * `data` - Sampling enabled (u8, 0/1)
+    fn write_settings(data: &[u8]) -> Result<(), AttErrorCode> {
+        let sampling_enabled = match data {
+            [0] => false,
+            [1] => true,
+            [_] => return Err(AttErrorCode::VALUE_NOT_ALLOWED),
+            _ => return Err(AttErrorCode::INVALID_ATTRIBUTE_VALUE_LENGTH),
+        };
+        let settings = SensorSettings { sampling_enabled };
+        info!("[sensor] settings changed: {:?}", settings);
+        sensors::update_settings(settings);
+        Ok(())
+    }
+}
+
+/// Set the interval and settings characteristics from the committed configuration and the
+/// current sensor settings
+///
+/// * `server` - GATT server
+pub fn init(server: &Server<'_>) {
+    let interval_ms = config::current().sensor_interval_ms;
+    if server
+        .set(&server.sensor_service.interval_ms, &interval_ms)
+        .is_err()
+    {
+        warn!("[sensor] failed to set the initial interval");
+    }
+    let settings = SENSOR_SETTINGS.try_get().unwrap_or(SensorSettings::DEFAULT);
+    if server
+        .set(
+            &server.sensor_service.sensor_settings,
+            &settings.sampling_enabled,
+        )
+        .is_err()
+    {
+        warn!("[sensor] failed to set the initial settings");
+    }
+}
+
+/// Notify the connected central of every measurement and battery level (if notifications are
+/// enabled in the configuration) and read the RSSI of the connection.
+/// Stops when the connection is closed by the central or an error occurs.
+///
+/// * `server` - GATT server
+/// * `conn` - Connection to notify
+/// * `stack` - BLE stack
+pub async fn notify_task<C: Controller, P: PacketPool>(
+    server: &Server<'_>,
+    conn: &GattConnection<'_, '_, P>,
+    stack: &Stack<'_, C, P>,
+) {
+    let Some(mut receiver) = MEASUREMENT.receiver() else {
+        warn!("[sensor] no free measurement receiver, notifications disabled");
+        return core::future::pending().await;
+    };
+    let service = &server.sensor_service;
+    loop {
+        let measurement = receiver.changed().await;
+        let config = config::current();
+        // Интервал мог быть изменен через сервис конфигурации
+        if server
+            .set(&service.interval_ms, &config.sensor_interval_ms)
+            .is_err()
+        {
+            warn!("[sensor] failed to update the interval");
+        }
+
+        let (temperature, humidity, voltage) = values(&measurement.into());
+        let sent = if config.notify_enabled {
+            service.temperature.notify(conn, &temperature).await.is_ok()
+                && service.humidity.notify(conn, &humidity).await.is_ok()
+                && service.voltage.notify(conn, &voltage).await.is_ok()
+        } else {
+            server.set(&service.temperature, &temperature).is_ok()
+                && server.set(&service.humidity, &humidity).is_ok()
+                && server.set(&service.voltage, &voltage).is_ok()
+        };
+        // Уровень заряда для стандартного Battery Service
+        let sent = sent
+            && battery_service::update(server, conn, &measurement, config.notify_enabled).await;
+        if !sent {
+            info!("[sensor] error notifying connection");
+            break;
+        }
+
+        // read RSSI (Received Signal Strength Indicator) of the connection.
+        if let Ok(rssi) = conn.raw().rssi(stack).await {
+            info!("[sensor] RSSI: {:?}", rssi);
+        } else {
+            info!("[sensor] error getting RSSI");
+            break;
+        };
+    }
+}
+
+/// Characteristic values of a measurement: temperature, humidity, voltage
+///
+/// * `reading` - Measurement
+pub(super) fn values(reading: &SensorReading) -> (i16, u16, u16) {
+    let temperature = reading.temperature_centi.unwrap_or(TEMPERATURE_UNKNOWN);
+    let humidity = reading.humidity_centi.unwrap_or(HUMIDITY_UNKNOWN);
+    // 1/64 V
+    let voltage = reading.battery_mv.map_or(VOLTAGE_UNKNOWN, |mv| {
+        u16::try_from(u32::from(mv) * 64 / 1000).unwrap_or(VOLTAGE_UNKNOWN)
+    });
+    (temperature, humidity, voltage)
+}
@@ -0,0 +1,141 @@
+//! BLE connection state for the rest of the firmware
+//!
+//! Several centrals can be connected at once, so [`BLE_STATE`] is derived from the number of
+//! live connections and the advertiser state rather than from the latest event. The events
+//! themselves, disconnect reasons included, are published by [`BLE_EVENTS`].
+
+use bt_hci::param::Status;
+use core::cell::Cell;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pubsub::PubSubChannel;
+use embassy_sync::watch::Watch;
+
+/// Maximal number of tasks observing the connection state
+const STATE_RECEIVERS_MAX: usize = 2;
+
+/// Events kept for a slow subscriber, older ones are dropped
+const EVENTS_QUEUE_LEN: usize = 4;
+
+/// Maximal number of tasks subscribed to the connection events
+const EVENTS_SUBSCRIBERS_MAX: usize = 2;
+
+/// Connection event of the peripheral role
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BleEvent {
+    /// Advertising has started
+    Advertising,
+    /// A central has connected
+    Connected,
+    /// A central has disconnected, HCI reason attached
+    Disconnected(Status),
+}
+
+/// Connection state of the peripheral role
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum BleState {
+    /// No central is connected, waiting for one to connect
+    Advertising,
+    /// Number of connected centrals (the device may advertise for one more)
+    Connected(u8),
+    /// No central is connected and advertising is stopped (retrying after an error)
+    Idle,
+}
+
+/// Live links of the peripheral role
+#[derive(Debug, Clone, Copy)]
+struct Links {
+    /// Connected centrals
+    connections: u8,
+    /// Advertising is running
+    advertising: bool,
+}
+
+impl Links {
+    /// State shown to the rest of the firmware
+    fn state(self) -> BleState {
+        if self.connections > 0 {
+            BleState::Connected(self.connections)
+        } else if self.advertising {
+            BleState::Advertising
+        } else {
+            BleState::Idle
+        }
+    }
+}
+
+static LINKS: Mutex<CriticalSectionRawMutex, Cell<Links>> = Mutex::new(Cell::new(Links {
+    connections: 0,
+    advertising: false,
+}));
+
+/// Current connection state, only the latest value is kept: receivers see the state, not
+/// every connection event. `None` - the stack has not started yet
+pub static BLE_STATE: Watch<CriticalSectionRawMutex, BleState, STATE_RECEIVERS_MAX> = Watch::new();
+
+/// Every connection event, a subscriber that falls behind loses the oldest ones
+pub static BLE_EVENTS: PubSubChannel<
+    CriticalSectionRawMutex,
+    BleEvent,
+    EVENTS_QUEUE_LEN,
+    EVENTS_SUBSCRIBERS_MAX,
+    1,
+> = PubSubChannel::new();
+
+/// Advertising has started
+pub(crate) fn advertising_started() {
+    // Перезапуск рекламы с новым содержимым - не новое событие
+    if !update(|links| links.advertising = true).advertising {
+        event(BleEvent::Advertising);
+    }
+}
+
+/// Advertising has stopped without a connection
+pub(crate) fn advertising_stopped() {
+    update(|links| links.advertising = false);
+}
+
+/// A central has connected, advertising stops with it
+pub(crate) fn connected() {
+    update(|links| {
+        links.advertising = false;
+        links.connections = links.connections.saturating_add(1);
+    });
+    event(BleEvent::Connected);
+}
+
+/// A central has disconnected
+///
+/// * `reason` - HCI disconnect reason
+pub(crate) fn disconnected(reason: Status) {
+    update(|links| links.connections = links.connections.saturating_sub(1));
+    event(BleEvent::Disconnected(reason));
+}
+
+/// Publish a connection event
+///
+/// * `event` - Event
+fn event(event: BleEvent) {
+    BLE_EVENTS.immediate_publisher().publish_immediate(event);
+}
+
+/// Change the links and publish the derived state if it has changed. Returns the links
+/// before the change
+fn update(change: impl FnOnce(&mut Links)) -> Links {
+    // Публикация под той же блокировкой, чтобы состояния не поменялись местами
+    LINKS.lock(|cell| {
+        let previous = cell.get();
+        let mut links = previous;
+        change(&mut links);
+        cell.set(links);
+        let state = links.state();
+        BLE_STATE.sender().send_if_modified(|current| {
+            if *current == Some(state) {
+                return false;
+            }
+            *current = Some(state);
+            true
+        });
+        previous
+    })
+}
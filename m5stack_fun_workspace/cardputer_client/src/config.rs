@@ -0,0 +1,401 @@
+//! Runtime device configuration
+//!
+//! Starts from the build-time [`AppConfig`] defaults (`config.schema.json`) and can be changed
+//! over BLE (configuration service). Values are exchanged as a compact binary blob of
+//! tag-length-value entries, a write may contain only the changed entries:
+//!
+//! ```text
+//! | format (1) | length (1) | key (1) | len (1) | value (len) | key | len | value | ... | 0 padding |
+//! ```
+//!
+//! Integers are little-endian, booleans are a single byte, the device name is UTF-8. Entries
+//! are identified by the [`ConfigKey`] of the host protocol (`shared_lib`), single entries can
+//! also be staged as protocol [`ConfigRecord`]s.
+//! Changes are staged per client in a [`ConfigTransaction`] and become visible to the rest of
+//! the firmware only on commit; every commit increments the generation published by
+//! [`CONFIG_CHANGED`].
+
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::watch::Watch;
+use heapless::String;
+
+// Public re-export of specifics that are available outside of module
+pub use shared_lib::{ConfigKey, ConfigRecord, ConfigValue};
+
+// Включаем сгенерированный конфиг файл
+include!(concat!(env!("OUT_DIR"), "/config.rs"));
+
+/// Device name capacity, bytes
+pub const NAME_MAX_LEN: usize = AppConfig::DEVICE_NAME_MAX_LEN;
+
+/// Size of the configuration blob (characteristic value)
+pub const CONFIG_BLOB_LEN: usize = 64;
+
+/// Blob format version
+const FORMAT_VERSION: u8 = 1;
+
+/// Blob header: format (1) + length (1)
+const HEADER_LEN: usize = 2;
+
+/// Maximal number of tasks waiting for configuration changes: the sensor loop, one notify task
+/// per BLE connection and the advertiser
+const CONFIG_RECEIVERS_MAX: usize = crate::ble::CONNECTIONS_MAX + 2;
+
+/// Blob tag of an entry, value types:
+/// * `DeviceName` - UTF-8
+/// * `SensorIntervalMs`, `AdvIntervalMs` - u32
+/// * `NotifyEnabled` - u8, 0/1
+/// * `TemperatureOffsetCenti`, `AdvTxPowerDbm` - i32
+///
+/// * `key` - Entry key
+const fn tag_of(key: ConfigKey) -> u8 {
+    match key {
+        ConfigKey::DeviceName => 0x01,
+        ConfigKey::SensorIntervalMs => 0x02,
+        ConfigKey::NotifyEnabled => 0x03,
+        ConfigKey::TemperatureOffsetCenti => 0x04,
+        ConfigKey::AdvIntervalMs => 0x05,
+        ConfigKey::AdvTxPowerDbm => 0x06,
+    }
+}
+
+/// Entry key of a blob tag
+///
+/// * `tag` - Blob tag
+fn key_of(tag: u8) -> Result<ConfigKey, ConfigError> {
+    match tag {
+        0x01 => Ok(ConfigKey::DeviceName),
+        0x02 => Ok(ConfigKey::SensorIntervalMs),
+        0x03 => Ok(ConfigKey::NotifyEnabled),
+        0x04 => Ok(ConfigKey::TemperatureOffsetCenti),
+        0x05 => Ok(ConfigKey::AdvIntervalMs),
+        0x06 => Ok(ConfigKey::AdvTxPowerDbm),
+        _ => Err(ConfigError::UnknownKey(tag)),
+    }
+}
+
+/// Configuration errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ConfigError {
+    /// Blob header or entry framing is malformed, or the format version is unknown
+    Format,
+    /// Unknown entry key
+    UnknownKey(u8),
+    /// Value length (or the record value type) does not match the key type
+    Length(ConfigKey),
+    /// Value is not valid or out of the allowed range
+    OutOfRange(ConfigKey),
+    /// Configuration was committed by someone else since the transaction started
+    Conflict,
+}
+
+/// Device configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceConfig {
+    /// Advertised device name
+    pub device_name: String<NAME_MAX_LEN>,
+    /// Sensor measurement and notification interval, ms
+    pub sensor_interval_ms: u32,
+    /// Send sensor notifications to the connected central
+    pub notify_enabled: bool,
+    /// Calibration offset added to the measured temperature, 0.01 °C
+    pub temperature_offset_centi: i32,
+    /// Advertising interval, ms
+    pub adv_interval_ms: u32,
+    /// Advertising transmit power, dBm
+    pub adv_tx_power_dbm: i32,
+}
+
+impl DeviceConfig {
+    /// Configuration taken from the build-time defaults
+    pub fn defaults() -> Self {
+        Self {
+            // Capacity is the schema limit in characters, ASCII names always fit
+            device_name: String::try_from(AppConfig::DEVICE_NAME).unwrap_or_default(),
+            sensor_interval_ms: AppConfig::SENSOR_INTERVAL_MS,
+            notify_enabled: AppConfig::NOTIFY_ENABLED,
+            temperature_offset_centi: AppConfig::TEMPERATURE_OFFSET_CENTI,
+            adv_interval_ms: AppConfig::ADV_INTERVAL_MS,
+            adv_tx_power_dbm: AppConfig::ADV_TX_POWER_DBM,
+        }
+    }
+
+    /// Serialize all entries into a blob
+    pub fn encode(&self) -> [u8; CONFIG_BLOB_LEN] {
+        let mut blob = [0_u8; CONFIG_BLOB_LEN];
+        let (header, body) = blob.split_at_mut(HEADER_LEN);
+
+        let mut len = 0;
+        let mut put = |key: ConfigKey, value: &[u8]| {
+            let Ok(value_len) = u8::try_from(value.len()) else {
+                return;
+            };
+            if let Some([entry_key, entry_len, entry_value @ ..]) =
+                body.get_mut(len..len + HEADER_LEN + value.len())
+            {
+                *entry_key = tag_of(key);
+                *entry_len = value_len;
+                entry_value.copy_from_slice(value);
+                len += HEADER_LEN + value.len();
+            }
+        };
+        put(ConfigKey::DeviceName, self.device_name.as_bytes());
+        put(
+            ConfigKey::SensorIntervalMs,
+            &self.sensor_interval_ms.to_le_bytes(),
+        );
+        put(ConfigKey::NotifyEnabled, &[u8::from(self.notify_enabled)]);
+        put(
+            ConfigKey::TemperatureOffsetCenti,
+            &self.temperature_offset_centi.to_le_bytes(),
+        );
+        put(
+            ConfigKey::AdvIntervalMs,
+            &self.adv_interval_ms.to_le_bytes(),
+        );
+        put(
+            ConfigKey::AdvTxPowerDbm,
+            &self.adv_tx_power_dbm.to_le_bytes(),
+        );
+
+        // All entries fit into the blob (at most 2 + 49 bytes)
+        header.copy_from_slice(&[FORMAT_VERSION, u8::try_from(len).unwrap_or_default()]);
+        blob
+    }
+
+    /// Apply the entries of a blob. Either all entries are applied or none
+    ///
+    /// * `blob` - Blob with a subset of the entries
+    pub fn apply(&mut self, blob: &[u8]) -> Result<(), ConfigError> {
+        let [format, len, body @ ..] = blob else {
+            return Err(ConfigError::Format);
+        };
+        if *format != FORMAT_VERSION {
+            return Err(ConfigError::Format);
+        }
+        let mut body = body.get(..usize::from(*len)).ok_or(ConfigError::Format)?;
+
+        let mut updated = self.clone();
+        while let [key, len, rest @ ..] = body {
+            let (value, next) = rest
+                .split_at_checked(usize::from(*len))
+                .ok_or(ConfigError::Format)?;
+            updated.set(key_of(*key)?, value)?;
+            body = next;
+        }
+        if !body.is_empty() {
+            return Err(ConfigError::Format);
+        }
+
+        *self = updated;
+        Ok(())
+    }
+
+    /// Entry as a protocol record
+    ///
+    /// * `key` - Entry key
+    pub fn record(&self, key: ConfigKey) -> ConfigRecord {
+        let value = match key {
+            // Protocol text is longer than the name capacity
+            ConfigKey::DeviceName => {
+                ConfigValue::Text(String::try_from(self.device_name.as_str()).unwrap_or_default())
+            }
+            ConfigKey::SensorIntervalMs => ConfigValue::U32(self.sensor_interval_ms),
+            ConfigKey::NotifyEnabled => ConfigValue::Bool(self.notify_enabled),
+            ConfigKey::TemperatureOffsetCenti => ConfigValue::I32(self.temperature_offset_centi),
+            ConfigKey::AdvIntervalMs => ConfigValue::U32(self.adv_interval_ms),
+            ConfigKey::AdvTxPowerDbm => ConfigValue::I32(self.adv_tx_power_dbm),
+        };
+        ConfigRecord { key, value }
+    }
+
+    /// Validate and set a single entry
+    ///
+    /// * `key` - Entry key
+    /// * `value` - Raw entry value
+    fn set(&mut self, key: ConfigKey, value: &[u8]) -> Result<(), ConfigError> {
+        let word = || -> Result<[u8; 4], ConfigError> {
+            value.try_into().map_err(|_| ConfigError::Length(key))
+        };
+        let in_range = |ok: bool| {
+            if ok {
+                Ok(())
+            } else {
+                Err(ConfigError::OutOfRange(key))
+            }
+        };
+
+        match key {
+            ConfigKey::DeviceName => {
+                let name = core::str::from_utf8(value).map_err(|_| ConfigError::OutOfRange(key))?;
+                in_range(!name.is_empty())?;
+                self.device_name = String::try_from(name).map_err(|_| ConfigError::Length(key))?;
+            }
+            ConfigKey::SensorIntervalMs => {
+                let interval = u32::from_le_bytes(word()?);
+                in_range(AppConfig::SENSOR_INTERVAL_MS_RANGE.contains(&interval))?;
+                self.sensor_interval_ms = interval;
+            }
+            ConfigKey::NotifyEnabled => {
+                let [enabled] = value else {
+                    return Err(ConfigError::Length(key));
+                };
+                in_range(*enabled <= 1)?;
+                self.notify_enabled = *enabled == 1;
+            }
+            ConfigKey::TemperatureOffsetCenti => {
+                let offset = i32::from_le_bytes(word()?);
+                in_range(AppConfig::TEMPERATURE_OFFSET_CENTI_RANGE.contains(&offset))?;
+                self.temperature_offset_centi = offset;
+            }
+            ConfigKey::AdvIntervalMs => {
+                let interval = u32::from_le_bytes(word()?);
+                in_range(AppConfig::ADV_INTERVAL_MS_RANGE.contains(&interval))?;
+                self.adv_interval_ms = interval;
+            }
+            ConfigKey::AdvTxPowerDbm => {
+                let power = i32::from_le_bytes(word()?);
+                in_range(AppConfig::ADV_TX_POWER_DBM_RANGE.contains(&power))?;
+                self.adv_tx_power_dbm = power;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Committed configuration, `None` - defaults
+static CONFIG: Mutex<CriticalSectionRawMutex, RefCell<Option<DeviceConfig>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Generation of the committed configuration, changes on every commit
+pub static CONFIG_CHANGED: Watch<CriticalSectionRawMutex, u32, CONFIG_RECEIVERS_MAX> =
+    Watch::new_with(0);
+
+/// Get a copy of the committed configuration
+pub fn current() -> DeviceConfig {
+    CONFIG.lock(|config| {
+        config
+            .borrow()
+            .clone()
+            .unwrap_or_else(DeviceConfig::defaults)
+    })
+}
+
+/// Generation of the committed configuration
+pub fn generation() -> u32 {
+    CONFIG_CHANGED.try_get().unwrap_or_default()
+}
+
+/// Replace the committed configuration and notify the receivers.
+/// Returns the new generation
+///
+/// * `config` - New configuration
+fn publish(config: DeviceConfig) -> u32 {
+    let generation = CONFIG.lock(|current| {
+        current.replace(Some(config));
+        generation().wrapping_add(1)
+    });
+    CONFIG_CHANGED.sender().send(generation);
+    generation
+}
+
+/// Changes of one client, applied to the committed configuration only on commit
+pub struct ConfigTransaction {
+    /// Generation the changes are based on
+    base: u32,
+    /// Committed configuration with the staged changes
+    staged: DeviceConfig,
+}
+
+impl ConfigTransaction {
+    /// Start a transaction from the committed configuration
+    pub fn begin() -> Self {
+        Self {
+            base: generation(),
+            staged: current(),
+        }
+    }
+
+    /// Configuration with the staged changes
+    pub fn staged(&self) -> &DeviceConfig {
+        &self.staged
+    }
+
+    /// Validate and stage the entries of a blob
+    ///
+    /// * `blob` - Blob with the changed entries
+    pub fn stage(&mut self, blob: &[u8]) -> Result<(), ConfigError> {
+        self.staged.apply(blob)
+    }
+
+    /// Validate and stage a single entry
+    ///
+    /// * `key` - Entry key
+    /// * `value` - Raw entry value
+    pub fn stage_entry(&mut self, key: ConfigKey, value: &[u8]) -> Result<(), ConfigError> {
+        self.staged.set(key, value)
+    }
+
+    /// Validate and stage a protocol record
+    ///
+    /// * `record` - Entry key and value
+    pub fn stage_record(&mut self, record: &ConfigRecord) -> Result<(), ConfigError> {
+        let typed = matches!(
+            (record.key, &record.value),
+            (ConfigKey::DeviceName, ConfigValue::Text(_))
+                | (ConfigKey::NotifyEnabled, ConfigValue::Bool(_))
+                | (
+                    ConfigKey::SensorIntervalMs | ConfigKey::AdvIntervalMs,
+                    ConfigValue::U32(_)
+                )
+                | (
+                    ConfigKey::TemperatureOffsetCenti | ConfigKey::AdvTxPowerDbm,
+                    ConfigValue::I32(_)
+                )
+        );
+        if !typed {
+            return Err(ConfigError::Length(record.key));
+        }
+        // Запись переводится в формат блоба, проверки значений общие
+        let flag;
+        let word;
+        let value: &[u8] = match &record.value {
+            ConfigValue::Bool(enabled) => {
+                flag = [u8::from(*enabled)];
+                &flag
+            }
+            ConfigValue::U32(number) => {
+                word = number.to_le_bytes();
+                &word
+            }
+            ConfigValue::I32(number) => {
+                word = number.to_le_bytes();
+                &word
+            }
+            ConfigValue::Text(text) => text.as_bytes(),
+        };
+        self.staged.set(record.key, value)
+    }
+
+    /// Stage the build-time defaults
+    pub fn stage_defaults(&mut self) {
+        self.staged = DeviceConfig::defaults();
+    }
+
+    /// Commit the staged changes. Fails if the configuration has been committed by someone
+    /// else since the transaction started (roll back and retry). Returns the new generation
+    pub fn commit(&mut self) -> Result<u32, ConfigError> {
+        if self.base != generation() {
+            return Err(ConfigError::Conflict);
+        }
+        self.base = publish(self.staged.clone());
+        Ok(self.base)
+    }
+
+    /// Discard the staged changes and start over from the committed configuration
+    pub fn rollback(&mut self) {
+        *self = Self::begin();
+    }
+}
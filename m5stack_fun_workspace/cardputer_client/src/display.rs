@@ -0,0 +1,329 @@
+//! Display - ST7789 screen of the Cardputer showing the pairing passkey and the scan results
+//!
+//! The device pairs as `DisplayOnly`: the central asks the user to type the passkey published
+//! by [`PAIRING_PASSKEY`]. [`start_display`] draws it in a large font and switches the
+//! backlight on; when pairing completes or fails the screen is cleared and the backlight is
+//! switched off again.
+//!
+//! The keyboard ([`KEY_PRESSES`]) opens the device list of the central role: `s` scans, the
+//! arrows select a device, `Enter` connects to it and `d` disconnects. The passkey is shown
+//! over the list while pairing is in progress.
+
+use crate::ble::PAIRING_PASSKEY;
+use crate::ble::central::{
+    self, CENTRAL_CHANGED, CENTRAL_COMMANDS, CentralCommand, CentralState, DEVICES_MAX, Device,
+    ScanFilter,
+};
+use crate::keyboard::{KEY_PRESSES, KeyPress};
+use core::fmt::Write;
+use defmt::{info, warn};
+use embassy_embedded_hal::shared_bus::asynch::spi::SpiDevice;
+use embassy_futures::select::{Either3, select3};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embedded_graphics::{
+    mono_font::{
+        MonoTextStyle,
+        ascii::{FONT_6X10, FONT_9X15, FONT_10X20},
+    },
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use esp_hal::Async;
+use esp_hal::gpio::Output;
+use esp_hal::spi::master::SpiDmaBus;
+use heapless::{String, Vec};
+use lcd_async::{
+    Builder, interface,
+    models::ST7789,
+    options::{ColorInversion, Orientation, Rotation},
+    raw_framebuf::RawFrameBuf,
+};
+use static_cell::StaticCell;
+
+/// Display width, px
+const WIDTH: u16 = 240;
+/// Display height, px
+const HEIGHT: u16 = 135;
+/// RGB565 = 2 bytes per pixel
+const PIXEL_SIZE: usize = 2;
+/// Framebuffer size, bytes
+const FRAME_SIZE: usize = (WIDTH as usize) * (HEIGHT as usize) * PIXEL_SIZE;
+
+/// Device list: title height, px
+const TITLE_HEIGHT: i32 = 18;
+/// Device list: row height, px
+const ROW_HEIGHT: i32 = 12;
+/// Device list: row text capacity - selection mark, address, RSSI and the longest name (the
+/// screen clips what does not fit)
+const ROW_LEN: usize = 1 + 27 + central::NAME_MAX;
+/// Device list: key hint at the bottom
+const LIST_HINT: &str = "s:scan  ;/.:select  Enter:connect  d:off";
+
+/// SPI device of the display
+pub type DisplaySpiDevice =
+    SpiDevice<'static, NoopRawMutex, SpiDmaBus<'static, Async>, Output<'static>>;
+type LcdDisplay = lcd_async::Display<
+    interface::SpiInterface<DisplaySpiDevice, Output<'static>>,
+    ST7789,
+    Output<'static>,
+>;
+type FrameBuffer<'a> = RawFrameBuf<Rgb565, &'a mut [u8]>;
+
+static FRAME_BUFFER: StaticCell<[u8; FRAME_SIZE]> = StaticCell::new();
+
+/// Display errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DisplayError {
+    /// Controller initialization failed
+    Init,
+    /// Framebuffer transfer failed
+    Transfer,
+}
+
+/// ST7789 display with its framebuffer
+struct Screen {
+    /// Display driver
+    display: LcdDisplay,
+    /// Framebuffer memory
+    frame_buffer: &'static mut [u8; FRAME_SIZE],
+}
+
+impl Screen {
+    /// Reset and initialize the controller. Can be called only once (framebuffer is
+    /// statically allocated)
+    ///
+    /// * `spi_device` - SPI device of the display
+    /// * `data_pin` - Data/command select pin
+    /// * `reset_pin` - Reset pin
+    async fn new(
+        spi_device: DisplaySpiDevice,
+        data_pin: Output<'static>,
+        reset_pin: Output<'static>,
+    ) -> Result<Self, DisplayError> {
+        let di = interface::SpiInterface::new(spi_device, data_pin);
+        let mut delay = embassy_time::Delay;
+
+        let display = Builder::new(ST7789, di)
+            .reset_pin(reset_pin)
+            .display_size(HEIGHT, WIDTH)
+            .orientation(Orientation {
+                rotation: Rotation::Deg90,
+                mirrored: false,
+            })
+            .display_offset(52, 40)
+            .invert_colors(ColorInversion::Inverted)
+            .init(&mut delay)
+            .await
+            .map_err(|_| DisplayError::Init)?;
+
+        let frame_buffer = FRAME_BUFFER.init_with(|| [0; FRAME_SIZE]);
+
+        Ok(Self {
+            display,
+            frame_buffer,
+        })
+    }
+
+    /// Framebuffer to draw the next frame on
+    fn frame(&mut self) -> FrameBuffer<'_> {
+        RawFrameBuf::<Rgb565, _>::new(
+            self.frame_buffer.as_mut_slice(),
+            WIDTH.into(),
+            HEIGHT.into(),
+        )
+    }
+
+    /// Send the framebuffer data to the display
+    async fn flush(&mut self) -> Result<(), DisplayError> {
+        self.display
+            .show_raw_data(0, 0, WIDTH, HEIGHT, self.frame_buffer)
+            .await
+            .map_err(|_| DisplayError::Transfer)
+    }
+}
+
+/// Device list of the central role, opened by the keyboard
+#[derive(Debug, Default)]
+struct DeviceList {
+    /// List is shown
+    visible: bool,
+    /// Index of the selected device
+    selected: usize,
+}
+
+impl DeviceList {
+    /// Handle a key press: move the selection or pass a command to the central
+    ///
+    /// * `key` - Pressed key
+    fn handle(&mut self, key: KeyPress) {
+        // Первое нажатие только открывает список
+        let shown = core::mem::replace(&mut self.visible, key != KeyPress::Back);
+        let command = match key {
+            KeyPress::Back => None,
+            KeyPress::Scan => {
+                self.selected = 0;
+                Some(CentralCommand::Scan(ScanFilter::default()))
+            }
+            _ if !shown => None,
+            KeyPress::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                None
+            }
+            KeyPress::Down => {
+                let mut count = 0;
+                central::for_each_device(|_, _| count += 1);
+                self.selected = (self.selected + 1).min(count.saturating_sub(1));
+                None
+            }
+            KeyPress::Enter => Some(CentralCommand::Connect(self.selected)),
+            KeyPress::Disconnect => Some(CentralCommand::Disconnect),
+        };
+        if let Some(command) = command
+            && CENTRAL_COMMANDS.try_send(command).is_err()
+        {
+            warn!("[display] central is busy, {:?} ignored", key);
+        }
+    }
+}
+
+/// Async task - show the pairing passkey while pairing is in progress and the device list
+/// while it is opened by the keyboard
+///
+/// * `spi_device` - SPI device of the display
+/// * `data_pin` - Data/command select pin
+/// * `reset_pin` - Reset pin
+/// * `backlight` - Backlight enable pin
+#[embassy_executor::task]
+pub async fn start_display(
+    spi_device: DisplaySpiDevice,
+    data_pin: Output<'static>,
+    reset_pin: Output<'static>,
+    mut backlight: Output<'static>,
+) {
+    let mut screen = match Screen::new(spi_device, data_pin, reset_pin).await {
+        Ok(screen) => screen,
+        Err(e) => {
+            // Без экрана сопряжение возможно только по ключу из журнала
+            warn!("[display] display is not available: {:?}", e);
+            return;
+        }
+    };
+    info!("[display] ready");
+
+    let mut passkey = None;
+    let mut list = DeviceList::default();
+    loop {
+        let Ok(()) = match passkey {
+            Some(passkey) => draw_passkey(&mut screen.frame(), passkey),
+            None if list.visible => draw_list(&mut screen.frame(), &list),
+            None => screen.frame().clear(Rgb565::BLACK),
+        };
+        if let Err(e) = screen.flush().await {
+            warn!("[display] failed to update the screen: {:?}", e);
+        }
+        // Подсветка только на время сопряжения или пока открыт список
+        backlight.set_level((passkey.is_some() || list.visible).into());
+
+        match select3(
+            PAIRING_PASSKEY.wait(),
+            KEY_PRESSES.receive(),
+            CENTRAL_CHANGED.wait(),
+        )
+        .await
+        {
+            Either3::First(value) => passkey = value,
+            Either3::Second(key) => list.handle(key),
+            // Состояние или список устройств центральной роли изменились
+            Either3::Third(()) => {}
+        }
+    }
+}
+
+/// Draw the passkey screen
+///
+/// * `fb` - Framebuffer to draw on
+/// * `passkey` - Passkey to show
+fn draw_passkey(fb: &mut FrameBuffer<'_>, passkey: u32) -> Result<(), core::convert::Infallible> {
+    fb.clear(Rgb565::BLACK)?;
+
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Top)
+        .build();
+    let center = i32::from(WIDTH) / 2;
+
+    let style = MonoTextStyle::new(&FONT_9X15, Rgb565::CSS_GRAY);
+    Text::with_text_style("Pairing passkey", Point::new(center, 16), style, centered).draw(fb)?;
+
+    // Passkey is always six digits, leading zeros included
+    let mut digits: String<6> = String::new();
+    write!(digits, "{passkey:06}").ok();
+
+    let style = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+    let top = (i32::from(HEIGHT) - 20) / 2;
+    Text::with_text_style(&digits, Point::new(center, top), style, centered).draw(fb)?;
+
+    let style = MonoTextStyle::new(&FONT_9X15, Rgb565::CSS_DIM_GRAY);
+    let hint_top = i32::from(HEIGHT) - 20;
+    Text::with_text_style(
+        "Enter it on the phone",
+        Point::new(center, hint_top),
+        style,
+        centered,
+    )
+    .draw(fb)?;
+    Ok(())
+}
+
+/// Draw the device list of the latest scan
+///
+/// * `fb` - Framebuffer to draw on
+/// * `list` - Device list with the selection
+fn draw_list(fb: &mut FrameBuffer<'_>, list: &DeviceList) -> Result<(), core::convert::Infallible> {
+    fb.clear(Rgb565::BLACK)?;
+    let top_left = TextStyleBuilder::new().baseline(Baseline::Top).build();
+
+    let title = match central::state() {
+        CentralState::Idle => "Devices",
+        CentralState::Scanning => "Scanning...",
+        CentralState::Connecting => "Connecting...",
+        CentralState::Connected => "Connected",
+    };
+    let style = MonoTextStyle::new(&FONT_9X15, Rgb565::WHITE);
+    Text::with_text_style(title, Point::new(0, 0), style, top_left).draw(fb)?;
+
+    // Копия списка, чтобы не рисовать внутри критической секции
+    let mut devices: Vec<Device, DEVICES_MAX> = Vec::new();
+    central::for_each_device(|_, device| {
+        let _ = devices.push(device.clone());
+    });
+    if devices.is_empty() {
+        let style = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_DIM_GRAY);
+        Text::with_text_style(
+            "No devices, press s to scan",
+            Point::new(0, TITLE_HEIGHT),
+            style,
+            top_left,
+        )
+        .draw(fb)?;
+    }
+    for ((index, device), row) in devices.iter().enumerate().zip(0..) {
+        let selected = index == list.selected;
+        let mut text: String<ROW_LEN> = String::new();
+        write!(text, "{}{device}", if selected { '>' } else { ' ' }).ok();
+        let color = if selected {
+            Rgb565::CSS_LIGHT_GREEN
+        } else {
+            Rgb565::CSS_GRAY
+        };
+        let style = MonoTextStyle::new(&FONT_6X10, color);
+        let top = TITLE_HEIGHT + ROW_HEIGHT * row;
+        Text::with_text_style(&text, Point::new(0, top), style, top_left).draw(fb)?;
+    }
+
+    let style = MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_DIM_GRAY);
+    let hint_top = i32::from(HEIGHT) - 10;
+    Text::with_text_style(LIST_HINT, Point::new(0, hint_top), style, top_left).draw(fb)?;
+    Ok(())
+}
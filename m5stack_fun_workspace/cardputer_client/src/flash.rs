@@ -0,0 +1,87 @@
+//! Shared flash - one `FlashStorage` for the bond storage and the firmware update
+//!
+//! [`init`] takes the flash driver once, [`SharedFlash`] handles borrow it for every operation.
+
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+use embedded_storage::{ReadStorage, Storage};
+use esp_storage::{FlashStorage, FlashStorageError};
+
+/// Flash driver, `None` - not initialized yet
+static FLASH: Mutex<CriticalSectionRawMutex, RefCell<Option<FlashStorage<'static>>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Hand the flash driver over to the shared handles
+///
+/// * `flash` - Flash driver
+pub fn init(flash: FlashStorage<'static>) {
+    FLASH.lock(|shared| shared.replace(Some(flash)));
+}
+
+/// Handle of the shared flash. Operations before [`init`] fail with
+/// [`FlashStorageError::IoError`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SharedFlash;
+
+impl SharedFlash {
+    /// Run an operation on the flash driver
+    ///
+    /// * `f` - Operation
+    fn with<T>(
+        f: impl FnOnce(&mut FlashStorage<'static>) -> Result<T, FlashStorageError>,
+    ) -> Result<T, FlashStorageError> {
+        FLASH.lock(|shared| match shared.borrow_mut().as_mut() {
+            Some(flash) => f(flash),
+            None => Err(FlashStorageError::IoError),
+        })
+    }
+}
+
+impl ErrorType for SharedFlash {
+    type Error = FlashStorageError;
+}
+
+impl ReadNorFlash for SharedFlash {
+    const READ_SIZE: usize = FlashStorage::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        Self::with(|flash| ReadNorFlash::read(flash, offset, bytes))
+    }
+
+    fn capacity(&self) -> usize {
+        Self::with(|flash| Ok(ReadNorFlash::capacity(flash))).unwrap_or_default()
+    }
+}
+
+impl NorFlash for SharedFlash {
+    const WRITE_SIZE: usize = FlashStorage::WRITE_SIZE;
+    const ERASE_SIZE: usize = FlashStorage::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        Self::with(|flash| NorFlash::erase(flash, from, to))
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        Self::with(|flash| NorFlash::write(flash, offset, bytes))
+    }
+}
+
+impl ReadStorage for SharedFlash {
+    type Error = FlashStorageError;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        Self::with(|flash| ReadStorage::read(flash, offset, bytes))
+    }
+
+    fn capacity(&self) -> usize {
+        Self::with(|flash| Ok(ReadStorage::capacity(flash))).unwrap_or_default()
+    }
+}
+
+impl Storage for SharedFlash {
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        Self::with(|flash| Storage::write(flash, offset, bytes))
+    }
+}
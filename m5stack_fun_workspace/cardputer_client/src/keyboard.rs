@@ -0,0 +1,142 @@
+//! Keyboard - key matrix of the Cardputer driving the device scan screen
+//!
+//! The keys form a matrix of 8 rows, selected by a 3 bit address on `A0`..`A2`, and 7 columns
+//! `Y0`..`Y6` (low while a key of the selected row is pressed). Only the keys of the scan
+//! screen are decoded, every new press is sent to [`KEY_PRESSES`]:
+//! * `s` - scan, `d` - disconnect
+//! * `;` / `.` (arrows up/down) - select a device, `Enter` - connect to it
+//! * `` ` `` (Esc) or `Backspace` - hide the screen
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::Timer;
+use esp_hal::gpio::{Input, InputConfig, Level, Output, OutputConfig, Pull};
+use esp_hal::peripherals::{
+    GPIO3, GPIO4, GPIO5, GPIO6, GPIO7, GPIO8, GPIO9, GPIO11, GPIO13, GPIO15,
+};
+
+/// Matrix scan period, ms
+const SCAN_PERIOD_MS: u64 = 10;
+
+/// Presses waiting for the display, extra presses are dropped
+const PENDING_PRESSES: usize = 4;
+
+/// Keys of the scan screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum KeyPress {
+    /// Select the previous device
+    Up,
+    /// Select the next device
+    Down,
+    /// Connect to the selected device
+    Enter,
+    /// Hide the screen
+    Back,
+    /// Start a scan
+    Scan,
+    /// Disconnect from the device
+    Disconnect,
+}
+
+/// Key presses for the display
+pub static KEY_PRESSES: Channel<CriticalSectionRawMutex, KeyPress, PENDING_PRESSES> =
+    Channel::new();
+
+/// Matrix bit of a key
+///
+/// * `row` - Row address
+/// * `column` - Column input
+const fn key(row: u32, column: u32) -> u64 {
+    1 << (row * 8 + column)
+}
+
+/// Decoded keys: matrix bits and the press they produce
+const KEYMAP: [(u64, KeyPress); 7] = [
+    (key(1, 5), KeyPress::Up),         // ;
+    (key(0, 5), KeyPress::Down),       // .
+    (key(1, 6), KeyPress::Enter),      // Enter
+    (key(7, 0), KeyPress::Back),       // ` (Esc)
+    (key(3, 6), KeyPress::Back),       // Backspace
+    (key(1, 1), KeyPress::Scan),       // s
+    (key(5, 2), KeyPress::Disconnect), // d
+];
+
+/// Key matrix of the Cardputer
+pub struct Keyboard<'a> {
+    /// Row address outputs `A0`..`A2`
+    address: [Output<'a>; 3],
+    /// Column inputs `Y0`..`Y6`
+    columns: [Input<'a>; 7],
+}
+
+impl<'a> Keyboard<'a> {
+    /// Configure the matrix pins
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        a0: GPIO8<'a>,
+        a1: GPIO9<'a>,
+        a2: GPIO11<'a>,
+        y0: GPIO13<'a>,
+        y1: GPIO15<'a>,
+        y2: GPIO3<'a>,
+        y3: GPIO4<'a>,
+        y4: GPIO5<'a>,
+        y5: GPIO6<'a>,
+        y6: GPIO7<'a>,
+    ) -> Self {
+        let output = OutputConfig::default();
+        let input = InputConfig::default().with_pull(Pull::Up);
+        Self {
+            address: [
+                Output::new(a0, Level::Low, output),
+                Output::new(a1, Level::Low, output),
+                Output::new(a2, Level::Low, output),
+            ],
+            columns: [
+                Input::new(y0, input),
+                Input::new(y1, input),
+                Input::new(y2, input),
+                Input::new(y3, input),
+                Input::new(y4, input),
+                Input::new(y5, input),
+                Input::new(y6, input),
+            ],
+        }
+    }
+
+    /// Matrix bits of the held keys
+    fn scan(&mut self) -> u64 {
+        let mut held = 0;
+        for row in 0..8_u32 {
+            for (bit, pin) in (0..).zip(self.address.iter_mut()) {
+                pin.set_level(Level::from((row >> bit) & 1 != 0));
+            }
+            for (column, pin) in (0..).zip(self.columns.iter()) {
+                if pin.is_low() {
+                    held |= key(row, column);
+                }
+            }
+        }
+        held
+    }
+}
+
+/// Async task - scan the key matrix and send the presses of the scan screen keys
+///
+/// * `keyboard` - Cardputer's keyboard matrix
+#[embassy_executor::task]
+pub async fn start_keyboard(mut keyboard: Keyboard<'static>) {
+    let mut held = 0;
+    loop {
+        let keys = keyboard.scan();
+        // Только новые нажатия, удержание не повторяется
+        let pressed = keys & !held;
+        held = keys;
+        for (bits, press) in KEYMAP {
+            if pressed & bits != 0 {
+                let _ = KEY_PRESSES.try_send(press);
+            }
+        }
+        Timer::after_millis(SCAN_PERIOD_MS).await;
+    }
+}
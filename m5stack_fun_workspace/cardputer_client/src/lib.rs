@@ -1,2 +1,9 @@
 #![no_std]
 pub mod ble;
+pub mod config;
+pub mod display;
+pub mod flash;
+pub mod keyboard;
+pub mod ota;
+pub mod sensors;
+pub mod shell;
@@ -0,0 +1,436 @@
+//! Firmware update - image transfer into the inactive OTA slot, verification and activation
+//!
+//! 1. [`begin`] with the image size and SHA-256 selects the inactive slot. If an update of the
+//!    same image is already in progress (the previous connection dropped) it is resumed and the
+//!    number of received bytes is returned
+//! 2. [`write`] appends CRC-32 protected chunks; chunks must be contiguous and a multiple of
+//!    4 bytes long (except the last one). Sectors are erased as the image grows
+//! 3. [`finish`] hands the complete image to [`ota_task`], which reads it back, checks the
+//!    SHA-256 and makes the slot the boot slot (state `New`). The hashing takes seconds, so it
+//!    runs outside of the BLE write handler; [`progress`] and [`verify_error`] report the
+//!    result. [`request_reboot`] restarts into the activated image
+//!
+//! The new image must confirm itself: [`ota_task`] marks it valid once the BLE stack is up.
+//! An image that resets before that is rolled back by the bootloader. This needs a bootloader
+//! built with `CONFIG_BOOTLOADER_APP_ROLLBACK_ENABLE` (the one bundled with espflash is not):
+//! `cargo run-rollback` flashes `bootloader/bootloader.bin`, see `bootloader/README.md`.
+//! The update state lives in RAM, a reset starts the transfer from scratch.
+
+use crate::ble::{BLE_STATE, BleState};
+use crate::flash::SharedFlash;
+use crate::shell;
+use config_store::Crc32;
+use core::cell::{Cell, RefCell};
+use defmt::{info, warn};
+use embassy_futures::select::{Either, select};
+use embassy_futures::yield_now;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use esp_bootloader_esp_idf::ota::OtaImageState;
+use esp_bootloader_esp_idf::ota_updater::OtaUpdater;
+use esp_bootloader_esp_idf::partitions::{self, PartitionType};
+use sha2::{Digest, Sha256};
+
+/// SHA-256 digest length, bytes
+pub const DIGEST_LEN: usize = 32;
+
+/// Maximal chunk length, bytes
+pub const CHUNK_MAX: usize = 236;
+
+/// Flash write granularity, bytes
+const WRITE_SIZE: u32 = 4;
+
+/// Flash erase sector, bytes
+const SECTOR_SIZE: u32 = 4096;
+
+/// Read-back block for the verification, bytes
+const VERIFY_BLOCK: usize = 256;
+
+/// Time the new image has to run with a working BLE stack before it is confirmed, ms
+const CONFIRM_DELAY_MS: u64 = 10_000;
+
+/// Time for the last response to reach the central before the reboot, ms
+const REBOOT_DELAY_MS: u64 = 500;
+
+/// Firmware update errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum OtaError {
+    /// Partition table or OTA data could not be used
+    Partition,
+    /// Flash read/write/erase failed
+    Flash,
+    /// No update in progress or the image is not complete
+    State,
+    /// Chunk does not continue the image, expected offset attached
+    Offset(u32),
+    /// Chunk CRC-32 mismatch
+    Crc,
+    /// Chunk length is not allowed (alignment or past the image end)
+    Length,
+    /// Image does not fit into the OTA slot
+    TooLarge,
+    /// SHA-256 of the written image does not match
+    Digest,
+}
+
+/// Stage of the update in progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum OtaPhase {
+    /// Chunks are being received
+    Receiving,
+    /// The complete image is being verified by [`ota_task`]
+    Verifying,
+    /// The image is verified and will be booted after a reboot
+    Activated,
+}
+
+/// Update progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct OtaProgress {
+    /// Stage of the update
+    pub phase: OtaPhase,
+    /// Image size, bytes
+    pub size: u32,
+    /// Received bytes
+    pub written: u32,
+}
+
+/// Update in progress
+struct Update {
+    /// Flash offset of the target slot
+    offset: u32,
+    /// Image size, bytes
+    size: u32,
+    /// Expected SHA-256 of the image
+    digest: [u8; DIGEST_LEN],
+    /// Received bytes
+    written: u32,
+    /// Bytes of the slot erased so far
+    erased: u32,
+    /// Stage of the update
+    phase: OtaPhase,
+}
+
+/// Update in progress, `None` - idle
+static UPDATE: Mutex<CriticalSectionRawMutex, RefCell<Option<Update>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Error of the latest verification, `None` - no verification failed since [`begin`]
+static VERIFY_ERROR: Mutex<CriticalSectionRawMutex, Cell<Option<OtaError>>> =
+    Mutex::new(Cell::new(None));
+
+/// Verify the received image
+static VERIFY: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Reboot into the activated image
+static REBOOT: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Start or resume an update. Returns the number of bytes already received
+///
+/// * `size` - Image size, bytes
+/// * `digest` - SHA-256 of the image
+pub fn begin(size: u32, digest: [u8; DIGEST_LEN]) -> Result<u32, OtaError> {
+    VERIFY_ERROR.lock(|error| error.set(None));
+    if let Some(written) = UPDATE.lock(|update| {
+        update
+            .borrow()
+            .as_ref()
+            .filter(|update| update.size == size && update.digest == digest)
+            .map(|update| update.written)
+    }) {
+        info!("[ota] resuming at {}", written);
+        return Ok(written);
+    }
+
+    let (offset, len) = next_slot()?;
+    if size == 0 || size > len {
+        return Err(OtaError::TooLarge);
+    }
+    info!("[ota] new image, {} bytes at {:#x}", size, offset);
+    shell::log(format_args!("[ota] receiving {size} bytes"));
+    UPDATE.lock(|update| {
+        update.replace(Some(Update {
+            offset,
+            size,
+            digest,
+            written: 0,
+            erased: 0,
+            phase: OtaPhase::Receiving,
+        }))
+    });
+    Ok(0)
+}
+
+/// Write the next chunk. Returns the number of received bytes.
+/// A repeated chunk (the response got lost) is accepted again without writing
+///
+/// * `offset` - Chunk offset in the image
+/// * `crc` - CRC-32 of the chunk
+/// * `data` - Chunk
+pub fn write(offset: u32, crc: u32, data: &[u8]) -> Result<u32, OtaError> {
+    let mut checksum = Crc32::new();
+    checksum.update(data);
+    if checksum.finish() != crc {
+        return Err(OtaError::Crc);
+    }
+    let len = u32::try_from(data.len()).map_err(|_| OtaError::Length)?;
+
+    UPDATE.lock(|update| {
+        let mut update = update.borrow_mut();
+        let update = update.as_mut().ok_or(OtaError::State)?;
+        let end = offset.checked_add(len).ok_or(OtaError::Length)?;
+        if end <= update.written {
+            return Ok(update.written);
+        }
+        if offset != update.written {
+            return Err(OtaError::Offset(update.written));
+        }
+        if end > update.size || (end < update.size && len % WRITE_SIZE != 0) {
+            return Err(OtaError::Length);
+        }
+
+        let mut flash = SharedFlash;
+        // Стирание по мере роста образа
+        let start = update.offset + offset;
+        let needed = end.div_ceil(WRITE_SIZE) * WRITE_SIZE;
+        if needed > update.erased {
+            let erase_to = needed.div_ceil(SECTOR_SIZE) * SECTOR_SIZE;
+            flash
+                .erase(update.offset + update.erased, update.offset + erase_to)
+                .map_err(|_| OtaError::Flash)?;
+            update.erased = erase_to;
+        }
+        // Последний фрагмент дополняется стёртыми байтами до границы записи
+        let mut buffer = [0xFF_u8; CHUNK_MAX + WRITE_SIZE as usize];
+        let padded = buffer
+            .get_mut(..(needed - offset) as usize)
+            .ok_or(OtaError::Length)?;
+        padded
+            .get_mut(..data.len())
+            .ok_or(OtaError::Length)?
+            .copy_from_slice(data);
+        flash.write(start, padded).map_err(|_| OtaError::Flash)?;
+
+        update.written = end;
+        Ok(end)
+    })
+}
+
+/// Request the verification and activation of the received image, done by [`ota_task`].
+/// A repeated request while the image is verified or already activated is accepted
+pub fn finish() -> Result<(), OtaError> {
+    UPDATE.lock(|update| {
+        let mut update = update.borrow_mut();
+        let update = update
+            .as_mut()
+            .filter(|update| update.written == update.size)
+            .ok_or(OtaError::State)?;
+        if update.phase == OtaPhase::Receiving {
+            update.phase = OtaPhase::Verifying;
+            VERIFY_ERROR.lock(|error| error.set(None));
+            VERIFY.signal(());
+        }
+        Ok(())
+    })
+}
+
+/// Error of the latest verification, cleared by [`begin`] and [`finish`]
+pub fn verify_error() -> Option<OtaError> {
+    VERIFY_ERROR.lock(Cell::get)
+}
+
+/// Verify the image requested by [`finish`] and activate it
+async fn verify() {
+    let Some((offset, size, digest)) = UPDATE.lock(|update| {
+        update
+            .borrow()
+            .as_ref()
+            .filter(|update| update.phase == OtaPhase::Verifying)
+            .map(|update| (update.offset, update.size, update.digest))
+    }) else {
+        return;
+    };
+
+    let result = match read_digest(offset, size).await {
+        Ok(actual) if actual == digest => activate(&digest),
+        Ok(_) => Err(OtaError::Digest),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
+            info!("[ota] image verified and activated");
+            shell::log(format_args!("[ota] image activated, reboot to run it"));
+        }
+        Err(OtaError::Digest) => {
+            warn!("[ota] image digest mismatch");
+            VERIFY_ERROR.lock(|error| error.set(Some(OtaError::Digest)));
+            // Сбрасывается только проверенное обновление, а не начатое за время проверки
+            UPDATE.lock(|update| {
+                let mut update = update.borrow_mut();
+                if update
+                    .as_ref()
+                    .is_some_and(|update| update.digest == digest)
+                {
+                    *update = None;
+                }
+            });
+        }
+        Err(e) => {
+            // Образ остаётся принятым, проверку можно запросить снова
+            warn!("[ota] verification failed: {:?}", e);
+            VERIFY_ERROR.lock(|error| error.set(Some(e)));
+            UPDATE.lock(|update| {
+                if let Some(update) = update.borrow_mut().as_mut()
+                    && update.phase == OtaPhase::Verifying
+                {
+                    update.phase = OtaPhase::Receiving;
+                }
+            });
+        }
+    }
+}
+
+/// SHA-256 of the written image. Other tasks run between the blocks
+///
+/// * `offset` - Flash offset of the image
+/// * `size` - Image size, bytes
+async fn read_digest(offset: u32, size: u32) -> Result<[u8; DIGEST_LEN], OtaError> {
+    // Проверяется то, что действительно записано во flash
+    let mut flash = SharedFlash;
+    let mut hasher = Sha256::new();
+    let mut block = [0_u8; VERIFY_BLOCK];
+    let mut position = 0;
+    while position < size {
+        let len = (size - position).min(VERIFY_BLOCK as u32);
+        let chunk = block.get_mut(..len as usize).ok_or(OtaError::Length)?;
+        flash
+            .read(offset + position, chunk)
+            .map_err(|_| OtaError::Flash)?;
+        hasher.update(&*chunk);
+        position += len;
+        yield_now().await;
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Make the verified slot the boot slot, unless the update was aborted or replaced meanwhile
+///
+/// * `digest` - SHA-256 of the verified image
+fn activate(digest: &[u8; DIGEST_LEN]) -> Result<(), OtaError> {
+    UPDATE.lock(|update| {
+        let mut update = update.borrow_mut();
+        let update = update
+            .as_mut()
+            .filter(|update| update.phase == OtaPhase::Verifying && update.digest == *digest)
+            .ok_or(OtaError::State)?;
+
+        let mut flash = SharedFlash;
+        let mut table_buffer = [0_u8; partitions::PARTITION_TABLE_MAX_LEN];
+        let mut updater =
+            OtaUpdater::new(&mut flash, &mut table_buffer).map_err(|_| OtaError::Partition)?;
+        updater
+            .activate_next_partition()
+            .and_then(|()| updater.set_current_ota_state(OtaImageState::New))
+            .map_err(|_| OtaError::Partition)?;
+        update.phase = OtaPhase::Activated;
+        Ok(())
+    })
+}
+
+/// Drop the update in progress
+pub fn abort() {
+    VERIFY.reset();
+    if UPDATE.lock(|update| update.replace(None)).is_some() {
+        info!("[ota] update aborted");
+    }
+}
+
+/// Progress of the update in progress, `None` - idle
+pub fn progress() -> Option<OtaProgress> {
+    UPDATE.lock(|update| {
+        update.borrow().as_ref().map(|update| OtaProgress {
+            phase: update.phase,
+            size: update.size,
+            written: update.written,
+        })
+    })
+}
+
+/// Reboot shortly (after the response has been sent)
+pub fn request_reboot() {
+    REBOOT.signal(());
+}
+
+/// Confirm the running image once the BLE stack works, verify received images and reboot
+/// on request
+#[embassy_executor::task]
+pub async fn ota_task() {
+    let confirm = async {
+        confirm_image().await;
+        core::future::pending::<()>().await;
+    };
+    let serve = async {
+        while let Either::First(()) = select(VERIFY.wait(), REBOOT.wait()).await {
+            verify().await;
+        }
+    };
+    select(confirm, serve).await;
+    info!("[ota] rebooting");
+    Timer::after_millis(REBOOT_DELAY_MS).await;
+    esp_hal::system::software_reset();
+}
+
+/// Mark a new image valid, so the bootloader does not roll it back
+async fn confirm_image() {
+    let mut table_buffer = [0_u8; partitions::PARTITION_TABLE_MAX_LEN];
+    let mut flash = SharedFlash;
+    let state = OtaUpdater::new(&mut flash, &mut table_buffer)
+        .and_then(|mut updater| updater.current_ota_state());
+    match state {
+        Ok(OtaImageState::New | OtaImageState::PendingVerify) => {}
+        Ok(_) => return,
+        Err(e) => {
+            warn!("[ota] OTA data is not available: {:?}", e);
+            return;
+        }
+    }
+
+    let Some(mut receiver) = BLE_STATE.receiver() else {
+        warn!("[ota] no free BLE state receiver, the image is not confirmed");
+        return;
+    };
+    receiver
+        .changed_and(|state| matches!(state, BleState::Advertising | BleState::Connected(_)))
+        .await;
+    Timer::after_millis(CONFIRM_DELAY_MS).await;
+
+    let confirmed = OtaUpdater::new(&mut flash, &mut table_buffer)
+        .and_then(|mut updater| updater.set_current_ota_state(OtaImageState::Valid));
+    match confirmed {
+        Ok(()) => info!("[ota] new image confirmed"),
+        Err(e) => warn!("[ota] failed to confirm the image: {:?}", e),
+    }
+}
+
+/// Flash offset and length of the inactive OTA slot
+fn next_slot() -> Result<(u32, u32), OtaError> {
+    let mut flash = SharedFlash;
+    let mut table_buffer = [0_u8; partitions::PARTITION_TABLE_MAX_LEN];
+    let slot = {
+        let mut updater =
+            OtaUpdater::new(&mut flash, &mut table_buffer).map_err(|_| OtaError::Partition)?;
+        let (_, slot) = updater.next_partition().map_err(|_| OtaError::Partition)?;
+        slot
+    };
+
+    let table = partitions::read_partition_table(&mut flash, &mut table_buffer)
+        .map_err(|_| OtaError::Partition)?;
+    let partition = table
+        .find_partition(PartitionType::App(slot))
+        .map_err(|_| OtaError::Partition)?
+        .ok_or(OtaError::Partition)?;
+    Ok((partition.offset(), partition.len()))
+}
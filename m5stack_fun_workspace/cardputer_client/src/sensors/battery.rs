@@ -0,0 +1,91 @@
+//! Battery voltage - ADC1 on GPIO10 behind a 1:2 resistor divider
+
+use embassy_time::Timer;
+use esp_hal::Blocking;
+use esp_hal::analog::adc::{Adc, AdcCalCurve, AdcConfig, AdcPin, Attenuation};
+use esp_hal::peripherals::{ADC1, GPIO10};
+
+/// Battery voltage divider ratio
+const DIVIDER: u32 = 2;
+
+/// Samples averaged per reading
+const SAMPLES: u32 = 8;
+
+/// Poll period while a conversion is running, µs
+const POLL_US: u64 = 20;
+
+/// LiPo discharge curve at a light load: voltage (mV, descending) and charge level (%)
+const DISCHARGE_CURVE: [(u16, u8); 7] = [
+    (4200, 100),
+    (4000, 80),
+    (3850, 60),
+    (3750, 40),
+    (3650, 20),
+    (3500, 5),
+    (3300, 0),
+];
+
+/// Calibrated ADC pin type
+type BatteryPin = AdcPin<GPIO10<'static>, ADC1<'static>, AdcCalCurve<ADC1<'static>>>;
+
+/// Battery voltage sensor
+pub struct Battery {
+    adc: Adc<'static, ADC1<'static>, Blocking>,
+    pin: BatteryPin,
+}
+
+impl Battery {
+    /// Battery sensor constructor
+    ///
+    /// * `adc` - ADC1 peripheral
+    /// * `pin` - Divider output pin
+    pub fn new(adc: ADC1<'static>, pin: GPIO10<'static>) -> Self {
+        let mut config = AdcConfig::new();
+        // 11 dB - up to ~3.1 V at the pin, enough for a full LiPo behind the divider
+        let pin =
+            config.enable_pin_with_cal::<_, AdcCalCurve<ADC1<'static>>>(pin, Attenuation::_11dB);
+        Self {
+            adc: Adc::new(adc, config),
+            pin,
+        }
+    }
+
+    /// Read the battery voltage, mV. Returns `None` if the ADC fails
+    pub async fn read_mv(&mut self) -> Option<u16> {
+        let mut sum = 0_u32;
+        for _ in 0..SAMPLES {
+            let pin_mv = loop {
+                match self.adc.read_oneshot(&mut self.pin) {
+                    Ok(mv) => break mv,
+                    Err(nb::Error::WouldBlock) => Timer::after_micros(POLL_US).await,
+                    Err(nb::Error::Other(_)) => return None,
+                }
+            };
+            sum += u32::from(pin_mv);
+        }
+        u16::try_from(sum / SAMPLES * DIVIDER).ok()
+    }
+}
+
+/// Estimate the battery charge level from its voltage
+///
+/// * `mv` - Battery voltage, mV
+pub fn level_percent(mv: u16) -> u8 {
+    let mut upper = (u16::MAX, 100);
+    for (point_mv, point_percent) in DISCHARGE_CURVE {
+        if mv >= point_mv {
+            // Линейная интерполяция между точками кривой
+            let (upper_mv, upper_percent) = upper;
+            if upper_mv == u16::MAX {
+                return point_percent;
+            }
+            let span_mv = u32::from(upper_mv - point_mv);
+            let span_percent = u32::from(upper_percent - point_percent);
+            let above = u32::from(mv - point_mv);
+            let percent = u32::from(point_percent) + above * span_percent / span_mv;
+            return u8::try_from(percent).unwrap_or(upper_percent);
+        }
+        upper = (point_mv, point_percent);
+    }
+    0
+}
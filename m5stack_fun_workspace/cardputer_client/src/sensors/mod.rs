@@ -0,0 +1,79 @@
+//! Sensors - SHTC3 climate sensor on the Grove port and the battery voltage
+//!
+//! Measurements are taken every `sensor_interval_ms` (runtime configuration) and published by
+//! [`MEASUREMENT`]. Sampling can be paused through [`SENSOR_SETTINGS`].
+
+mod battery;
+mod sensor_loop;
+mod shtc3;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::watch::Watch;
+use shared_lib::SensorReading;
+
+// Public re-export of specifics that are available outside of module
+pub use battery::{Battery, level_percent};
+pub use sensor_loop::start_sensors;
+pub use shtc3::{Climate, Shtc3, Shtc3Error};
+
+/// Maximal number of tasks waiting for measurements: one notify task per BLE connection and
+/// the advertiser (sensor snapshot)
+const MEASUREMENT_RECEIVERS_MAX: usize = crate::ble::CONNECTIONS_MAX + 1;
+
+/// Maximal number of tasks waiting for sensor settings changes
+const SETTINGS_RECEIVERS_MAX: usize = 2;
+
+/// Sensor settings changed at runtime (not persisted, sampling is enabled after reset)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct SensorSettings {
+    /// Take measurements, `false` - the sensors stay asleep
+    pub sampling_enabled: bool,
+}
+
+impl SensorSettings {
+    /// Settings after reset
+    pub const DEFAULT: Self = Self {
+        sampling_enabled: true,
+    };
+}
+
+/// Result of one measurement cycle, `None` - the sensor is not available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Measurement {
+    /// Measurement counter since reset
+    pub sequence: u32,
+    /// Temperature and humidity, temperature offset already applied
+    pub climate: Option<Climate>,
+    /// Battery voltage, mV
+    pub battery_mv: Option<u16>,
+}
+
+impl From<Measurement> for SensorReading {
+    fn from(measurement: Measurement) -> Self {
+        Self {
+            sequence: measurement.sequence,
+            temperature_centi: measurement.climate.map(|climate| climate.temperature_centi),
+            humidity_centi: measurement.climate.map(|climate| climate.humidity_centi),
+            battery_mv: measurement.battery_mv,
+        }
+    }
+}
+
+/// Latest measurement
+pub static MEASUREMENT: Watch<CriticalSectionRawMutex, Measurement, MEASUREMENT_RECEIVERS_MAX> =
+    Watch::new();
+
+/// Current sensor settings, every change is published to the receivers
+pub static SENSOR_SETTINGS: Watch<CriticalSectionRawMutex, SensorSettings, SETTINGS_RECEIVERS_MAX> =
+    Watch::new_with(SensorSettings::DEFAULT);
+
+/// Change the sensor settings, receivers are notified only about real changes
+///
+/// * `settings` - New settings
+pub fn update_settings(settings: SensorSettings) {
+    SENSOR_SETTINGS.sender().send_if_modified(|current| {
+        let modified = *current != Some(settings);
+        *current = Some(settings);
+        modified
+    });
+}
@@ -0,0 +1,84 @@
+use super::{Battery, Climate, MEASUREMENT, Measurement, SENSOR_SETTINGS, Shtc3};
+use crate::config::{self, CONFIG_CHANGED};
+use crate::shell;
+use defmt::{info, warn};
+use embassy_futures::select::select3;
+use embassy_time::Timer;
+use esp_hal::Async;
+use esp_hal::i2c::master::I2c;
+
+/// Measure periodically and publish the results, sleep while sampling is disabled
+///
+/// * `i2c` - I2C bus with the SHTC3
+/// * `battery` - Battery voltage sensor
+#[embassy_executor::task]
+pub async fn start_sensors(i2c: I2c<'static, Async>, mut battery: Battery) {
+    let mut shtc3 = Shtc3::new(i2c);
+    match shtc3.id().await {
+        Ok(id) => info!("[sensors] SHTC3 ID: {:#06x}", id),
+        Err(e) => warn!("[sensors] SHTC3 is not available: {:?}", e),
+    }
+
+    let sender = MEASUREMENT.sender();
+    // Новый интервал применяется сразу, не дожидаясь окончания текущего
+    let mut config_changed = CONFIG_CHANGED
+        .receiver()
+        .expect("No free configuration receiver");
+    config_changed.get().await;
+    let mut settings_changed = SENSOR_SETTINGS
+        .receiver()
+        .expect("No free sensor settings receiver");
+    let mut sequence: u32 = 0;
+    loop {
+        if !settings_changed.get().await.sampling_enabled {
+            info!("[sensors] sampling paused");
+            settings_changed
+                .changed_and(|settings| settings.sampling_enabled)
+                .await;
+            info!("[sensors] sampling resumed");
+        }
+
+        let config = config::current();
+        let climate = match shtc3.measure().await {
+            Ok(climate) => Some(Climate {
+                temperature_centi: climate
+                    .temperature_centi
+                    .saturating_add(offset(config.temperature_offset_centi)),
+                ..climate
+            }),
+            Err(e) => {
+                warn!("[sensors] SHTC3 measurement failed: {:?}", e);
+                None
+            }
+        };
+        sequence = sequence.wrapping_add(1);
+        let measurement = Measurement {
+            sequence,
+            climate,
+            battery_mv: battery.read_mv().await,
+        };
+        info!("[sensors] {:?}", measurement);
+        shell::log(format_args!(
+            "[sensors] climate: {:?}, battery: {:?} mV",
+            measurement
+                .climate
+                .map(|c| (c.temperature_centi, c.humidity_centi)),
+            measurement.battery_mv
+        ));
+        sender.send(measurement);
+
+        select3(
+            Timer::after_millis(u64::from(config.sensor_interval_ms)),
+            config_changed.changed(),
+            settings_changed.changed(),
+        )
+        .await;
+    }
+}
+
+/// Temperature offset limited to the measurement type (the schema range always fits)
+///
+/// * `offset_centi` - Configured offset, 0.01 °C
+fn offset(offset_centi: i32) -> i16 {
+    i16::try_from(offset_centi).unwrap_or_default()
+}
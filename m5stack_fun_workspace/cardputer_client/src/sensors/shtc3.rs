@@ -0,0 +1,163 @@
+//! SHTC3 temperature and humidity sensor (I2C address 0x70, up to 1 MHz)
+
+use embassy_time::Timer;
+use embedded_hal_async::i2c::I2c;
+
+/// Sensor I2C address
+const ADDRESS: u8 = 0x70;
+
+/// Leave the sleep mode
+const CMD_WAKEUP: [u8; 2] = [0x35, 0x17];
+
+/// Enter the sleep mode
+const CMD_SLEEP: [u8; 2] = [0xB0, 0x98];
+
+/// Read the ID register
+const CMD_READ_ID: [u8; 2] = [0xEF, 0xC8];
+
+/// Normal mode measurement, temperature first, no clock stretching
+const CMD_MEASURE: [u8; 2] = [0x78, 0x66];
+
+/// Wake-up time, µs (datasheet max 240 µs)
+const WAKEUP_US: u64 = 300;
+
+/// Normal mode measurement duration, ms (datasheet max 12.1 ms)
+const MEASURE_MS: u64 = 13;
+
+/// SHTC3 errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Shtc3Error {
+    /// I2C transfer failed (no sensor on the bus?)
+    Bus,
+    /// Received word does not match its checksum
+    Crc,
+}
+
+/// Single SHTC3 measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Climate {
+    /// Temperature, 0.01 °C
+    pub temperature_centi: i16,
+    /// Relative humidity, 0.01 %
+    pub humidity_centi: u16,
+}
+
+/// SHTC3 driver
+pub struct Shtc3<I> {
+    i2c: I,
+}
+
+impl<I: I2c> Shtc3<I> {
+    /// Driver constructor
+    ///
+    /// * `i2c` - I2C bus with the sensor
+    pub fn new(i2c: I) -> Self {
+        Self { i2c }
+    }
+
+    /// Read the sensor ID (bits 11 and 5..0 of the ID register)
+    pub async fn id(&mut self) -> Result<u16, Shtc3Error> {
+        self.wakeup().await?;
+        let mut buffer = [0_u8; 3];
+        let result = self.read(&CMD_READ_ID, &mut buffer).await;
+        self.sleep().await;
+        let id = result?;
+        Ok(id & 0x083F)
+    }
+
+    /// Wake the sensor up, measure and put it back to sleep
+    pub async fn measure(&mut self) -> Result<Climate, Shtc3Error> {
+        self.wakeup().await?;
+        let result = self.measure_awake().await;
+        self.sleep().await;
+        result
+    }
+
+    /// Measure temperature and humidity, the sensor must be awake
+    async fn measure_awake(&mut self) -> Result<Climate, Shtc3Error> {
+        self.i2c
+            .write(ADDRESS, &CMD_MEASURE)
+            .await
+            .map_err(|_| Shtc3Error::Bus)?;
+        Timer::after_millis(MEASURE_MS).await;
+
+        let mut buffer = [0_u8; 6];
+        self.i2c
+            .read(ADDRESS, &mut buffer)
+            .await
+            .map_err(|_| Shtc3Error::Bus)?;
+        let [t_msb, t_lsb, t_crc, rh_msb, rh_lsb, rh_crc] = buffer;
+        let temperature = checked_word([t_msb, t_lsb], t_crc)?;
+        let humidity = checked_word([rh_msb, rh_lsb], rh_crc)?;
+
+        Ok(Climate {
+            temperature_centi: temperature_centi(temperature),
+            humidity_centi: humidity_centi(humidity),
+        })
+    }
+
+    /// Send a command and read one checked word
+    ///
+    /// * `command` - Command
+    /// * `buffer` - Word and its checksum
+    async fn read(&mut self, command: &[u8; 2], buffer: &mut [u8; 3]) -> Result<u16, Shtc3Error> {
+        self.i2c
+            .write_read(ADDRESS, command, buffer)
+            .await
+            .map_err(|_| Shtc3Error::Bus)?;
+        let [msb, lsb, crc] = *buffer;
+        checked_word([msb, lsb], crc)
+    }
+
+    /// Leave the sleep mode
+    async fn wakeup(&mut self) -> Result<(), Shtc3Error> {
+        self.i2c
+            .write(ADDRESS, &CMD_WAKEUP)
+            .await
+            .map_err(|_| Shtc3Error::Bus)?;
+        Timer::after_micros(WAKEUP_US).await;
+        Ok(())
+    }
+
+    /// Enter the sleep mode (~0.3 µA). A failure only costs power, the next wake-up recovers
+    async fn sleep(&mut self) {
+        let _ = self.i2c.write(ADDRESS, &CMD_SLEEP).await;
+    }
+}
+
+/// Check the CRC-8 (polynomial 0x31, init 0xFF) of a received word
+///
+/// * `word` - Big-endian word
+/// * `crc` - Received checksum
+fn checked_word(word: [u8; 2], crc: u8) -> Result<u16, Shtc3Error> {
+    let mut calculated = 0xFF_u8;
+    for byte in word {
+        calculated ^= byte;
+        for _ in 0..8 {
+            calculated = if calculated & 0x80 != 0 {
+                (calculated << 1) ^ 0x31
+            } else {
+                calculated << 1
+            };
+        }
+    }
+    if calculated == crc {
+        Ok(u16::from_be_bytes(word))
+    } else {
+        Err(Shtc3Error::Crc)
+    }
+}
+
+/// T = -45 + 175 * raw / 2^16 °C
+fn temperature_centi(raw: u16) -> i16 {
+    let centi = -4500 + ((17500 * i32::from(raw)) >> 16);
+    // -4500..=12999 always fits
+    i16::try_from(centi).unwrap_or(i16::MAX)
+}
+
+/// RH = 100 * raw / 2^16 %
+fn humidity_centi(raw: u16) -> u16 {
+    let centi = (10000 * u32::from(raw)) >> 16;
+    // 0..=9999 always fits
+    u16::try_from(centi).unwrap_or(u16::MAX)
+}
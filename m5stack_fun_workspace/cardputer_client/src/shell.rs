@@ -0,0 +1,333 @@
+//! Command shell - text commands from a terminal (BLE UART) and the log stream back
+//!
+//! Commands:
+//! * `help` - list of commands
+//! * `status` - BLE state, latest measurement and sensor settings
+//! * `config` - committed configuration
+//! * `set <key> <value>` - change and commit a configuration entry
+//! * `sampling on|off` - enable or pause the sensors
+//! * `scan [name=<prefix>] [uuid=<uuid>] [rssi=<dBm>]`, `devices`, `connect <n>`, `disconnect`,
+//!   `discover <service uuid>`, `read <uuid>`, `write <uuid> <hex>`, `subscribe <uuid>` - BLE
+//!   central, results are reported as log lines (see [`crate::ble::central`])
+//!
+//! Responses and log lines share [`SHELL_OUTPUT`]. Responses wait for free space (the terminal
+//! is slow - the shell waits), log lines are dropped instead and the number of dropped lines is
+//! reported once there is space again.
+
+use crate::ble::central::{self, CENTRAL_COMMANDS, CentralCommand, ScanFilter};
+use crate::ble::{BLE_STATE, BleState};
+use crate::config::{self, ConfigKey, ConfigRecord, ConfigTransaction, ConfigValue};
+use crate::sensors::{self, MEASUREMENT, SENSOR_SETTINGS, SensorSettings};
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU32, Ordering};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pipe::Pipe;
+use heapless::{String, Vec};
+
+/// Maximal command line length, bytes
+pub const LINE_MAX: usize = 128;
+
+/// Maximal response length, bytes
+pub const RESPONSE_MAX: usize = 512;
+
+/// Maximal log line length without the line ending, bytes
+const LOG_TEXT_MAX: usize = LINE_MAX - 2;
+
+/// Output buffer size, bytes
+const OUTPUT_LEN: usize = 1024;
+
+/// Command line
+pub type Line = String<LINE_MAX>;
+
+/// Command response
+pub type Response = String<RESPONSE_MAX>;
+
+/// Text for the terminal: command responses and log lines
+pub static SHELL_OUTPUT: Pipe<CriticalSectionRawMutex, OUTPUT_LEN> = Pipe::new();
+
+/// Log lines dropped since the last reported drop
+static DROPPED_LINES: AtomicU32 = AtomicU32::new(0);
+
+/// `help` response
+const HELP: &str = "\
+help                 - this list\r\n\
+status               - BLE state and latest measurement\r\n\
+config               - configuration\r\n\
+set <key> <value>    - change configuration: name, interval (ms), notify (on/off),\r\n\
+\x20                      offset (0.01 C), adv (ms), txpower (dBm)\r\n\
+sampling on|off      - enable or pause the sensors\r\n\
+scan [name=<prefix>] [uuid=<uuid>] [rssi=<dBm>] - scan for BLE devices\r\n\
+devices              - devices of the latest scan\r\n\
+connect <n>          - connect to a device of the list\r\n\
+disconnect           - disconnect from the device\r\n\
+discover <uuid>      - select a service of the device\r\n\
+read <uuid>          - read a characteristic\r\n\
+write <uuid> <hex>   - write a characteristic\r\n\
+subscribe <uuid>     - log notifications of a characteristic\r\n";
+
+/// Execute a command line
+///
+/// * `line` - Command line without the line ending
+/// * `out` - Response
+pub fn execute(line: &str, out: &mut impl Write) -> fmt::Result {
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next(), words.next(), words.next()) {
+        (None, ..) => Ok(()),
+        (Some("help"), None, ..) => out.write_str(HELP),
+        (Some("status"), None, ..) => status(out),
+        (Some("config"), None, ..) => show_config(out),
+        (Some("set"), Some(key), Some(value), None) => set(key, value, out),
+        (Some("sampling"), Some(state @ ("on" | "off")), None, _) => {
+            sensors::update_settings(SensorSettings {
+                sampling_enabled: state == "on",
+            });
+            write!(out, "sampling {state}\r\n")
+        }
+        (Some("scan"), ..) => match parse_filter(line.split_whitespace().skip(1)) {
+            Ok(filter) => request(CentralCommand::Scan(filter), out),
+            Err(e) => write!(out, "error: {e}\r\n"),
+        },
+        (Some("devices"), None, ..) => devices(out),
+        (Some("connect"), Some(index), None, _) => match index.parse() {
+            Ok(index) => request(CentralCommand::Connect(index), out),
+            Err(_) => out.write_str("error: not a number\r\n"),
+        },
+        (Some("disconnect"), None, ..) => request(CentralCommand::Disconnect, out),
+        (Some(command @ ("discover" | "read" | "subscribe")), Some(uuid), None, _) => {
+            let Some(uuid) = central::parse_uuid(uuid) else {
+                return out.write_str("error: bad UUID\r\n");
+            };
+            let command = match command {
+                "discover" => CentralCommand::Discover(uuid),
+                "read" => CentralCommand::Read(uuid),
+                _ => CentralCommand::Subscribe(uuid),
+            };
+            request(command, out)
+        }
+        (Some("write"), Some(uuid), Some(value), None) => {
+            let Some(uuid) = central::parse_uuid(uuid) else {
+                return out.write_str("error: bad UUID\r\n");
+            };
+            match parse_hex(value) {
+                Some(value) => request(CentralCommand::Write(uuid, value), out),
+                None => out.write_str("error: bad hex value\r\n"),
+            }
+        }
+        _ => out.write_str("unknown command, try `help`\r\n"),
+    }
+}
+
+/// Append a log line to the output. The line is dropped if the terminal does not keep up
+///
+/// * `args` - Line without the line ending, truncated to [`LINE_MAX`]
+pub fn log(args: fmt::Arguments) {
+    let dropped = DROPPED_LINES.load(Ordering::Relaxed);
+    if dropped > 0 {
+        let mut notice = Line::new();
+        let _ = write!(notice, "[{dropped} log lines dropped]\r\n");
+        if !try_write_line(&notice) {
+            DROPPED_LINES.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        DROPPED_LINES.fetch_sub(dropped, Ordering::Relaxed);
+    }
+
+    let mut text = String::<LOG_TEXT_MAX>::new();
+    // Ошибка означает только обрезанную строку
+    let _ = text.write_fmt(args);
+    let mut line = Line::new();
+    let _ = line.push_str(&text);
+    let _ = line.push_str("\r\n");
+    if !try_write_line(&line) {
+        DROPPED_LINES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Write a whole line or nothing
+///
+/// * `line` - Line with the line ending
+fn try_write_line(line: &str) -> bool {
+    SHELL_OUTPUT.free_capacity() >= line.len() && SHELL_OUTPUT.try_write(line.as_bytes()).is_ok()
+}
+
+/// `status` - BLE state, latest measurement and sensor settings
+fn status(out: &mut impl Write) -> fmt::Result {
+    match BLE_STATE.try_get() {
+        None => out.write_str("ble: starting\r\n")?,
+        Some(BleState::Advertising) => out.write_str("ble: advertising\r\n")?,
+        Some(BleState::Connected(connections)) => {
+            write!(out, "ble: connected ({connections})\r\n")?;
+        }
+        Some(BleState::Idle) => out.write_str("ble: idle\r\n")?,
+    }
+    let settings = SENSOR_SETTINGS.try_get().unwrap_or(SensorSettings::DEFAULT);
+    let sampling = if settings.sampling_enabled {
+        "on"
+    } else {
+        "off"
+    };
+    write!(out, "sampling: {sampling}\r\n")?;
+
+    let Some(measurement) = MEASUREMENT.try_get() else {
+        return out.write_str("no measurement yet\r\n");
+    };
+    match measurement.climate {
+        Some(climate) => write!(
+            out,
+            "temperature: {} C\r\nhumidity: {} %\r\n",
+            Centi(i32::from(climate.temperature_centi)),
+            Centi(i32::from(climate.humidity_centi))
+        )?,
+        None => out.write_str("temperature/humidity: not available\r\n")?,
+    }
+    match measurement.battery_mv {
+        Some(mv) => write!(out, "battery: {mv} mV\r\n"),
+        None => out.write_str("battery: not available\r\n"),
+    }
+}
+
+/// `config` - committed configuration
+fn show_config(out: &mut impl Write) -> fmt::Result {
+    let config = config::current();
+    write!(
+        out,
+        "generation: {}\r\nname: {}\r\ninterval: {} ms\r\nnotify: {}\r\noffset: {} C\r\nadv: {} ms\r\ntxpower: {} dBm\r\n",
+        config::generation(),
+        config.device_name,
+        config.sensor_interval_ms,
+        if config.notify_enabled { "on" } else { "off" },
+        Centi(config.temperature_offset_centi),
+        config.adv_interval_ms,
+        config.adv_tx_power_dbm
+    )
+}
+
+/// `set <key> <value>` - change and commit a configuration entry
+///
+/// * `key` - Short key name
+/// * `value` - Value text
+fn set(key: &str, value: &str, out: &mut impl Write) -> fmt::Result {
+    let record = match parse_record(key, value) {
+        Ok(record) => record,
+        Err(e) => return write!(out, "error: {e}\r\n"),
+    };
+
+    let mut transaction = ConfigTransaction::begin();
+    match transaction
+        .stage_record(&record)
+        .and_then(|()| transaction.commit())
+    {
+        Ok(generation) => write!(out, "ok, generation {generation}\r\n"),
+        Err(e) => write!(out, "error: {e:?}\r\n"),
+    }
+}
+
+/// Convert a `set` argument into a configuration record (ranges are checked on staging)
+///
+/// * `key` - Short key name, as in the host CLI
+/// * `value` - Value text
+fn parse_record(key: &str, value: &str) -> Result<ConfigRecord, &'static str> {
+    let number = || value.parse::<u32>().map_err(|_| "not a number");
+    let signed = || value.parse::<i32>().map_err(|_| "not a number");
+    let (key, value) = match key {
+        "name" => (
+            ConfigKey::DeviceName,
+            ConfigValue::Text(value.try_into().map_err(|_| "name is too long")?),
+        ),
+        "notify" => (
+            ConfigKey::NotifyEnabled,
+            ConfigValue::Bool(match value {
+                "on" | "1" => true,
+                "off" | "0" => false,
+                _ => return Err("expected on/off"),
+            }),
+        ),
+        "interval" => (ConfigKey::SensorIntervalMs, ConfigValue::U32(number()?)),
+        "offset" => (
+            ConfigKey::TemperatureOffsetCenti,
+            ConfigValue::I32(signed()?),
+        ),
+        "adv" => (ConfigKey::AdvIntervalMs, ConfigValue::U32(number()?)),
+        "txpower" => (ConfigKey::AdvTxPowerDbm, ConfigValue::I32(signed()?)),
+        _ => return Err("unknown key"),
+    };
+    Ok(ConfigRecord { key, value })
+}
+
+/// Pass a command to the BLE central, the result is reported later as a log line
+///
+/// * `command` - Central command
+fn request(command: CentralCommand, out: &mut impl Write) -> fmt::Result {
+    match CENTRAL_COMMANDS.try_send(command) {
+        Ok(()) => out.write_str("ok\r\n"),
+        Err(_) => out.write_str("error: central is busy\r\n"),
+    }
+}
+
+/// `devices` - devices of the latest scan
+fn devices(out: &mut impl Write) -> fmt::Result {
+    let mut result = Ok(());
+    let mut count = 0;
+    central::for_each_device(|index, device| {
+        count += 1;
+        if result.is_ok() {
+            result = write!(out, "{index}: {device}\r\n");
+        }
+    });
+    result?;
+    if count == 0 {
+        out.write_str("no devices, try `scan`\r\n")?;
+    }
+    Ok(())
+}
+
+/// Parse `scan` arguments: `name=<prefix>`, `uuid=<uuid>`, `rssi=<dBm>`
+///
+/// * `args` - Arguments
+fn parse_filter<'a>(args: impl Iterator<Item = &'a str>) -> Result<ScanFilter, &'static str> {
+    let mut filter = ScanFilter::default();
+    for arg in args {
+        match arg.split_once('=') {
+            Some(("name", name)) => {
+                let mut prefix = central::Name::new();
+                prefix.push_str(name).map_err(|_| "name too long")?;
+                filter.name = Some(prefix);
+            }
+            Some(("uuid", uuid)) => {
+                filter.service = Some(central::parse_uuid(uuid).ok_or("bad UUID")?);
+            }
+            Some(("rssi", rssi)) => {
+                filter.rssi_min = Some(rssi.parse().map_err(|_| "bad RSSI")?);
+            }
+            _ => return Err("unknown filter"),
+        }
+    }
+    Ok(filter)
+}
+
+/// Parse a hex value (`0a1b2c`)
+///
+/// * `text` - Hex text, two digits per byte
+fn parse_hex(text: &str) -> Option<Vec<u8, { central::VALUE_MAX }>> {
+    let mut value = Vec::new();
+    for pair in text.as_bytes().chunks(2) {
+        let [high, low] = *pair else {
+            return None;
+        };
+        let high = char::from(high).to_digit(16)?;
+        let low = char::from(low).to_digit(16)?;
+        value.push(u8::try_from(high << 4 | low).ok()?).ok()?;
+    }
+    Some(value)
+}
+
+/// Fixed point value with 2 decimals
+struct Centi(i32);
+
+impl fmt::Display for Centi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:02}", abs / 100, abs % 100)
+    }
+}
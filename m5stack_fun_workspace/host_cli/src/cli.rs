@@ -0,0 +1,343 @@
+//! Command line and the execution of its commands
+
+use crate::client::Client;
+use anyhow::{Context, anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use shared_lib::{ConfigKey, ConfigRecord, ConfigValue, Request, Response};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Protocol version with info, log, SD card and screenshot requests
+const FILES_VERSION: u16 = 2;
+
+/// Log polling period of `log tail --follow`, ms
+const LOG_POLL_MS: u64 = 500;
+
+/// Talk to the Cardputer firmware over a serial port
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Serial port, e.g. /dev/ttyACM0
+    #[arg(short, long)]
+    pub port: String,
+
+    /// Baud rate
+    #[arg(short, long, default_value_t = 115_200)]
+    pub baud: u32,
+
+    /// Response timeout, ms
+    #[arg(long, default_value_t = 2_000)]
+    pub timeout_ms: u64,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Device commands
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Device name, firmware version and uptime
+    Info,
+    /// Read or change the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Device log
+    #[command(subcommand)]
+    Log(LogCommand),
+    /// SD card files
+    #[command(subcommand)]
+    Sd(SdCommand),
+    /// Save the screen contents as a PPM image
+    Screenshot {
+        /// Output file
+        #[arg(short, long, default_value = "screenshot.ppm")]
+        output: PathBuf,
+    },
+}
+
+/// Configuration commands
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print an entry
+    Get {
+        /// Entry
+        key: Key,
+    },
+    /// Change and commit an entry
+    Set {
+        /// Entry
+        key: Key,
+        /// New value: text, number or on/off
+        value: String,
+    },
+}
+
+/// Log commands
+#[derive(Debug, Subcommand)]
+pub enum LogCommand {
+    /// Print the buffered log lines
+    Tail {
+        /// Keep printing new lines until interrupted
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+/// SD card commands
+#[derive(Debug, Subcommand)]
+pub enum SdCommand {
+    /// List a directory
+    Ls {
+        /// Directory
+        #[arg(default_value = "/")]
+        path: String,
+    },
+    /// Copy a file to the host
+    Get {
+        /// File on the SD card
+        path: String,
+        /// Output file, the name of the SD card file by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Configuration entries, named as in the device shell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Key {
+    /// Advertised device name
+    Name,
+    /// Sensor interval, ms
+    Interval,
+    /// Sensor notifications, on/off
+    Notify,
+    /// Temperature offset, 0.01 °C
+    Offset,
+    /// Advertising interval, ms
+    Adv,
+    /// Advertising transmit power, dBm
+    Txpower,
+}
+
+impl Key {
+    /// Protocol key
+    fn config_key(self) -> ConfigKey {
+        match self {
+            Self::Name => ConfigKey::DeviceName,
+            Self::Interval => ConfigKey::SensorIntervalMs,
+            Self::Notify => ConfigKey::NotifyEnabled,
+            Self::Offset => ConfigKey::TemperatureOffsetCenti,
+            Self::Adv => ConfigKey::AdvIntervalMs,
+            Self::Txpower => ConfigKey::AdvTxPowerDbm,
+        }
+    }
+
+    /// Convert a command line value into the protocol value
+    ///
+    /// * `value` - Value text
+    fn parse(self, value: &str) -> anyhow::Result<ConfigValue> {
+        Ok(match self {
+            Self::Name => ConfigValue::Text(
+                value
+                    .try_into()
+                    .map_err(|_| anyhow!("name is longer than {}", shared_lib::TEXT_MAX))?,
+            ),
+            Self::Notify => ConfigValue::Bool(match value {
+                "on" | "1" => true,
+                "off" | "0" => false,
+                _ => bail!("expected on/off"),
+            }),
+            Self::Interval | Self::Adv => ConfigValue::U32(value.parse().context("not a number")?),
+            Self::Offset | Self::Txpower => ConfigValue::I32(value.parse().context("not a number")?),
+        })
+    }
+}
+
+/// Execute a command
+///
+/// * `command` - Command
+/// * `client` - Connected device
+/// * `out` - Output of the command
+pub fn run<T: Read + Write>(
+    command: &Command,
+    client: &mut Client<T>,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    match command {
+        Command::Info => info(client, out),
+        Command::Config(ConfigCommand::Get { key }) => {
+            match client.request(Request::GetConfig(key.config_key()))? {
+                Response::Config(record) => print_value(&record.value, out),
+                response => bail!("unexpected response: {response:?}"),
+            }
+        }
+        Command::Config(ConfigCommand::Set { key, value }) => {
+            let record = ConfigRecord {
+                key: key.config_key(),
+                value: key.parse(value)?,
+            };
+            match client.request(Request::SetConfig(record))? {
+                Response::Ack => Ok(writeln!(out, "ok")?),
+                response => bail!("unexpected response: {response:?}"),
+            }
+        }
+        Command::Log(LogCommand::Tail { follow }) => log_tail(client, *follow, out),
+        Command::Sd(SdCommand::Ls { path }) => sd_ls(client, path, out),
+        Command::Sd(SdCommand::Get { path, output }) => {
+            let output = match output {
+                Some(output) => output.clone(),
+                None => Path::new(path)
+                    .file_name()
+                    .map(PathBuf::from)
+                    .context("no file name in the path")?,
+            };
+            let len = sd_get(client, path, &output)?;
+            Ok(writeln!(out, "{len} bytes -> {}", output.display())?)
+        }
+        Command::Screenshot { output } => {
+            let (width, height) = screenshot(client, output)?;
+            Ok(writeln!(out, "{width}x{height} -> {}", output.display())?)
+        }
+    }
+}
+
+/// Print the device identification
+fn info<T: Read + Write>(client: &mut Client<T>, out: &mut impl Write) -> anyhow::Result<()> {
+    client.require(FILES_VERSION)?;
+    match client.request(Request::GetInfo)? {
+        Response::Info(info) => {
+            writeln!(out, "name: {}", info.name)?;
+            writeln!(out, "firmware: {}", info.firmware)?;
+            writeln!(out, "uptime: {} s", info.uptime_s)?;
+            writeln!(out, "protocol: {}", client.version())?;
+            Ok(())
+        }
+        response => bail!("unexpected response: {response:?}"),
+    }
+}
+
+/// Print a configuration value in the form accepted by `config set`
+fn print_value(value: &ConfigValue, out: &mut impl Write) -> anyhow::Result<()> {
+    match value {
+        ConfigValue::Bool(true) => writeln!(out, "on")?,
+        ConfigValue::Bool(false) => writeln!(out, "off")?,
+        ConfigValue::U32(value) => writeln!(out, "{value}")?,
+        ConfigValue::I32(value) => writeln!(out, "{value}")?,
+        ConfigValue::Text(value) => writeln!(out, "{value}")?,
+    }
+    Ok(())
+}
+
+/// Print the buffered log lines, with `follow` keep polling for new ones
+fn log_tail<T: Read + Write>(
+    client: &mut Client<T>,
+    follow: bool,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    client.require(FILES_VERSION)?;
+    loop {
+        match client.request(Request::ReadLog)? {
+            Response::Log(lines) if lines.is_empty() => {
+                if !follow {
+                    return Ok(());
+                }
+                out.flush()?;
+                thread::sleep(Duration::from_millis(LOG_POLL_MS));
+            }
+            Response::Log(lines) => write!(out, "{lines}")?,
+            response => bail!("unexpected response: {response:?}"),
+        }
+    }
+}
+
+/// Print the entries of an SD card directory
+fn sd_ls<T: Read + Write>(
+    client: &mut Client<T>,
+    path: &str,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    client.require(FILES_VERSION)?;
+    for index in 0.. {
+        let request = Request::ListDir {
+            path: device_path(path)?,
+            index,
+        };
+        match client.request(request)? {
+            Response::DirEntry(Some(entry)) if entry.is_dir => {
+                writeln!(out, "{:>10}  {}/", "<dir>", entry.name)?
+            }
+            Response::DirEntry(Some(entry)) => writeln!(out, "{:>10}  {}", entry.size, entry.name)?,
+            Response::DirEntry(None) => return Ok(()),
+            response => bail!("unexpected response: {response:?}"),
+        }
+    }
+    bail!("too many directory entries")
+}
+
+/// Copy an SD card file to the host. Returns the file length
+fn sd_get<T: Read + Write>(
+    client: &mut Client<T>,
+    path: &str,
+    output: &Path,
+) -> anyhow::Result<u32> {
+    client.require(FILES_VERSION)?;
+    // Файл создаётся только после успешного чтения
+    let mut content = Vec::new();
+    loop {
+        let request = Request::ReadFile {
+            path: device_path(path)?,
+            offset: u32::try_from(content.len())?,
+        };
+        match client.request(request)? {
+            Response::Data(data) if data.is_empty() => break,
+            Response::Data(data) => content.extend_from_slice(&data),
+            response => bail!("unexpected response: {response:?}"),
+        }
+    }
+    fs::write(output, &content).with_context(|| format!("{}", output.display()))?;
+    Ok(u32::try_from(content.len())?)
+}
+
+/// Convert an SD card path for a request
+fn device_path<S: for<'a> TryFrom<&'a str>>(path: &str) -> anyhow::Result<S> {
+    S::try_from(path).map_err(|_| anyhow!("path is longer than {}", shared_lib::PATH_MAX))
+}
+
+/// Save the screen contents as a binary PPM image. Returns the screen size
+fn screenshot<T: Read + Write>(
+    client: &mut Client<T>,
+    output: &Path,
+) -> anyhow::Result<(u16, u16)> {
+    client.require(FILES_VERSION)?;
+    let mut pixels = Vec::new();
+    let (width, height) = loop {
+        let request = Request::Screenshot {
+            offset: u32::try_from(pixels.len())?,
+        };
+        match client.request(request)? {
+            Response::Screen(chunk) if chunk.data.is_empty() => break (chunk.width, chunk.height),
+            Response::Screen(chunk) => pixels.extend_from_slice(&chunk.data),
+            response => bail!("unexpected response: {response:?}"),
+        }
+    };
+    if pixels.len() != usize::from(width) * usize::from(height) * 2 {
+        bail!("{} bytes of pixels for {width}x{height}", pixels.len());
+    }
+
+    let file = File::create(output).with_context(|| format!("{}", output.display()))?;
+    let mut file = BufWriter::new(file);
+    write!(file, "P6\n{width} {height}\n255\n")?;
+    for pixel in pixels.as_chunks::<2>().0 {
+        let rgb565 = u16::from_be_bytes(*pixel);
+        // 5/6/5 бит расширяются до 8 с повтором старших бит
+        let r = ((rgb565 >> 11) & 0x1F) as u8;
+        let g = ((rgb565 >> 5) & 0x3F) as u8;
+        let b = (rgb565 & 0x1F) as u8;
+        file.write_all(&[r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2])?;
+    }
+    file.flush()?;
+    Ok((width, height))
+}
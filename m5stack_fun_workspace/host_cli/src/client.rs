@@ -0,0 +1,178 @@
+//! Request/response exchange with a device over a byte stream
+
+use shared_lib::{
+    CodecError, ErrorCode, FRAME_MAX, Frame, Hello, Request, Response, decode_cobs, encode_cobs,
+};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// Client errors
+#[derive(Debug)]
+pub enum ClientError {
+    /// Port read or write failed (a timeout included)
+    Io(io::Error),
+    /// Frame could not be encoded or decoded
+    Codec(CodecError),
+    /// Device does not support a common protocol version, device versions attached
+    Version(Hello),
+    /// Request needs a newer protocol version than negotiated
+    Unsupported {
+        /// Version the request needs
+        needed: u16,
+        /// Negotiated version
+        version: u16,
+    },
+    /// Device rejected the request
+    Device(ErrorCode),
+    /// Response does not match the request
+    Unexpected(Box<Response>),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "port error: {e}"),
+            Self::Codec(e) => write!(f, "bad frame: {e:?}"),
+            Self::Version(peer) => write!(
+                f,
+                "device speaks protocol {}..={}, this tool {}..={}",
+                peer.min_version,
+                peer.version,
+                Hello::LOCAL.min_version,
+                Hello::LOCAL.version
+            ),
+            Self::Unsupported { needed, version } => write!(
+                f,
+                "request needs protocol {needed}, the device speaks {version}"
+            ),
+            Self::Device(code) => write!(f, "device error: {code:?}"),
+            Self::Unexpected(response) => write!(f, "unexpected response: {response:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<CodecError> for ClientError {
+    fn from(e: CodecError) -> Self {
+        Self::Codec(e)
+    }
+}
+
+/// Connection to a device
+pub struct Client<T> {
+    /// Serial port or any other byte stream
+    port: T,
+    /// Identifier of the next request
+    next_id: u16,
+    /// Negotiated protocol version
+    version: u16,
+    /// Received bytes of an incomplete frame
+    received: Vec<u8>,
+}
+
+impl<T: Read + Write> Client<T> {
+    /// Negotiate the protocol version with the device
+    ///
+    /// * `port` - Byte stream connected to the device
+    pub fn connect(port: T) -> Result<Self, ClientError> {
+        let mut client = Self {
+            port,
+            next_id: 0,
+            version: Hello::LOCAL.min_version,
+            received: Vec::with_capacity(FRAME_MAX),
+        };
+        match client.request(Request::Hello(Hello::LOCAL))? {
+            Response::Hello(peer) => {
+                client.version = Hello::LOCAL
+                    .negotiate(&peer)
+                    .ok_or(ClientError::Version(peer))?;
+                Ok(client)
+            }
+            response => Err(ClientError::Unexpected(Box::new(response))),
+        }
+    }
+
+    /// Negotiated protocol version
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Fail unless the negotiated version has the request
+    ///
+    /// * `needed` - Protocol version the request appeared in
+    pub fn require(&self, needed: u16) -> Result<(), ClientError> {
+        if self.version < needed {
+            return Err(ClientError::Unsupported {
+                needed,
+                version: self.version,
+            });
+        }
+        Ok(())
+    }
+
+    /// Send a request and wait for its response. [`Response::Error`] becomes
+    /// [`ClientError::Device`]
+    ///
+    /// * `message` - Request
+    pub fn request(&mut self, message: Request) -> Result<Response, ClientError> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let mut buffer = [0_u8; FRAME_MAX];
+        let bytes = encode_cobs(&Frame { id, message }, &mut buffer)?;
+        self.port.write_all(bytes)?;
+        self.port.flush()?;
+
+        loop {
+            let mut frame = self.receive()?;
+            match decode_cobs::<Frame<Response>>(&mut frame)? {
+                // Ответ на запрос, на который уже не ждали (таймаут)
+                Frame { id: other, .. } if other != id => continue,
+                Frame {
+                    message: Response::Error(code),
+                    ..
+                } => return Err(ClientError::Device(code)),
+                Frame { message, .. } => return Ok(message),
+            }
+        }
+    }
+
+    /// Read the next frame up to and without the zero delimiter
+    fn receive(&mut self) -> Result<Vec<u8>, ClientError> {
+        loop {
+            if let Some(end) = self.received.iter().position(|&b| b == 0) {
+                let frame: Vec<u8> = self.received.drain(..=end).take(end).collect();
+                if frame.is_empty() {
+                    continue;
+                }
+                return Ok(frame);
+            }
+            if self.received.len() > FRAME_MAX {
+                // Мусор без разделителя - кадр потерян
+                self.received.clear();
+                return Err(ClientError::Codec(CodecError::Malformed));
+            }
+
+            let mut chunk = [0_u8; FRAME_MAX];
+            let len = self.port.read(&mut chunk)?;
+            if len == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            self.received.extend_from_slice(&chunk[..len]);
+        }
+    }
+}
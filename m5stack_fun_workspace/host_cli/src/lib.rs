@@ -0,0 +1,12 @@
+//! Host tool talking to the Cardputer firmware over a serial port
+//!
+//! Requests and responses are the [`shared_lib`] protocol, COBS-framed on the port. The
+//! connection starts with the version negotiation ([`Client::connect`]), commands that need
+//! a newer protocol than the device speaks fail without sending anything.
+
+mod cli;
+mod client;
+
+// Public re-export of specifics that are available outside of module
+pub use cli::{Cli, Command, ConfigCommand, Key, LogCommand, SdCommand, run};
+pub use client::{Client, ClientError};
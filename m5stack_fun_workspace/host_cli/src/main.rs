@@ -0,0 +1,15 @@
+use anyhow::Context;
+use clap::Parser;
+use host_cli::{Cli, Client, run};
+use std::io;
+use std::time::Duration;
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let port = serialport::new(&cli.port, cli.baud)
+        .timeout(Duration::from_millis(cli.timeout_ms))
+        .open()
+        .with_context(|| format!("failed to open {}", cli.port))?;
+    let mut client = Client::connect(port).context("no answer from the device")?;
+    run(&cli.command, &mut client, &mut io::stdout().lock())
+}
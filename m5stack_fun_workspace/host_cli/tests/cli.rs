@@ -0,0 +1,186 @@
+//! Commands of the tool against an in-process device speaking the same protocol
+
+mod fake_device;
+
+use clap::Parser;
+use fake_device::FakeDevice;
+use host_cli::{Cli, Client, ClientError, run};
+use shared_lib::{ConfigKey, ConfigValue, ErrorCode, Hello};
+use std::fs;
+
+/// Run the tool with the arguments after `--port`, returns the output and the device state
+fn cli(device: FakeDevice, args: &[&str]) -> (anyhow::Result<String>, FakeDevice) {
+    let cli =
+        Cli::try_parse_from(["cardputer", "--port", "fake"].iter().chain(args)).expect("arguments");
+    let (port, handle) = device.spawn();
+    let result = Client::connect(port)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut client| {
+            let mut out = Vec::new();
+            run(&cli.command, &mut client, &mut out)?;
+            Ok(String::from_utf8(out)?)
+        });
+    // Клиент закрыл свой конец канала - поток устройства завершается
+    let device = handle.join().expect("device thread");
+    (result, device)
+}
+
+#[test]
+fn info_prints_the_identification() {
+    let (output, _) = cli(FakeDevice::default(), &["info"]);
+    assert_eq!(
+        output.unwrap(),
+        "name: cardputer\nfirmware: 0.1.0\nuptime: 42 s\nprotocol: 2\n"
+    );
+}
+
+#[test]
+fn config_get_and_set() {
+    let (output, device) = cli(FakeDevice::default(), &["config", "get", "offset"]);
+    assert_eq!(output.unwrap(), "-150\n");
+
+    let (output, device) = cli(device, &["config", "set", "name", "kitchen"]);
+    assert_eq!(output.unwrap(), "ok\n");
+    assert!(
+        device
+            .config
+            .iter()
+            .any(|record| record.key == ConfigKey::DeviceName
+                && record.value == ConfigValue::Text("kitchen".try_into().unwrap()))
+    );
+
+    let (output, device) = cli(device, &["config", "set", "notify", "off"]);
+    assert_eq!(output.unwrap(), "ok\n");
+    let (output, _) = cli(device, &["config", "get", "notify"]);
+    assert_eq!(output.unwrap(), "off\n");
+}
+
+#[test]
+fn config_errors_are_reported() {
+    let (output, device) = cli(FakeDevice::default(), &["config", "set", "notify", "maybe"]);
+    assert!(output.unwrap_err().to_string().contains("on/off"));
+
+    // Нет такой записи на устройстве
+    let (output, _) = cli(device, &["config", "get", "adv"]);
+    let error = output.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<ClientError>(),
+        Some(ClientError::Device(ErrorCode::InvalidValue))
+    ));
+}
+
+#[test]
+fn log_tail_prints_buffered_lines() {
+    let (output, device) = cli(FakeDevice::default(), &["log", "tail"]);
+    assert_eq!(output.unwrap(), "[ble] advertising\n[ble] connected\n");
+    assert!(device.log.is_empty());
+}
+
+#[test]
+fn sd_ls_lists_files_and_directories() {
+    let (output, device) = cli(FakeDevice::default(), &["sd", "ls"]);
+    assert_eq!(
+        output.unwrap(),
+        "         6  notes.txt\n     <dir>  logs/\n"
+    );
+    let (output, _) = cli(device, &["sd", "ls", "/logs"]);
+    assert_eq!(output.unwrap(), "       700  boot.txt\n     <dir>  old/\n");
+}
+
+#[test]
+fn sd_get_copies_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("boot.txt");
+    let device = FakeDevice::default();
+    let expected = device.files[1].1.clone();
+
+    let (result, device) = cli(
+        device,
+        &[
+            "sd",
+            "get",
+            "/logs/boot.txt",
+            "-o",
+            output.to_str().unwrap(),
+        ],
+    );
+    assert!(result.unwrap().starts_with("700 bytes"));
+    assert_eq!(fs::read(&output).unwrap(), expected);
+
+    let missing = dir.path().join("missing.txt");
+    let (result, _) = cli(
+        device,
+        &["sd", "get", "/missing.txt", "-o", missing.to_str().unwrap()],
+    );
+    let error = result.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<ClientError>(),
+        Some(ClientError::Device(ErrorCode::NotFound))
+    ));
+    assert!(!missing.exists());
+}
+
+#[test]
+fn screenshot_is_saved_as_ppm() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("screen.ppm");
+    let (result, _) = cli(
+        FakeDevice::default(),
+        &["screenshot", "-o", output.to_str().unwrap()],
+    );
+    assert!(result.unwrap().starts_with("2x2"));
+
+    let mut expected = b"P6\n2 2\n255\n".to_vec();
+    expected.extend_from_slice(&[255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]);
+    assert_eq!(fs::read(&output).unwrap(), expected);
+}
+
+#[test]
+fn version_1_device_gets_only_version_1_requests() {
+    let device = FakeDevice {
+        hello: Hello {
+            version: 1,
+            min_version: 1,
+        },
+        ..FakeDevice::default()
+    };
+    let (output, device) = cli(device, &["config", "get", "offset"]);
+    assert_eq!(output.unwrap(), "-150\n");
+
+    let (output, _) = cli(device, &["info"]);
+    let error = output.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<ClientError>(),
+        Some(ClientError::Unsupported {
+            needed: 2,
+            version: 1
+        })
+    ));
+}
+
+#[test]
+fn newer_device_without_common_version_is_rejected() {
+    let device = FakeDevice {
+        hello: Hello {
+            version: 9,
+            min_version: 8,
+        },
+        ..FakeDevice::default()
+    };
+    let (output, _) = cli(device, &["info"]);
+    let error = output.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<ClientError>(),
+        Some(ClientError::Version(_))
+    ));
+}
+
+#[test]
+fn stale_and_empty_frames_are_skipped() {
+    let device = FakeDevice {
+        noise: true,
+        ..FakeDevice::default()
+    };
+    let (output, _) = cli(device, &["log", "tail"]);
+    assert_eq!(output.unwrap(), "[ble] advertising\n[ble] connected\n");
+}
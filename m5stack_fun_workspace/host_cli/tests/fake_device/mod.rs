@@ -0,0 +1,234 @@
+//! In-process device: answers the protocol requests from fixed data over an in-memory pipe
+
+use shared_lib::{
+    ConfigKey, ConfigRecord, ConfigValue, DATA_MAX, DeviceInfo, DirEntry, ErrorCode, FRAME_MAX,
+    Frame, Hello, Request, Response, ScreenChunk, decode_cobs, encode_cobs,
+};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// One end of a bidirectional byte pipe, reading returns 0 once the other end is dropped
+pub struct Pipe {
+    tx: Sender<Vec<u8>>,
+    rx: Receiver<Vec<u8>>,
+    pending: VecDeque<u8>,
+}
+
+/// Connected pipe ends
+pub fn pipe() -> (Pipe, Pipe) {
+    let (a_tx, a_rx) = mpsc::channel();
+    let (b_tx, b_rx) = mpsc::channel();
+    let a = Pipe {
+        tx: a_tx,
+        rx: b_rx,
+        pending: VecDeque::new(),
+    };
+    let b = Pipe {
+        tx: b_tx,
+        rx: a_rx,
+        pending: VecDeque::new(),
+    };
+    (a, b)
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.rx.recv() {
+                Ok(bytes) => self.pending.extend(bytes),
+                Err(_) => return Ok(0),
+            }
+        }
+        self.pending.read(buf)
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Device state
+pub struct FakeDevice {
+    /// Supported protocol versions
+    pub hello: Hello,
+    /// Configuration entries
+    pub config: Vec<ConfigRecord>,
+    /// Log lines not read yet
+    pub log: VecDeque<String>,
+    /// SD card files, full paths
+    pub files: Vec<(String, Vec<u8>)>,
+    /// Screen width, height and RGB565 pixels
+    pub screen: (u16, u16, Vec<u8>),
+    /// Send an empty frame and a frame with a wrong identifier before every response
+    pub noise: bool,
+}
+
+impl Default for FakeDevice {
+    fn default() -> Self {
+        Self {
+            hello: Hello::LOCAL,
+            config: vec![
+                ConfigRecord {
+                    key: ConfigKey::DeviceName,
+                    value: ConfigValue::Text("cardputer".try_into().unwrap()),
+                },
+                ConfigRecord {
+                    key: ConfigKey::NotifyEnabled,
+                    value: ConfigValue::Bool(true),
+                },
+                ConfigRecord {
+                    key: ConfigKey::TemperatureOffsetCenti,
+                    value: ConfigValue::I32(-150),
+                },
+            ],
+            log: ["[ble] advertising\n", "[ble] connected\n"]
+                .map(String::from)
+                .into(),
+            files: vec![
+                ("/notes.txt".into(), b"hello\n".to_vec()),
+                // Несколько ответов на файл
+                (
+                    "/logs/boot.txt".into(),
+                    (0..=255).cycle().take(700).collect(),
+                ),
+                ("/logs/old/1.txt".into(), Vec::new()),
+            ],
+            // Красный, зелёный / синий, белый
+            screen: (2, 2, vec![0xF8, 0x00, 0x07, 0xE0, 0x00, 0x1F, 0xFF, 0xFF]),
+            noise: false,
+        }
+    }
+}
+
+impl FakeDevice {
+    /// Serve requests on a thread until the host end of the pipe is dropped.
+    /// Returns the host end and the thread returning the final device state
+    pub fn spawn(mut self) -> (Pipe, JoinHandle<Self>) {
+        let (host, mut port) = pipe();
+        let handle = thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut chunk = [0_u8; FRAME_MAX];
+            loop {
+                let len = port.read(&mut chunk).expect("read");
+                if len == 0 {
+                    return self;
+                }
+                received.extend_from_slice(&chunk[..len]);
+                while let Some(end) = received.iter().position(|&b| b == 0) {
+                    let mut frame: Vec<u8> = received.drain(..=end).collect();
+                    let request: Frame<Request> = decode_cobs(&mut frame).expect("request");
+                    let response = self.respond(request.message);
+                    if self.noise {
+                        port.write_all(&[0]).expect("write");
+                        send(&mut port, request.id.wrapping_sub(1), Response::Ack);
+                    }
+                    send(&mut port, request.id, response);
+                }
+            }
+        });
+        (host, handle)
+    }
+
+    /// Response to a request
+    fn respond(&mut self, request: Request) -> Response {
+        match request {
+            Request::Hello(_) => Response::Hello(self.hello),
+            Request::GetConfig(key) => self
+                .config
+                .iter()
+                .find(|record| record.key == key)
+                .map_or(Response::Error(ErrorCode::InvalidValue), |record| {
+                    Response::Config(record.clone())
+                }),
+            Request::SetConfig(record) => {
+                self.config.retain(|other| other.key != record.key);
+                self.config.push(record);
+                Response::Ack
+            }
+            Request::GetInfo => Response::Info(DeviceInfo {
+                name: "cardputer".try_into().unwrap(),
+                firmware: "0.1.0".try_into().unwrap(),
+                uptime_s: 42,
+            }),
+            Request::ReadLog => {
+                let line = self.log.pop_front().unwrap_or_default();
+                Response::Log(line.as_str().try_into().unwrap())
+            }
+            Request::ListDir { path, index } => self.list(&path, index),
+            Request::ReadFile { path, offset } => {
+                let Some((_, content)) = self
+                    .files
+                    .iter()
+                    .find(|(name, _)| name.as_str() == path.as_str())
+                else {
+                    return Response::Error(ErrorCode::NotFound);
+                };
+                Response::Data(part(content, offset))
+            }
+            Request::Screenshot { offset } => {
+                let (width, height, pixels) = &self.screen;
+                Response::Screen(ScreenChunk {
+                    width: *width,
+                    height: *height,
+                    data: part(pixels, offset),
+                })
+            }
+            _ => Response::Error(ErrorCode::UnknownRequest),
+        }
+    }
+
+    /// Directory entry with the index
+    fn list(&self, path: &str, index: u16) -> Response {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let mut entries: Vec<(&str, u32, bool)> = Vec::new();
+        for (name, content) in &self.files {
+            let Some(rest) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let entry = match rest.split_once('/') {
+                Some((dir, _)) => (dir, 0, true),
+                None => (rest, content.len() as u32, false),
+            };
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+        if entries.is_empty() {
+            return Response::Error(ErrorCode::NotFound);
+        }
+        Response::DirEntry(
+            entries
+                .get(usize::from(index))
+                .map(|&(name, size, is_dir)| DirEntry {
+                    name: name.try_into().unwrap(),
+                    size,
+                    is_dir,
+                }),
+        )
+    }
+}
+
+/// Up to [`DATA_MAX`] bytes from the offset
+fn part<V: for<'a> TryFrom<&'a [u8]>>(bytes: &[u8], offset: u32) -> V {
+    let start = (offset as usize).min(bytes.len());
+    let end = (start + DATA_MAX).min(bytes.len());
+    V::try_from(&bytes[start..end]).unwrap_or_else(|_| unreachable!())
+}
+
+/// Send a response frame
+fn send(port: &mut Pipe, id: u16, message: Response) {
+    let mut buffer = [0_u8; FRAME_MAX];
+    let bytes = encode_cobs(&Frame { id, message }, &mut buffer).expect("encode");
+    port.write_all(bytes).expect("write");
+}
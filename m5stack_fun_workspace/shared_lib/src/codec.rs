@@ -0,0 +1,63 @@
+//! Binary encoding of the messages
+
+use serde::{Deserialize, Serialize};
+
+/// Maximal encoded message length, bytes (COBS encoding and the delimiter included)
+pub const FRAME_MAX: usize = 192;
+
+/// Encoding errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CodecError {
+    /// Buffer is too small for the message
+    BufferFull,
+    /// Bytes are not a valid message of the type (truncated, unknown variant, bad text)
+    Malformed,
+}
+
+/// Encode a message. Returns the used part of the buffer
+///
+/// * `value` - Message
+/// * `buffer` - Output buffer, [`FRAME_MAX`] bytes fit every message
+pub fn encode<'a, T: Serialize>(
+    value: &T,
+    buffer: &'a mut [u8],
+) -> Result<&'a mut [u8], CodecError> {
+    postcard::to_slice(value, buffer).map_err(|e| match e {
+        postcard::Error::SerializeBufferFull => CodecError::BufferFull,
+        _ => CodecError::Malformed,
+    })
+}
+
+/// Decode a message, trailing bytes are not allowed
+///
+/// * `bytes` - Encoded message
+pub fn decode<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, CodecError> {
+    match postcard::take_from_bytes(bytes) {
+        Ok((value, [])) => Ok(value),
+        _ => Err(CodecError::Malformed),
+    }
+}
+
+/// Encode a message for a byte stream: COBS, terminated by a zero byte.
+/// Returns the used part of the buffer
+///
+/// * `value` - Message
+/// * `buffer` - Output buffer, [`FRAME_MAX`] bytes fit every message
+pub fn encode_cobs<'a, T: Serialize>(
+    value: &T,
+    buffer: &'a mut [u8],
+) -> Result<&'a mut [u8], CodecError> {
+    postcard::to_slice_cobs(value, buffer).map_err(|e| match e {
+        postcard::Error::SerializeBufferFull => CodecError::BufferFull,
+        _ => CodecError::Malformed,
+    })
+}
+
+/// Decode a message received from a byte stream, the buffer is decoded in place
+///
+/// * `frame` - COBS-encoded message with or without the zero delimiter
+pub fn decode_cobs<'a, T: Deserialize<'a>>(frame: &'a mut [u8]) -> Result<T, CodecError> {
+    let len = cobs::decode_in_place(frame).map_err(|_| CodecError::Malformed)?;
+    decode(frame.get(..len).ok_or(CodecError::Malformed)?)
+}
@@ -0,0 +1,49 @@
+//! Configuration records
+
+use heapless::String;
+use serde::{Deserialize, Serialize};
+
+/// Maximal text value length, bytes
+pub const TEXT_MAX: usize = 32;
+
+/// Configuration entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigKey {
+    /// Advertised device name, text
+    DeviceName,
+    /// Sensor interval, ms
+    SensorIntervalMs,
+    /// Sensor notifications enabled
+    NotifyEnabled,
+    /// Temperature offset, 0.01 °C
+    TemperatureOffsetCenti,
+    /// Advertising interval, ms
+    AdvIntervalMs,
+    /// Advertising transmit power, dBm
+    AdvTxPowerDbm,
+}
+
+/// Configuration value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigValue {
+    /// Flag
+    Bool(bool),
+    /// Unsigned number
+    U32(u32),
+    /// Signed number
+    I32(i32),
+    /// UTF-8 text
+    Text(String<TEXT_MAX>),
+}
+
+/// Configuration entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConfigRecord {
+    /// Entry key
+    pub key: ConfigKey,
+    /// Entry value
+    pub value: ConfigValue,
+}
@@ -0,0 +1,49 @@
+//! Device information, SD card files and screenshots
+
+use heapless::{String, Vec};
+use serde::{Deserialize, Serialize};
+
+/// Maximal path length, bytes
+pub const PATH_MAX: usize = 64;
+
+/// Maximal file or device name length, bytes
+pub const NAME_MAX: usize = 32;
+
+/// Maximal data length per response, bytes
+pub const DATA_MAX: usize = 128;
+
+/// Device identification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceInfo {
+    /// Device name
+    pub name: String<NAME_MAX>,
+    /// Firmware version
+    pub firmware: String<NAME_MAX>,
+    /// Time since reset, s
+    pub uptime_s: u32,
+}
+
+/// SD card directory entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DirEntry {
+    /// Entry name
+    pub name: String<NAME_MAX>,
+    /// File size, bytes (0 for directories)
+    pub size: u32,
+    /// Entry is a directory
+    pub is_dir: bool,
+}
+
+/// Part of the screen contents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ScreenChunk {
+    /// Screen width, pixels
+    pub width: u16,
+    /// Screen height, pixels
+    pub height: u16,
+    /// RGB565 pixels (big-endian, row by row) from the requested offset, empty - end of data
+    pub data: Vec<u8, DATA_MAX>,
+}
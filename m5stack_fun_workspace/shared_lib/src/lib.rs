@@ -1,5 +1,31 @@
+//! Wire protocol shared between the firmware and the host tools
+//!
+//! Messages are `serde` types encoded with `postcard` (see [`encode`], [`decode`]): varint
+//! integers, enums as a variant index, no field names. The encoding is stable as long as the
+//! types follow the compatibility rules:
+//! * fields are never reordered, removed or retyped
+//! * enum variants are only appended, never reordered or removed
+//! * a message that needs more fields becomes a new variant, the old one stays
+//!
+//! Peers exchange [`Hello`] first and use the version returned by [`Hello::negotiate`].
+//! Every change of the types increments [`PROTOCOL_VERSION`].
+//!
+//! On byte streams (serial ports) messages are COBS-encoded and end with a zero byte
+//! ([`encode_cobs`], [`decode_cobs`]).
+
 #![no_std]
 
-pub fn get_sum(a: u32, b: u32) -> u32 {
-    a + b
-}
+mod codec;
+mod config;
+mod device;
+mod message;
+mod sensor;
+mod version;
+
+// Public re-export of specifics that are available outside of module
+pub use codec::{CodecError, FRAME_MAX, decode, decode_cobs, encode, encode_cobs};
+pub use config::{ConfigKey, ConfigRecord, ConfigValue, TEXT_MAX};
+pub use device::{DATA_MAX, DeviceInfo, DirEntry, NAME_MAX, PATH_MAX, ScreenChunk};
+pub use message::{COMMAND_MAX, ErrorCode, Frame, OUTPUT_MAX, Request, Response};
+pub use sensor::SensorReading;
+pub use version::{Hello, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
@@ -0,0 +1,110 @@
+//! Command and response frames
+
+use crate::config::{ConfigKey, ConfigRecord};
+use crate::device::{DATA_MAX, DeviceInfo, DirEntry, PATH_MAX, ScreenChunk};
+use crate::sensor::SensorReading;
+use crate::version::Hello;
+use heapless::{String, Vec};
+use serde::{Deserialize, Serialize};
+
+/// Maximal shell command length, bytes
+pub const COMMAND_MAX: usize = 64;
+
+/// Maximal shell output length per response, bytes
+pub const OUTPUT_MAX: usize = 128;
+
+/// Message with the request identifier, the response repeats the identifier of its request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame<T> {
+    /// Request identifier, chosen by the requesting peer
+    pub id: u16,
+    /// Request or response
+    pub message: T,
+}
+
+/// Requests of the host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Request {
+    /// Version negotiation, answered with [`Response::Hello`]
+    Hello(Hello),
+    /// Latest measurement, answered with [`Response::Reading`]
+    GetReading,
+    /// Configuration entry, answered with [`Response::Config`]
+    GetConfig(ConfigKey),
+    /// Change and commit a configuration entry, answered with [`Response::Ack`]
+    SetConfig(ConfigRecord),
+    /// Shell command line, answered with [`Response::Output`]
+    Command(String<COMMAND_MAX>),
+    /// Device identification, answered with [`Response::Info`] (version 2)
+    GetInfo,
+    /// Next buffered log lines, answered with [`Response::Log`], empty - nothing new (version 2)
+    ReadLog,
+    /// Entry of an SD card directory, answered with [`Response::DirEntry`] (version 2)
+    ListDir {
+        /// Directory path, `/` - root
+        path: String<PATH_MAX>,
+        /// Entry index, `None` in the response - no more entries
+        index: u16,
+    },
+    /// Part of an SD card file, answered with [`Response::Data`], empty - end of file
+    /// (version 2)
+    ReadFile {
+        /// File path
+        path: String<PATH_MAX>,
+        /// Offset in the file, bytes
+        offset: u32,
+    },
+    /// Part of the screen contents, answered with [`Response::Screen`] (version 2)
+    Screenshot {
+        /// Offset in the RGB565 pixel data, bytes
+        offset: u32,
+    },
+}
+
+/// Responses of the device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Response {
+    /// Versions supported by the device
+    Hello(Hello),
+    /// Latest measurement
+    Reading(SensorReading),
+    /// Configuration entry
+    Config(ConfigRecord),
+    /// Request executed
+    Ack,
+    /// Request failed
+    Error(ErrorCode),
+    /// Shell output
+    Output(String<OUTPUT_MAX>),
+    /// Device identification (version 2)
+    Info(DeviceInfo),
+    /// Log lines (version 2)
+    Log(String<OUTPUT_MAX>),
+    /// Directory entry, `None` - no more entries (version 2)
+    DirEntry(Option<DirEntry>),
+    /// File data (version 2)
+    Data(Vec<u8, DATA_MAX>),
+    /// Screen contents (version 2)
+    Screen(ScreenChunk),
+}
+
+/// Reasons of a failed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ErrorCode {
+    /// No common protocol version
+    UnsupportedVersion,
+    /// Request could not be decoded (possibly a newer variant)
+    UnknownRequest,
+    /// Value is out of range or has the wrong type
+    InvalidValue,
+    /// Device can not execute the request now
+    Busy,
+    /// Device failure
+    Internal,
+    /// File or directory does not exist (version 2)
+    NotFound,
+}
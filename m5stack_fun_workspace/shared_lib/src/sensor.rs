@@ -0,0 +1,17 @@
+//! Sensor readings
+
+use serde::{Deserialize, Serialize};
+
+/// One measurement cycle, `None` - the sensor is not available
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SensorReading {
+    /// Measurement counter since reset
+    pub sequence: u32,
+    /// Temperature, 0.01 °C
+    pub temperature_centi: Option<i16>,
+    /// Relative humidity, 0.01 %
+    pub humidity_centi: Option<u16>,
+    /// Battery voltage, mV
+    pub battery_mv: Option<u16>,
+}
@@ -0,0 +1,37 @@
+//! Protocol version negotiation
+
+use serde::{Deserialize, Serialize};
+
+/// Protocol version implemented by this crate
+/// * 1 - readings, configuration, shell commands
+/// * 2 - device info, log, SD card files, screenshots
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// Oldest protocol version this crate still understands
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// First message of both peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hello {
+    /// Newest supported version
+    pub version: u16,
+    /// Oldest supported version
+    pub min_version: u16,
+}
+
+impl Hello {
+    /// Versions supported by this crate
+    pub const LOCAL: Self = Self {
+        version: PROTOCOL_VERSION,
+        min_version: MIN_PROTOCOL_VERSION,
+    };
+
+    /// Newest version supported by both peers, `None` - the ranges do not overlap
+    ///
+    /// * `peer` - Hello of the other peer
+    pub fn negotiate(&self, peer: &Hello) -> Option<u16> {
+        let version = self.version.min(peer.version);
+        (version >= self.min_version.max(peer.min_version)).then_some(version)
+    }
+}
@@ -0,0 +1,238 @@
+//! Encoding tests: round trips and the stability of the version 1 encoding
+
+use shared_lib::{
+    CodecError, ConfigKey, ConfigRecord, ConfigValue, DATA_MAX, DeviceInfo, DirEntry, ErrorCode,
+    FRAME_MAX, Frame, Hello, PROTOCOL_VERSION, Request, Response, ScreenChunk, SensorReading,
+    decode, decode_cobs, encode, encode_cobs,
+};
+
+/// Encode and decode a value, returns the encoded bytes
+fn round_trip<T>(value: &T) -> Vec<u8>
+where
+    T: serde::Serialize + for<'a> serde::Deserialize<'a> + PartialEq + core::fmt::Debug,
+{
+    let mut buffer = [0_u8; FRAME_MAX];
+    let bytes = encode(value, &mut buffer).expect("encode").to_vec();
+    let decoded: T = decode(&bytes).expect("decode");
+    assert_eq!(&decoded, value);
+    bytes
+}
+
+fn reading() -> SensorReading {
+    SensorReading {
+        sequence: 5,
+        temperature_centi: Some(-150),
+        humidity_centi: Some(4520),
+        battery_mv: None,
+    }
+}
+
+#[test]
+fn requests_round_trip() {
+    let requests = [
+        Request::Hello(Hello::LOCAL),
+        Request::GetReading,
+        Request::GetConfig(ConfigKey::AdvIntervalMs),
+        Request::SetConfig(ConfigRecord {
+            key: ConfigKey::TemperatureOffsetCenti,
+            value: ConfigValue::I32(-250),
+        }),
+        Request::Command("set interval 5000".try_into().unwrap()),
+        Request::GetInfo,
+        Request::ReadLog,
+        Request::ListDir {
+            path: "/logs".try_into().unwrap(),
+            index: 2,
+        },
+        Request::ReadFile {
+            path: "/logs/boot.txt".try_into().unwrap(),
+            offset: 4096,
+        },
+        Request::Screenshot { offset: 128 },
+    ];
+    for (id, message) in (0..).zip(requests) {
+        round_trip(&Frame { id, message });
+    }
+}
+
+#[test]
+fn responses_round_trip() {
+    let responses = [
+        Response::Hello(Hello::LOCAL),
+        Response::Reading(reading()),
+        Response::Config(ConfigRecord {
+            key: ConfigKey::NotifyEnabled,
+            value: ConfigValue::Bool(true),
+        }),
+        Response::Ack,
+        Response::Error(ErrorCode::Busy),
+        Response::Output("ok, generation 3\r\n".try_into().unwrap()),
+        Response::Info(DeviceInfo {
+            name: "cardputer".try_into().unwrap(),
+            firmware: "0.1.0".try_into().unwrap(),
+            uptime_s: 3600,
+        }),
+        Response::Log("[ble] connected\n".try_into().unwrap()),
+        Response::DirEntry(Some(DirEntry {
+            name: "boot.txt".try_into().unwrap(),
+            size: 17,
+            is_dir: false,
+        })),
+        Response::DirEntry(None),
+        Response::Data(b"\0\x01\x02".as_slice().try_into().unwrap()),
+        Response::Screen(ScreenChunk {
+            width: 240,
+            height: 135,
+            data: [].as_slice().try_into().unwrap(),
+        }),
+        Response::Error(ErrorCode::NotFound),
+    ];
+    for (id, message) in (0..).zip(responses) {
+        round_trip(&Frame { id, message });
+    }
+}
+
+#[test]
+fn largest_messages_fit_the_frame() {
+    let output = "x".repeat(shared_lib::OUTPUT_MAX);
+    round_trip(&Frame {
+        id: u16::MAX,
+        message: Response::Output(output.as_str().try_into().unwrap()),
+    });
+    let command = "x".repeat(shared_lib::COMMAND_MAX);
+    round_trip(&Frame {
+        id: u16::MAX,
+        message: Request::Command(command.as_str().try_into().unwrap()),
+    });
+
+    // COBS adds a byte per 254 bytes and the delimiter
+    let mut buffer = [0_u8; FRAME_MAX];
+    let frame = Frame {
+        id: u16::MAX,
+        message: Response::Screen(ScreenChunk {
+            width: u16::MAX,
+            height: u16::MAX,
+            data: [0xFF; DATA_MAX].as_slice().try_into().unwrap(),
+        }),
+    };
+    encode_cobs(&frame, &mut buffer).expect("encode");
+}
+
+#[test]
+fn cobs_frames_have_no_zero_bytes_but_the_delimiter() {
+    let frame = Frame {
+        id: 0,
+        message: Response::Data([0, 1, 0, 0, 2].as_slice().try_into().unwrap()),
+    };
+    let mut buffer = [0_u8; FRAME_MAX];
+    let bytes = encode_cobs(&frame, &mut buffer).expect("encode");
+    let (last, body) = bytes.split_last_mut().unwrap();
+    assert_eq!(*last, 0);
+    assert!(!body.contains(&0));
+    assert_eq!(decode_cobs::<Frame<Response>>(bytes), Ok(frame));
+
+    assert_eq!(
+        decode_cobs::<Frame<Response>>(&mut [0x05, 0x01, 0x00]),
+        Err(CodecError::Malformed)
+    );
+}
+
+/// Bytes produced by protocol version 1 must decode forever
+#[test]
+fn version_1_encoding_is_stable() {
+    let cases: [(Frame<Request>, &[u8]); 3] = [
+        (
+            Frame {
+                id: 1,
+                message: Request::GetReading,
+            },
+            &[0x01, 0x01],
+        ),
+        (
+            Frame {
+                id: 300,
+                message: Request::Hello(Hello {
+                    version: 1,
+                    min_version: 1,
+                }),
+            },
+            &[0xAC, 0x02, 0x00, 0x01, 0x01],
+        ),
+        (
+            Frame {
+                id: 2,
+                message: Request::SetConfig(ConfigRecord {
+                    key: ConfigKey::DeviceName,
+                    value: ConfigValue::Text("cp".try_into().unwrap()),
+                }),
+            },
+            &[0x02, 0x03, 0x00, 0x03, 0x02, b'c', b'p'],
+        ),
+    ];
+    for (frame, bytes) in cases {
+        assert_eq!(round_trip(&frame), bytes);
+        assert_eq!(decode::<Frame<Request>>(bytes).unwrap(), frame);
+    }
+
+    let frame = Frame {
+        id: 7,
+        message: Response::Reading(reading()),
+    };
+    let bytes = [0x07, 0x01, 0x05, 0x01, 0xAB, 0x02, 0x01, 0xA8, 0x23, 0x00];
+    assert_eq!(round_trip(&frame), bytes);
+}
+
+#[test]
+fn unknown_variant_is_malformed() {
+    // Variant 0x7F - a request from a newer protocol version
+    assert_eq!(
+        decode::<Frame<Request>>(&[0x01, 0x7F]),
+        Err(CodecError::Malformed)
+    );
+}
+
+#[test]
+fn truncated_and_trailing_bytes_are_malformed() {
+    assert_eq!(
+        decode::<Frame<Request>>(&[0xAC, 0x02, 0x00, 0x01]),
+        Err(CodecError::Malformed)
+    );
+    assert_eq!(
+        decode::<Frame<Request>>(&[0x01, 0x01, 0x00]),
+        Err(CodecError::Malformed)
+    );
+}
+
+#[test]
+fn small_buffer_is_reported() {
+    let mut buffer = [0_u8; 4];
+    let frame = Frame {
+        id: 7,
+        message: Response::Reading(reading()),
+    };
+    assert_eq!(encode(&frame, &mut buffer), Err(CodecError::BufferFull));
+}
+
+#[test]
+fn negotiation_picks_the_newest_common_version() {
+    let local = Hello {
+        version: 3,
+        min_version: 1,
+    };
+    let older = Hello {
+        version: 2,
+        min_version: 2,
+    };
+    assert_eq!(local.negotiate(&older), Some(2));
+    assert_eq!(older.negotiate(&local), Some(2));
+
+    let newer = Hello {
+        version: 5,
+        min_version: 4,
+    };
+    assert_eq!(local.negotiate(&newer), None);
+    assert_eq!(
+        Hello::LOCAL.negotiate(&Hello::LOCAL),
+        Some(PROTOCOL_VERSION)
+    );
+}
@@ -0,0 +1,69 @@
+//! CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF, not reflected)
+
+/// Polynomial
+const POLYNOMIAL: u16 = 0x1021;
+
+/// Lookup table, computed at compile time
+#[allow(clippy::indexing_slicing, clippy::cast_possible_truncation)] // i < 256
+const TABLE: [u16; 256] = {
+    let mut table = [0_u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ POLYNOMIAL
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Incremental CRC-16 calculation
+pub struct Crc16(u16);
+
+impl Default for Crc16 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc16 {
+    /// Start a new calculation
+    pub fn new() -> Self {
+        Self(0xFFFF)
+    }
+
+    /// Feed the next bytes
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            let [high, _] = self.0.to_be_bytes();
+            let index = usize::from(high ^ byte);
+            self.0 = (self.0 << 8) ^ TABLE.get(index).copied().unwrap_or_default();
+        }
+    }
+
+    /// Final CRC value
+    pub fn finish(self) -> u16 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        let mut crc = Crc16::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0x29B1);
+    }
+}
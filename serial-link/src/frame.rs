@@ -0,0 +1,208 @@
+//! Frame encoding and the byte-by-byte frame decoder
+//!
+//! ```text
+//! COBS( | kind (1) | sequence (1) | payload (0..=PAYLOAD_MAX) | CRC-16 (2, little-endian) | ) 0x00
+//! ```
+
+use crate::crc::Crc16;
+
+/// Maximal payload length, bytes (the raw frame fits one COBS block)
+pub const PAYLOAD_MAX: usize = 250;
+
+/// Kind, sequence and CRC-16
+const OVERHEAD: usize = 4;
+
+/// Maximal encoded frame length with the delimiter, bytes
+pub const FRAME_MAX: usize = cobs::max_encoding_length(PAYLOAD_MAX + OVERHEAD) + 1;
+
+/// Frame kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// Payload, must be acknowledged
+    Data = 0,
+    /// Data frame with the sequence number was received
+    Ack = 1,
+    /// Corrupted frame was received, retransmit the sequence number
+    Nak = 2,
+    /// Link was created, the receiver starts a new session; acknowledged like data
+    Sync = 3,
+}
+
+/// Frame decoding errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// More than [`FRAME_MAX`] bytes without a delimiter
+    Overflow,
+    /// Bytes are not a frame (invalid COBS, too short, unknown kind)
+    Malformed,
+    /// CRC-16 mismatch
+    Crc,
+}
+
+/// Decoded frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Frame<'a> {
+    pub kind: Kind,
+    pub seq: u8,
+    pub payload: &'a [u8],
+}
+
+/// Encode a frame with the delimiter. Returns the used part of the buffer
+///
+/// * `kind` - Frame kind
+/// * `seq` - Sequence number
+/// * `payload` - Payload, up to [`PAYLOAD_MAX`] bytes
+/// * `out` - Output buffer
+pub(crate) fn encode<'a>(
+    kind: Kind,
+    seq: u8,
+    payload: &[u8],
+    out: &'a mut [u8; FRAME_MAX],
+) -> &'a [u8] {
+    let mut raw = [0_u8; PAYLOAD_MAX + OVERHEAD];
+    let payload = payload.get(..PAYLOAD_MAX).unwrap_or(payload);
+    let len = payload.len() + OVERHEAD;
+    if let Some(raw) = raw.get_mut(..len) {
+        let (header, rest) = raw.split_at_mut(2);
+        header.copy_from_slice(&[kind as u8, seq]);
+        let (body, crc) = rest.split_at_mut(payload.len());
+        body.copy_from_slice(payload);
+        let mut checksum = Crc16::new();
+        checksum.update(&[kind as u8, seq]);
+        checksum.update(payload);
+        crc.copy_from_slice(&checksum.finish().to_le_bytes());
+    }
+
+    // Буфер рассчитан на худший случай, кодирование не может не поместиться
+    let encoded = raw
+        .get(..len)
+        .and_then(|raw| cobs::try_encode(raw, out).ok())
+        .unwrap_or_default();
+    if let Some(delimiter) = out.get_mut(encoded) {
+        *delimiter = 0;
+    }
+    out.get(..=encoded).unwrap_or_default()
+}
+
+/// Collects bytes up to a delimiter and decodes the frame
+pub(crate) struct Decoder {
+    buffer: [u8; FRAME_MAX],
+    len: usize,
+    /// Frame is too long, bytes are dropped until the next delimiter
+    overflow: bool,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            buffer: [0; FRAME_MAX],
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    /// Feed the next received byte. Returns a frame or an error once a delimiter arrives
+    ///
+    /// * `byte` - Received byte
+    pub fn push(&mut self, byte: u8) -> Option<Result<Frame<'_>, FrameError>> {
+        if byte != 0 {
+            if !self.overflow {
+                match self.buffer.get_mut(self.len) {
+                    Some(slot) => {
+                        *slot = byte;
+                        self.len += 1;
+                    }
+                    None => self.overflow = true,
+                }
+            }
+            return None;
+        }
+
+        let len = core::mem::take(&mut self.len);
+        if core::mem::take(&mut self.overflow) {
+            return Some(Err(FrameError::Overflow));
+        }
+        // Лишние разделители между кадрами допустимы
+        if len == 0 {
+            return None;
+        }
+        Some(self.decode(len))
+    }
+
+    /// Decode the collected bytes
+    fn decode(&mut self, len: usize) -> Result<Frame<'_>, FrameError> {
+        let encoded = self.buffer.get_mut(..len).ok_or(FrameError::Overflow)?;
+        let len = cobs::decode_in_place(encoded).map_err(|_| FrameError::Malformed)?;
+        let raw = self.buffer.get(..len).ok_or(FrameError::Malformed)?;
+        if raw.len() < OVERHEAD {
+            return Err(FrameError::Malformed);
+        }
+        let (body, crc) = raw.split_at(raw.len() - 2);
+        let mut checksum = Crc16::new();
+        checksum.update(body);
+        if crc != checksum.finish().to_le_bytes() {
+            return Err(FrameError::Crc);
+        }
+        let [kind, seq, payload @ ..] = body else {
+            return Err(FrameError::Malformed);
+        };
+        let kind = match kind {
+            0 => Kind::Data,
+            1 => Kind::Ack,
+            2 => Kind::Nak,
+            3 => Kind::Sync,
+            _ => return Err(FrameError::Malformed),
+        };
+        Ok(Frame {
+            kind,
+            seq: *seq,
+            payload,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(
+        decoder: &mut Decoder,
+        bytes: &[u8],
+    ) -> Option<Result<(Kind, u8, usize), FrameError>> {
+        let mut last = None;
+        for &byte in bytes {
+            if let Some(result) = decoder.push(byte) {
+                last = Some(result.map(|frame| (frame.kind, frame.seq, frame.payload.len())));
+            }
+        }
+        last
+    }
+
+    #[test]
+    fn largest_frame_fits() {
+        let mut out = [0_u8; FRAME_MAX];
+        let payload = [0xAA_u8; PAYLOAD_MAX];
+        let frame = encode(Kind::Data, 7, &payload, &mut out).to_vec();
+        assert!(frame.len() <= FRAME_MAX);
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            decode_all(&mut decoder, &frame),
+            Some(Ok((Kind::Data, 7, PAYLOAD_MAX)))
+        );
+    }
+
+    #[test]
+    fn overflow_resynchronises() {
+        let mut decoder = Decoder::new();
+        assert_eq!(
+            decode_all(
+                &mut decoder,
+                &[[0x55; FRAME_MAX + 10].as_slice(), &[0]].concat()
+            ),
+            Some(Err(FrameError::Overflow))
+        );
+        let mut out = [0_u8; FRAME_MAX];
+        let frame = encode(Kind::Ack, 1, &[], &mut out);
+        assert_eq!(decode_all(&mut decoder, frame), Some(Ok((Kind::Ack, 1, 0))));
+    }
+}
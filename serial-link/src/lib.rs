@@ -0,0 +1,52 @@
+//! Reliable message link over a byte stream (UART)
+//!
+//! Every message travels in its own frame, so messages survive being split across reads
+//! (idle timeouts, FIFO thresholds) and are never glued together:
+//!
+//! ```text
+//! COBS( | kind (1) | sequence (1) | payload | CRC-16 (2) | ) 0x00
+//! ```
+//!
+//! * COBS removes zero bytes from the frame, zero marks the frame end. After noise or a lost
+//!   byte the receiver resynchronises on the next zero
+//! * CRC-16/CCITT-FALSE covers kind, sequence and payload
+//! * `kind` - data, sync, ACK or NAK. Every data frame is acknowledged with its sequence
+//!   number, a corrupted frame is answered with a NAK and retransmitted at once, a lost one
+//!   after [`LinkConfig::ack_timeout_ms`]. A repeated sequence number (the ACK got lost) is
+//!   acknowledged again but not delivered twice
+//! * A new [`Link`] starts a session: before its first payload it sends an empty sync frame
+//!   and waits for the acknowledgement. The receiver takes the sync sequence number as the
+//!   last received one, so after a peer restart the first payload is never mistaken for a
+//!   duplicate of the previous session
+//!
+//! The frames use the `cobs` crate, the same COBS encoding as the serial port frames of
+//! `shared_lib` (`encode_cobs`, `decode_cobs`).
+//!
+//! [`Link`] does no I/O and takes the time as an argument, so the same code works with a
+//! blocking UART, an interrupt handler or an async driver:
+//!
+//! ```ignore
+//! let mut link = Link::new(LinkConfig::default());
+//! let mut frame = [0; FRAME_MAX];
+//! loop {
+//!     for byte in uart_bytes() {
+//!         if let Some(Event::Received(payload)) = link.push(byte) {
+//!             handle(payload);
+//!         }
+//!     }
+//!     while let Ok(Some(bytes)) = link.poll(now_ms(), &mut frame) {
+//!         uart_write(bytes);
+//!     }
+//! }
+//! ```
+
+#![no_std]
+
+mod crc;
+mod frame;
+mod link;
+
+// Public re-export of specifics that are available outside of module
+pub use crc::Crc16;
+pub use frame::{FRAME_MAX, FrameError, PAYLOAD_MAX};
+pub use link::{Event, Link, LinkConfig, LinkError, LinkStats};
@@ -0,0 +1,251 @@
+//! Stop-and-wait link: one data frame in flight, acknowledged by sequence number
+
+use crate::frame::{self, Decoder, FRAME_MAX, FrameError, Kind, PAYLOAD_MAX};
+
+/// Link timing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkConfig {
+    /// Time to wait for an acknowledgement before retransmitting, ms
+    pub ack_timeout_ms: u64,
+    /// Retransmissions before the frame is dropped
+    pub retries_max: u8,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            ack_timeout_ms: 200,
+            retries_max: 5,
+        }
+    }
+}
+
+/// Link errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkError {
+    /// Previous payload is not acknowledged yet
+    Busy,
+    /// Payload is longer than [`PAYLOAD_MAX`]
+    TooLong,
+    /// Peer did not acknowledge the payload, it was dropped
+    Timeout,
+}
+
+/// Link counters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LinkStats {
+    /// Payloads acknowledged by the peer
+    pub delivered: u32,
+    /// Payloads received from the peer (duplicates excluded)
+    pub received: u32,
+    /// Data frames sent again
+    pub retransmits: u32,
+    /// Data frames received again (the acknowledgement was lost)
+    pub duplicates: u32,
+    /// Frames with a CRC mismatch
+    pub crc_errors: u32,
+    /// Frames that were not valid or too long
+    pub malformed: u32,
+    /// Payloads dropped after [`LinkConfig::retries_max`]
+    pub failed: u32,
+}
+
+/// Result of a received byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<'a> {
+    /// New payload of the peer, the acknowledgement is queued
+    Received(&'a [u8]),
+    /// Peer acknowledged the sent payload, the next one can be sent
+    Delivered,
+    /// Corrupted frame was dropped, a NAK is queued
+    Corrupted(FrameError),
+}
+
+/// Payload waiting for its acknowledgement
+struct Pending {
+    seq: u8,
+    payload: [u8; PAYLOAD_MAX],
+    len: usize,
+    /// Time of the last transmission, `None` - transmit on the next poll
+    sent_at_ms: Option<u64>,
+    /// Transmissions so far
+    attempts: u8,
+}
+
+/// Reliable link state. It does no I/O: received bytes go to [`Link::push`], frames to send
+/// come from [`Link::poll`]
+pub struct Link {
+    config: LinkConfig,
+    decoder: Decoder,
+    /// Sequence number of the next payload
+    tx_seq: u8,
+    pending: Option<Pending>,
+    /// Peer acknowledged the sync frame of this session, until then the pending payload waits
+    synced: bool,
+    /// Sequence number of the last delivered payload of the peer
+    rx_last: Option<u8>,
+    /// ACK or NAK to send
+    control: Option<(Kind, u8)>,
+    stats: LinkStats,
+}
+
+impl Link {
+    /// Create an idle link
+    ///
+    /// * `config` - Link timing
+    pub fn new(config: LinkConfig) -> Self {
+        Self {
+            config,
+            decoder: Decoder::new(),
+            tx_seq: 0,
+            pending: None,
+            synced: false,
+            rx_last: None,
+            control: None,
+            stats: LinkStats::default(),
+        }
+    }
+
+    /// Queue a payload. Only one payload is in flight, the next one is accepted after
+    /// [`Event::Delivered`] or [`LinkError::Timeout`]. The first payload of the link is sent
+    /// after the sync frame is acknowledged
+    ///
+    /// * `payload` - Up to [`PAYLOAD_MAX`] bytes
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), LinkError> {
+        if self.pending.is_some() {
+            return Err(LinkError::Busy);
+        }
+        let mut pending = Pending {
+            seq: self.tx_seq,
+            payload: [0; PAYLOAD_MAX],
+            len: payload.len(),
+            sent_at_ms: None,
+            attempts: 0,
+        };
+        pending
+            .payload
+            .get_mut(..payload.len())
+            .ok_or(LinkError::TooLong)?
+            .copy_from_slice(payload);
+        self.tx_seq = self.tx_seq.wrapping_add(1);
+        self.pending = Some(pending);
+        Ok(())
+    }
+
+    /// Whether a new payload can be sent
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_none()
+    }
+
+    /// Link counters
+    pub fn stats(&self) -> LinkStats {
+        self.stats
+    }
+
+    /// Feed a received byte
+    ///
+    /// * `byte` - Received byte
+    pub fn push(&mut self, byte: u8) -> Option<Event<'_>> {
+        let frame = match self.decoder.push(byte)? {
+            Ok(frame) => frame,
+            Err(e) => {
+                match e {
+                    FrameError::Crc => self.stats.crc_errors += 1,
+                    FrameError::Overflow | FrameError::Malformed => self.stats.malformed += 1,
+                }
+                // Номер испорченного кадра неизвестен - ждём следующий по порядку
+                let expected = self.rx_last.map_or(0, |seq| seq.wrapping_add(1));
+                self.control = Some((Kind::Nak, expected));
+                return Some(Event::Corrupted(e));
+            }
+        };
+
+        match frame.kind {
+            Kind::Data => {
+                self.control = Some((Kind::Ack, frame.seq));
+                if self.rx_last == Some(frame.seq) {
+                    self.stats.duplicates += 1;
+                    return None;
+                }
+                self.rx_last = Some(frame.seq);
+                self.stats.received += 1;
+                Some(Event::Received(frame.payload))
+            }
+            Kind::Sync => {
+                // Новая сессия собеседника: следующий кадр данных не считается повтором
+                self.control = Some((Kind::Ack, frame.seq));
+                if self.rx_last == Some(frame.seq) {
+                    self.stats.duplicates += 1;
+                }
+                self.rx_last = Some(frame.seq);
+                None
+            }
+            Kind::Ack => {
+                let pending = self.pending.as_mut()?;
+                if pending.seq != frame.seq {
+                    return None;
+                }
+                if !self.synced {
+                    // Данные уходят со следующим номером, опоздавший ACK синхронизации их не
+                    // подтвердит
+                    self.synced = true;
+                    pending.seq = pending.seq.wrapping_add(1);
+                    pending.sent_at_ms = None;
+                    pending.attempts = 0;
+                    self.tx_seq = pending.seq.wrapping_add(1);
+                    return None;
+                }
+                self.pending = None;
+                self.stats.delivered += 1;
+                Some(Event::Delivered)
+            }
+            Kind::Nak => {
+                let pending = self.pending.as_mut()?;
+                if pending.seq == frame.seq {
+                    pending.sent_at_ms = None;
+                }
+                None
+            }
+        }
+    }
+
+    /// Next frame to write: a queued ACK/NAK, the sync frame, a new payload or a retransmission.
+    /// Call until it returns `Ok(None)` after every [`Link::push`] and periodically
+    ///
+    /// * `now_ms` - Monotonic time, ms
+    /// * `out` - Frame buffer
+    pub fn poll<'a>(
+        &mut self,
+        now_ms: u64,
+        out: &'a mut [u8; FRAME_MAX],
+    ) -> Result<Option<&'a [u8]>, LinkError> {
+        if let Some((kind, seq)) = self.control.take() {
+            return Ok(Some(frame::encode(kind, seq, &[], out)));
+        }
+
+        let Some(pending) = self.pending.as_mut() else {
+            return Ok(None);
+        };
+        let due = pending
+            .sent_at_ms
+            .is_none_or(|sent| now_ms.saturating_sub(sent) >= self.config.ack_timeout_ms);
+        if !due {
+            return Ok(None);
+        }
+        if pending.attempts > self.config.retries_max {
+            self.pending = None;
+            self.stats.failed += 1;
+            return Err(LinkError::Timeout);
+        }
+        if pending.attempts > 0 {
+            self.stats.retransmits += 1;
+        }
+        pending.attempts += 1;
+        pending.sent_at_ms = Some(now_ms);
+        if !self.synced {
+            return Ok(Some(frame::encode(Kind::Sync, pending.seq, &[], out)));
+        }
+        let payload = pending.payload.get(..pending.len).unwrap_or_default();
+        Ok(Some(frame::encode(Kind::Data, pending.seq, payload, out)))
+    }
+}
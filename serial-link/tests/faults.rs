@@ -0,0 +1,344 @@
+//! Two links connected by a simulated UART that drops, corrupts and injects bytes
+
+#![allow(clippy::indexing_slicing, clippy::cast_possible_truncation)]
+
+use serial_link::{Event, FRAME_MAX, Link, LinkConfig, LinkError, PAYLOAD_MAX};
+use std::collections::VecDeque;
+
+/// Deterministic pseudo-random numbers (xorshift64)
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// True with the probability `1 / n`, never for `n == 0`
+    fn one_in(&mut self, n: u64) -> bool {
+        n != 0 && self.next().is_multiple_of(n)
+    }
+}
+
+/// Fault rates of one direction, `1 / n` per byte, 0 - never
+#[derive(Clone, Copy, Default)]
+struct Faults {
+    drop: u64,
+    flip: u64,
+    noise: u64,
+}
+
+/// One direction of the UART
+struct Wire {
+    bytes: VecDeque<u8>,
+    faults: Faults,
+    /// Everything written is lost
+    cut: bool,
+}
+
+impl Wire {
+    fn new(faults: Faults) -> Self {
+        Self {
+            bytes: VecDeque::new(),
+            faults,
+            cut: false,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8], rng: &mut Rng) {
+        if self.cut {
+            return;
+        }
+        for &byte in bytes {
+            if rng.one_in(self.faults.noise) {
+                self.bytes.push_back(rng.next() as u8);
+            }
+            if rng.one_in(self.faults.drop) {
+                continue;
+            }
+            let byte = if rng.one_in(self.faults.flip) {
+                byte ^ (1 << (rng.next() % 8))
+            } else {
+                byte
+            };
+            self.bytes.push_back(byte);
+        }
+    }
+}
+
+/// Link with its traffic
+struct Peer {
+    link: Link,
+    outbox: VecDeque<Vec<u8>>,
+    received: Vec<Vec<u8>>,
+    timeouts: u32,
+}
+
+impl Peer {
+    fn new(messages: &[Vec<u8>]) -> Self {
+        Self {
+            link: Link::new(LinkConfig {
+                ack_timeout_ms: 100,
+                retries_max: 10,
+            }),
+            outbox: messages.iter().cloned().collect(),
+            received: Vec::new(),
+            timeouts: 0,
+        }
+    }
+
+    /// Queue the next message and write the due frames
+    fn transmit(&mut self, now_ms: u64, wire: &mut Wire, rng: &mut Rng) {
+        if self.link.is_idle()
+            && let Some(message) = self.outbox.pop_front()
+        {
+            self.link
+                .send(&message)
+                .expect("idle link accepts a payload");
+        }
+        let mut frame = [0; FRAME_MAX];
+        loop {
+            match self.link.poll(now_ms, &mut frame) {
+                Ok(Some(bytes)) => wire.write(bytes, rng),
+                Ok(None) => break,
+                Err(LinkError::Timeout) => self.timeouts += 1,
+                Err(e) => panic!("unexpected error {e:?}"),
+            }
+        }
+    }
+
+    /// Read up to `count` bytes from the wire
+    fn receive(&mut self, wire: &mut Wire, count: usize) {
+        for _ in 0..count {
+            let Some(byte) = wire.bytes.pop_front() else {
+                break;
+            };
+            if let Some(Event::Received(payload)) = self.link.push(byte) {
+                self.received.push(payload.to_vec());
+            }
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.outbox.is_empty() && self.link.is_idle()
+    }
+}
+
+/// Messages of different lengths, zero bytes included
+fn messages(count: usize, rng: &mut Rng) -> Vec<Vec<u8>> {
+    (0..count)
+        .map(|i| {
+            let len = match i % 4 {
+                0 => 0,
+                1 => PAYLOAD_MAX,
+                _ => (rng.next() % PAYLOAD_MAX as u64) as usize,
+            };
+            (0..len)
+                .map(|_| if rng.one_in(4) { 0 } else { rng.next() as u8 })
+                .collect()
+        })
+        .collect()
+}
+
+/// Exchange messages in both directions, `cut_ms` - the link from b to a is down until then.
+/// Returns both peers once everything is delivered
+fn exchange(faults: Faults, seed: u64, count: usize, cut_ms: u64) -> (Peer, Peer) {
+    let mut rng = Rng(seed);
+    let (to_b, to_a) = (messages(count, &mut rng), messages(count, &mut rng));
+    let mut a = Peer::new(&to_b);
+    let mut b = Peer::new(&to_a);
+    let (mut a_to_b, mut b_to_a) = (Wire::new(faults), Wire::new(faults));
+
+    for now_ms in 0..200_000 {
+        b_to_a.cut = now_ms < cut_ms;
+        a.transmit(now_ms, &mut a_to_b, &mut rng);
+        b.transmit(now_ms, &mut b_to_a, &mut rng);
+        // Порции разной длины, как при чтении по таймауту простоя
+        let chunk = (rng.next() % 64) as usize;
+        b.receive(&mut a_to_b, chunk);
+        a.receive(&mut b_to_a, chunk);
+        if a.done() && b.done() && now_ms > cut_ms {
+            assert_eq!((a.timeouts, b.timeouts), (0, 0));
+            assert_eq!(b.received, to_b, "a -> b");
+            assert_eq!(a.received, to_a, "b -> a");
+            return (a, b);
+        }
+    }
+    panic!("messages were not delivered");
+}
+
+#[test]
+fn clean_line_delivers_in_order() {
+    let (a, b) = exchange(Faults::default(), 1, 50, 0);
+    let stats = a.link.stats();
+    assert_eq!((stats.delivered, stats.received), (50, 50));
+    assert_eq!(stats.retransmits + stats.crc_errors + stats.malformed, 0);
+    assert_eq!(b.link.stats().duplicates, 0);
+}
+
+#[test]
+fn flipped_bits_are_detected_and_retransmitted() {
+    let faults = Faults {
+        flip: 1_000,
+        ..Faults::default()
+    };
+    for seed in 1..=5 {
+        let (a, b) = exchange(faults, seed, 40, 0);
+        let (a, b) = (a.link.stats(), b.link.stats());
+        assert!(a.crc_errors + a.malformed + b.crc_errors + b.malformed > 0);
+        assert!(a.retransmits + b.retransmits > 0);
+    }
+}
+
+#[test]
+fn dropped_and_injected_bytes_resynchronise() {
+    let faults = Faults {
+        drop: 2_000,
+        noise: 2_000,
+        ..Faults::default()
+    };
+    for seed in 10..=14 {
+        let (a, b) = exchange(faults, seed, 40, 0);
+        assert!(a.link.stats().retransmits + b.link.stats().retransmits > 0);
+    }
+}
+
+#[test]
+fn all_faults_together() {
+    let faults = Faults {
+        drop: 3_000,
+        flip: 3_000,
+        noise: 3_000,
+    };
+    for seed in 20..=29 {
+        exchange(faults, seed, 30, 0);
+    }
+}
+
+#[test]
+fn lost_acknowledgements_do_not_duplicate_payloads() {
+    // b получает данные, но его ACK теряются - a повторяет кадр
+    let (a, b) = exchange(Faults::default(), 3, 5, 50);
+    assert!(a.link.stats().retransmits > 0);
+    assert!(b.link.stats().duplicates > 0);
+    assert_eq!(b.link.stats().received, 5);
+}
+
+/// Move every due frame from one link to the other, returns the received payloads
+fn transfer(from: &mut Link, to: &mut Link, now_ms: u64) -> Vec<Vec<u8>> {
+    let mut frame = [0; FRAME_MAX];
+    let mut received = Vec::new();
+    while let Ok(Some(bytes)) = from.poll(now_ms, &mut frame) {
+        for &byte in bytes {
+            if let Some(Event::Received(payload)) = to.push(byte) {
+                received.push(payload.to_vec());
+            }
+        }
+    }
+    received
+}
+
+#[test]
+fn nak_triggers_an_immediate_retransmission() {
+    let mut a = Link::new(LinkConfig {
+        ack_timeout_ms: 1_000,
+        retries_max: 3,
+    });
+    let mut b = Link::new(LinkConfig::default());
+    let mut frame = [0; FRAME_MAX];
+
+    a.send(b"hello").expect("send");
+    // Синхронизация сессии перед первыми данными
+    assert_eq!(transfer(&mut a, &mut b, 0), Vec::<Vec<u8>>::new());
+    assert_eq!(transfer(&mut b, &mut a, 0), Vec::<Vec<u8>>::new());
+    let mut bytes = a
+        .poll(0, &mut frame)
+        .expect("poll")
+        .expect("frame")
+        .to_vec();
+    bytes[3] ^= 0x01;
+    let events: Vec<_> = bytes
+        .iter()
+        .filter_map(|&byte| {
+            b.push(byte)
+                .map(|event| matches!(event, Event::Corrupted(_)))
+        })
+        .collect();
+    assert_eq!(events, [true]);
+
+    let nak = b.poll(1, &mut frame).expect("poll").expect("NAK").to_vec();
+    for byte in nak {
+        assert_eq!(a.push(byte), None);
+    }
+    // Повтор сразу, не дожидаясь таймаута
+    let bytes = a
+        .poll(2, &mut frame)
+        .expect("poll")
+        .expect("retransmission")
+        .to_vec();
+    let received: Vec<_> = bytes
+        .iter()
+        .filter_map(|&byte| match b.push(byte) {
+            Some(Event::Received(payload)) => Some(payload.to_vec()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(received, [b"hello".to_vec()]);
+    assert_eq!(a.stats().retransmits, 1);
+}
+
+#[test]
+fn silent_peer_times_out() {
+    let config = LinkConfig {
+        ack_timeout_ms: 10,
+        retries_max: 2,
+    };
+    let mut link = Link::new(config);
+    let mut frame = [0; FRAME_MAX];
+    link.send(b"ping").expect("send");
+    assert_eq!(link.send(b"pong"), Err(LinkError::Busy));
+
+    let mut transmissions = 0;
+    let mut result = Ok(None);
+    for now_ms in 0..100 {
+        result = link
+            .poll(now_ms, &mut frame)
+            .map(|frame| frame.map(<[u8]>::len));
+        match result {
+            Ok(Some(_)) => transmissions += 1,
+            Ok(None) => {}
+            Err(_) => break,
+        }
+    }
+    assert_eq!(result, Err(LinkError::Timeout));
+    assert_eq!(transmissions, 1 + u32::from(config.retries_max));
+    assert_eq!(link.stats().failed, 1);
+    assert!(link.is_idle());
+    assert_eq!(link.send(&[0; PAYLOAD_MAX + 1]), Err(LinkError::TooLong));
+}
+
+#[test]
+fn restarted_peer_starts_a_new_session() {
+    let mut a = Link::new(LinkConfig::default());
+    let mut b = Link::new(LinkConfig::default());
+    let mut received = Vec::new();
+    a.send(b"one").expect("send");
+    for now_ms in 0..4 {
+        received.extend(transfer(&mut a, &mut b, now_ms));
+        transfer(&mut b, &mut a, now_ms);
+    }
+    assert!(a.is_idle());
+
+    // Тот же порядок номеров после перезапуска: без синхронизации первый кадр был бы повтором
+    let mut a = Link::new(LinkConfig::default());
+    a.send(b"again").expect("send");
+    for now_ms in 0..4 {
+        received.extend(transfer(&mut a, &mut b, now_ms));
+        transfer(&mut b, &mut a, now_ms);
+    }
+    assert!(a.is_idle());
+    assert_eq!(received, [b"one".to_vec(), b"again".to_vec()]);
+    assert_eq!(b.stats().duplicates, 0);
+}
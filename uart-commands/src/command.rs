@@ -0,0 +1,252 @@
+//! Command line parser
+
+use core::str::SplitAsciiWhitespace;
+
+/// Longest accepted line without the line end, bytes
+pub const LINE_MAX: usize = 64;
+
+/// Error codes, sent as `ERR <code>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ErrorCode {
+    /// Unknown command
+    Unknown = 1,
+    /// Missing, extra or malformed arguments
+    Syntax = 2,
+    /// Argument is out of range
+    Range = 3,
+    /// Line is longer than [`LINE_MAX`] or not ASCII text
+    Line = 4,
+    /// Peripheral did not respond
+    Device = 5,
+}
+
+impl ErrorCode {
+    /// Numeric code of the reply
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+/// LED output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedState {
+    /// Constantly off
+    Off,
+    /// Constantly on
+    On,
+    /// PWM (LEDC) with the duty cycle, percent, `1..=100`
+    Pwm(u8),
+}
+
+/// Time of day, 24-hour clock
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimeOfDay {
+    hours: u8,
+    minutes: u8,
+    seconds: u8,
+}
+
+impl TimeOfDay {
+    /// Seconds in a day
+    pub const DAY_SECONDS: u32 = 24 * 60 * 60;
+
+    /// Create the time, `None` if a field is out of range
+    ///
+    /// * `hours` - `0..24`
+    /// * `minutes` - `0..60`
+    /// * `seconds` - `0..60`
+    pub fn new(hours: u8, minutes: u8, seconds: u8) -> Option<Self> {
+        (hours < 24 && minutes < 60 && seconds < 60).then_some(Self {
+            hours,
+            minutes,
+            seconds,
+        })
+    }
+
+    /// Time of day of a clock counting seconds since midnight, days are dropped
+    ///
+    /// * `seconds` - Seconds since midnight
+    pub fn from_seconds(seconds: u64) -> Self {
+        let seconds = seconds % u64::from(Self::DAY_SECONDS);
+        // Остатки от деления меньше 60 и 24 - влезают в u8
+        #[allow(clippy::cast_possible_truncation)]
+        Self {
+            hours: (seconds / 3600) as u8,
+            minutes: (seconds / 60 % 60) as u8,
+            seconds: (seconds % 60) as u8,
+        }
+    }
+
+    /// Seconds since midnight
+    pub fn seconds_since_midnight(self) -> u32 {
+        u32::from(self.hours) * 3600 + u32::from(self.minutes) * 60 + u32::from(self.seconds)
+    }
+
+    /// Hours, `0..24`
+    pub fn hours(self) -> u8 {
+        self.hours
+    }
+
+    /// Minutes, `0..60`
+    pub fn minutes(self) -> u8 {
+        self.minutes
+    }
+
+    /// Seconds, `0..60`
+    pub fn seconds(self) -> u8 {
+        self.seconds
+    }
+
+    /// Parse `HH:MM:SS`, one-digit fields are accepted
+    fn parse(text: &str) -> Result<Self, ErrorCode> {
+        let mut fields = text.split(':').map(|field| number(field, 2));
+        let (Some(hours), Some(minutes), Some(seconds), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(ErrorCode::Syntax);
+        };
+        let field = |value: u32| u8::try_from(value).map_err(|_| ErrorCode::Range);
+        Self::new(field(hours?)?, field(minutes?)?, field(seconds?)?).ok_or(ErrorCode::Range)
+    }
+}
+
+/// Parsed command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// `LED ON`, `LED OFF`, `LED PWM <percent>`
+    Led(LedState),
+    /// `LED?`
+    LedQuery,
+    /// `TEMP?` - temperature and humidity
+    Temperature,
+    /// `TIME HH:MM:SS`
+    SetTime(TimeOfDay),
+    /// `TIME?`
+    Time,
+}
+
+impl Command {
+    /// Parse a line. Case and extra spaces do not matter, a trailing `\r\n` is ignored
+    ///
+    /// * `line` - Received line
+    pub fn parse(line: &[u8]) -> Result<Self, ErrorCode> {
+        let line = line.trim_ascii();
+        if line.len() > LINE_MAX || !line.is_ascii() {
+            return Err(ErrorCode::Line);
+        }
+        let line = core::str::from_utf8(line).map_err(|_| ErrorCode::Line)?;
+
+        let mut words = line.split_ascii_whitespace();
+        let name = words.next().ok_or(ErrorCode::Unknown)?;
+        let is = |expected: &str| name.eq_ignore_ascii_case(expected);
+
+        let command = if is("LED") {
+            let state = words.next().ok_or(ErrorCode::Syntax)?;
+            if state.eq_ignore_ascii_case("ON") {
+                Self::Led(LedState::On)
+            } else if state.eq_ignore_ascii_case("OFF") {
+                Self::Led(LedState::Off)
+            } else if state.eq_ignore_ascii_case("PWM") {
+                let duty = number(words.next().ok_or(ErrorCode::Syntax)?, 3)?;
+                match u8::try_from(duty) {
+                    Ok(duty @ 1..=100) => Self::Led(LedState::Pwm(duty)),
+                    // 0% - это выключенный светодиод, для него есть LED OFF
+                    _ => return Err(ErrorCode::Range),
+                }
+            } else {
+                return Err(ErrorCode::Syntax);
+            }
+        } else if is("TIME") {
+            Self::SetTime(TimeOfDay::parse(words.next().ok_or(ErrorCode::Syntax)?)?)
+        } else if is("LED?") {
+            Self::LedQuery
+        } else if is("TEMP?") {
+            Self::Temperature
+        } else if is("TIME?") {
+            Self::Time
+        } else {
+            return Err(ErrorCode::Unknown);
+        };
+        end(words)?;
+        Ok(command)
+    }
+}
+
+/// Unsigned decimal number of up to `digits` digits, longer ones are out of range
+fn number(text: &str, digits: usize) -> Result<u32, ErrorCode> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ErrorCode::Syntax);
+    }
+    if text.len() > digits {
+        return Err(ErrorCode::Range);
+    }
+    text.parse().map_err(|_| ErrorCode::Syntax)
+}
+
+/// Fail on extra arguments
+fn end(mut words: SplitAsciiWhitespace<'_>) -> Result<(), ErrorCode> {
+    match words.next() {
+        Some(_) => Err(ErrorCode::Syntax),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        let cases: [(&[u8], Command); 7] = [
+            (b"LED ON", Command::Led(LedState::On)),
+            (b"led off\r\n", Command::Led(LedState::Off)),
+            (b"  LED   PWM 40 ", Command::Led(LedState::Pwm(40))),
+            (b"LED?", Command::LedQuery),
+            (b"TEMP?\n", Command::Temperature),
+            (b"Time?", Command::Time),
+            (
+                b"TIME 12:30:00",
+                Command::SetTime(TimeOfDay {
+                    hours: 12,
+                    minutes: 30,
+                    seconds: 0,
+                }),
+            ),
+        ];
+        for (line, command) in cases {
+            assert_eq!(Command::parse(line), Ok(command), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let cases: [(&[u8], ErrorCode); 14] = [
+            (b"", ErrorCode::Unknown),
+            (b"BLINK", ErrorCode::Unknown),
+            (b"TEMP", ErrorCode::Unknown),
+            (b"LED", ErrorCode::Syntax),
+            (b"LED DIM", ErrorCode::Syntax),
+            (b"LED ON NOW", ErrorCode::Syntax),
+            (b"LED PWM", ErrorCode::Syntax),
+            (b"LED PWM -5", ErrorCode::Syntax),
+            (b"LED PWM 0", ErrorCode::Range),
+            (b"LED PWM 101", ErrorCode::Range),
+            (b"LED PWM 4000", ErrorCode::Range),
+            (b"TIME 12:30", ErrorCode::Syntax),
+            (b"TIME 24:00:00", ErrorCode::Range),
+            (b"TEMP?\xff", ErrorCode::Line),
+        ];
+        for (line, code) in cases {
+            assert_eq!(Command::parse(line), Err(code), "{line:?}");
+        }
+        assert_eq!(Command::parse(&[b'A'; LINE_MAX + 1]), Err(ErrorCode::Line));
+    }
+
+    #[test]
+    fn time_of_day_wraps_at_midnight() {
+        let time = TimeOfDay::from_seconds(u64::from(TimeOfDay::DAY_SECONDS) * 3 + 45_296);
+        assert_eq!((time.hours(), time.minutes(), time.seconds()), (12, 34, 56));
+        assert_eq!(time.seconds_since_midnight(), 45_296);
+    }
+}
@@ -0,0 +1,42 @@
+//! Dispatch of parsed commands to the peripherals
+
+use crate::command::{Command, ErrorCode, LedState, TimeOfDay};
+use crate::reply::{Measurement, Reply};
+
+/// Peripherals controlled by the commands, implemented by the firmware
+pub trait Handler {
+    /// Switch the LED or start PWM
+    ///
+    /// * `state` - New LED output
+    fn set_led(&mut self, state: LedState) -> Result<(), ErrorCode>;
+
+    /// Current LED output
+    fn led(&mut self) -> LedState;
+
+    /// Measure temperature and humidity
+    fn measure(&mut self) -> Result<Measurement, ErrorCode>;
+
+    /// Set the clock
+    ///
+    /// * `time` - New time of day
+    fn set_time(&mut self, time: TimeOfDay) -> Result<(), ErrorCode>;
+
+    /// Current time of day
+    fn time(&mut self) -> TimeOfDay;
+}
+
+/// Parse a line, run the command and build the reply
+///
+/// * `line` - Received line
+/// * `handler` - Peripherals
+pub fn dispatch(line: &[u8], handler: &mut impl Handler) -> Reply {
+    let reply = match Command::parse(line) {
+        Ok(Command::Led(state)) => handler.set_led(state).map(|()| Reply::Ok),
+        Ok(Command::LedQuery) => Ok(Reply::Led(handler.led())),
+        Ok(Command::Temperature) => handler.measure().map(Reply::Measurement),
+        Ok(Command::SetTime(time)) => handler.set_time(time).map(|()| Reply::Ok),
+        Ok(Command::Time) => Ok(Reply::Time(handler.time())),
+        Err(code) => Err(code),
+    };
+    reply.unwrap_or_else(Reply::Error)
+}
@@ -0,0 +1,41 @@
+//! Line-based commands for the UART examples
+//!
+//! Every line is one command, words are separated by spaces, case does not matter:
+//!
+//! ```text
+//! LED ON            -> OK
+//! LED PWM 40        -> OK
+//! LED?              -> OK PWM 40
+//! TEMP?             -> OK 23.45 41.20      (°C, %RH)
+//! TIME 12:30:00     -> OK
+//! TIME?             -> OK 12:30:05
+//! LED BLINK         -> ERR 2
+//! ```
+//!
+//! Error codes ([`ErrorCode`]):
+//!
+//! * `1` - unknown command
+//! * `2` - missing, extra or malformed arguments
+//! * `3` - argument out of range (`LED PWM 1..=100`, `TIME 00:00:00..=23:59:59`)
+//! * `4` - line is too long or not ASCII
+//! * `5` - peripheral did not respond
+//!
+//! The crate is `no_std` and does no I/O: the firmware implements [`Handler`] for its LED,
+//! LEDC, SHTC3 and RTC and passes every received line to [`dispatch`]:
+//!
+//! ```ignore
+//! let reply = dispatch(line, &mut device);
+//! let mut out = [0; REPLY_MAX];
+//! uart_write(reply.encode(&mut out));
+//! ```
+
+#![no_std]
+
+mod command;
+mod handler;
+mod reply;
+
+// Public re-export of specifics that are available outside of module
+pub use command::{Command, ErrorCode, LINE_MAX, LedState, TimeOfDay};
+pub use handler::{Handler, dispatch};
+pub use reply::{Measurement, REPLY_MAX, Reply};
@@ -0,0 +1,95 @@
+//! Replies: `OK [value]` or `ERR <code>`
+
+use core::fmt::{self, Write};
+
+use crate::command::{ErrorCode, LedState, TimeOfDay};
+
+/// Longest reply, bytes
+pub const REPLY_MAX: usize = 32;
+
+/// SHTC3 measurement
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    /// Temperature, thousandths of °C
+    pub millicelsius: i32,
+    /// Relative humidity, thousandths of %
+    pub millipercent: i32,
+}
+
+/// Reply to a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reply {
+    /// `OK`
+    Ok,
+    /// `OK ON`, `OK OFF`, `OK PWM <percent>`
+    Led(LedState),
+    /// `OK <°C> <%RH>` with two decimals, `OK 23.45 41.20`
+    Measurement(Measurement),
+    /// `OK HH:MM:SS`
+    Time(TimeOfDay),
+    /// `ERR <code>`
+    Error(ErrorCode),
+}
+
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => f.write_str("OK"),
+            Self::Led(LedState::On) => f.write_str("OK ON"),
+            Self::Led(LedState::Off) => f.write_str("OK OFF"),
+            Self::Led(LedState::Pwm(duty)) => write!(f, "OK PWM {duty}"),
+            Self::Measurement(measurement) => {
+                f.write_str("OK ")?;
+                hundredths(f, measurement.millicelsius)?;
+                f.write_char(' ')?;
+                hundredths(f, measurement.millipercent)
+            }
+            Self::Time(time) => write!(
+                f,
+                "OK {:02}:{:02}:{:02}",
+                time.hours(),
+                time.minutes(),
+                time.seconds()
+            ),
+            Self::Error(code) => write!(f, "ERR {}", code.code()),
+        }
+    }
+}
+
+impl Reply {
+    /// Text of the reply without a line end
+    ///
+    /// * `out` - Reply buffer
+    pub fn encode<'a>(&self, out: &'a mut [u8; REPLY_MAX]) -> &'a [u8] {
+        let mut cursor = Cursor { out, len: 0 };
+        // Самый длинный ответ короче REPLY_MAX
+        let _ = write!(cursor, "{self}");
+        let len = cursor.len;
+        out.get(..len).unwrap_or_default()
+    }
+}
+
+/// Thousandths as a number with two decimals, the rest is truncated
+fn hundredths(f: &mut fmt::Formatter<'_>, thousandths: i32) -> fmt::Result {
+    let sign = if thousandths < 0 { "-" } else { "" };
+    let value = thousandths.unsigned_abs() / 10;
+    write!(f, "{sign}{}.{:02}", value / 100, value % 100)
+}
+
+/// Writer into a fixed buffer
+struct Cursor<'a> {
+    out: &'a mut [u8; REPLY_MAX],
+    len: usize,
+}
+
+impl Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.out
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+//! Commands dispatched to a simulated device, replies compared as text
+
+use uart_commands::{
+    ErrorCode, Handler, LedState, Measurement, REPLY_MAX, Reply, TimeOfDay, dispatch,
+};
+
+/// Device with an LED, a sensor and a clock
+struct FakeDevice {
+    led: LedState,
+    sensor: Option<Measurement>,
+    /// Seconds since midnight
+    clock: u64,
+}
+
+impl Handler for FakeDevice {
+    fn set_led(&mut self, state: LedState) -> Result<(), ErrorCode> {
+        self.led = state;
+        Ok(())
+    }
+
+    fn led(&mut self) -> LedState {
+        self.led
+    }
+
+    fn measure(&mut self) -> Result<Measurement, ErrorCode> {
+        self.sensor.ok_or(ErrorCode::Device)
+    }
+
+    fn set_time(&mut self, time: TimeOfDay) -> Result<(), ErrorCode> {
+        self.clock = u64::from(time.seconds_since_midnight());
+        Ok(())
+    }
+
+    fn time(&mut self) -> TimeOfDay {
+        TimeOfDay::from_seconds(self.clock)
+    }
+}
+
+impl FakeDevice {
+    fn new() -> Self {
+        Self {
+            led: LedState::Off,
+            sensor: Some(Measurement {
+                millicelsius: 23_456,
+                millipercent: 41_209,
+            }),
+            clock: 0,
+        }
+    }
+
+    /// Run a command, the reply as text
+    fn run(&mut self, line: &str) -> String {
+        let mut out = [0; REPLY_MAX];
+        let reply = dispatch(line.as_bytes(), self);
+        String::from_utf8(reply.encode(&mut out).to_vec()).expect("ASCII reply")
+    }
+}
+
+#[test]
+fn led_commands_change_the_state() {
+    let mut device = FakeDevice::new();
+    assert_eq!(device.run("LED?"), "OK OFF");
+    assert_eq!(device.run("LED ON"), "OK");
+    assert_eq!(device.run("LED?"), "OK ON");
+    assert_eq!(device.run("LED PWM 40\r\n"), "OK");
+    assert_eq!(device.led, LedState::Pwm(40));
+    assert_eq!(device.run("LED?"), "OK PWM 40");
+    assert_eq!(device.run("LED PWM 140"), "ERR 3");
+    assert_eq!(
+        device.led,
+        LedState::Pwm(40),
+        "rejected command changes nothing"
+    );
+}
+
+#[test]
+fn temperature_is_formatted_with_two_decimals() {
+    let mut device = FakeDevice::new();
+    assert_eq!(device.run("TEMP?"), "OK 23.45 41.20");
+    device.sensor = Some(Measurement {
+        millicelsius: -5_009,
+        millipercent: 100_000,
+    });
+    assert_eq!(device.run("temp?"), "OK -5.00 100.00");
+    device.sensor = None;
+    assert_eq!(device.run("TEMP?"), "ERR 5");
+}
+
+#[test]
+fn clock_is_set_and_read() {
+    let mut device = FakeDevice::new();
+    assert_eq!(device.run("TIME 12:30:00"), "OK");
+    device.clock += 5;
+    assert_eq!(device.run("TIME?"), "OK 12:30:05");
+    assert_eq!(device.run("TIME 7:5:3"), "OK");
+    assert_eq!(device.run("TIME?"), "OK 07:05:03");
+    assert_eq!(device.run("TIME 12:60:00"), "ERR 3");
+    assert_eq!(device.run("TIME noon"), "ERR 2");
+}
+
+#[test]
+fn errors_have_codes() {
+    let mut device = FakeDevice::new();
+    assert_eq!(device.run("HELLO"), "ERR 1");
+    assert_eq!(device.run("LED BLINK"), "ERR 2");
+    assert_eq!(device.run(&"X".repeat(100)), "ERR 4");
+}
+
+#[test]
+fn longest_reply_fits() {
+    let reply = Reply::Measurement(Measurement {
+        millicelsius: i32::MIN,
+        millipercent: i32::MIN,
+    });
+    let mut out = [0; REPLY_MAX];
+    assert_eq!(reply.encode(&mut out), b"OK -2147483.64 -2147483.64");
+}
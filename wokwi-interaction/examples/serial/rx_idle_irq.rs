@@ -9,38 +9,86 @@
 use core::cell::{Cell, RefCell};
 use esp_backtrace as _;
 
+use buffered_uart::UartState;
 use critical_section::Mutex;
 use esp_hal::clock::CpuClock;
-use esp_hal::gpio::{Event, Input, InputConfig, Io, Level, Output, OutputConfig};
-
-use esp_hal::time::Duration;
+use esp_hal::delay::Delay;
+use esp_hal::gpio::{DriveMode, Event, Input, InputConfig, Io};
+use esp_hal::i2c::master::{self, I2c};
+use esp_hal::ledc::channel::{self, ChannelIFace};
+use esp_hal::ledc::timer::{self, TimerIFace};
+use esp_hal::ledc::{LSGlobalClkSource, Ledc, LowSpeed};
+use esp_hal::rtc_cntl::Rtc;
+use esp_hal::time::{Duration, Instant, Rate};
 use esp_hal::timer::PeriodicTimer;
 use esp_hal::timer::timg::{MwdtStage, TimerGroup};
-use esp_hal::uart::{Config, DataBits, RxConfig, Uart, UartInterrupt};
+use esp_hal::uart::{Config, DataBits, RxConfig, Uart};
 use esp_hal::{Blocking, handler, main};
+use heapless::{Deque, Vec};
 use log::{error, info, warn};
+use serial_link::{Event as LinkEvent, FRAME_MAX, Link, LinkConfig};
+use shtcx::{LowPower, PowerMode, ShtC3};
+use uart_commands::{ErrorCode, Handler, LedState, Measurement, REPLY_MAX, TimeOfDay, dispatch};
 
-struct MessageBuffer {
-    message: [u8; 64],
-    length: usize,
-}
-impl MessageBuffer {
-    fn new() -> Self {
-        Self {
-            message: [0; 64],
-            length: 0,
-        }
-    }
-}
 static BUTTON: Mutex<RefCell<Option<Input>>> = Mutex::new(RefCell::new(None));
 static TIMER: Mutex<RefCell<Option<PeriodicTimer<'_, Blocking>>>> = Mutex::new(RefCell::new(None));
-static UART: Mutex<RefCell<Option<Uart<'_, Blocking>>>> = Mutex::new(RefCell::new(None));
+// Прерывание складывает принятые байты в кольцевой буфер, следующее сообщение не затирает предыдущее
+static UART: UartState<512, 512> = UartState::new();
 
-static MSG_RECEIVED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
-static MSG_BUFFER: Mutex<RefCell<Option<MessageBuffer>>> = Mutex::new(RefCell::new(None));
 static PERIOD_ELAPSED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
 static BTN_PRESSED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
 
+/// Replies waiting for the link, one payload is in flight at a time
+const REPLIES_MAX: usize = 4;
+
+/// Peripherals controlled by the UART commands
+struct Device<'a> {
+    led: channel::Channel<'a, LowSpeed>,
+    led_state: LedState,
+    sht: ShtC3<I2c<'a, Blocking>>,
+    delay: Delay,
+    rtc: Rtc<'a>,
+}
+
+impl Handler for Device<'_> {
+    fn set_led(&mut self, state: LedState) -> Result<(), ErrorCode> {
+        // Светодиод всегда на канале LEDC, ON/OFF - это 100% и 0%
+        let duty = match state {
+            LedState::Off => 0,
+            LedState::On => 100,
+            LedState::Pwm(duty) => duty,
+        };
+        self.led.set_duty(duty).map_err(|_| ErrorCode::Device)?;
+        self.led_state = state;
+        Ok(())
+    }
+
+    fn led(&mut self) -> LedState {
+        self.led_state
+    }
+
+    fn measure(&mut self) -> Result<Measurement, ErrorCode> {
+        let measurement = self
+            .sht
+            .measure(PowerMode::NormalMode, &mut self.delay)
+            .map_err(|_| ErrorCode::Device)?;
+        Ok(Measurement {
+            millicelsius: measurement.temperature.as_millidegrees_celsius(),
+            millipercent: measurement.humidity.as_millipercent(),
+        })
+    }
+
+    fn set_time(&mut self, time: TimeOfDay) -> Result<(), ErrorCode> {
+        self.rtc
+            .set_current_time_us(u64::from(time.seconds_since_midnight()) * 1_000_000);
+        Ok(())
+    }
+
+    fn time(&mut self) -> TimeOfDay {
+        TimeOfDay::from_seconds(self.rtc.current_time_us() / 1_000_000)
+    }
+}
+
 #[handler]
 fn button_irq_handler() {
     critical_section::with(|cs| {
@@ -68,26 +116,9 @@ fn gpt_irq_handler() {
 }
 
 #[handler]
-fn uart_rx_irq_handler() {
-    critical_section::with(|cs| {
-        // Очистка флага прерывания
-        let mut serial = UART.borrow_ref_mut(cs);
-
-        if let Some(serial) = serial.as_mut() {
-            let mut buf = [0u8; 64];
-            if let Ok(cnt) = serial.read_buffered(&mut buf) {
-                let mut buffer = MSG_BUFFER.borrow_ref_mut(cs);
-                buffer.as_mut().unwrap().length = cnt;
-                for i in 0..cnt {
-                    buffer.as_mut().unwrap().message[i] = buf[i];
-                }
-                // Установка флага
-                MSG_RECEIVED.borrow(cs).set(true);
-            }
-
-            serial.clear_interrupts(UartInterrupt::RxTimeout | UartInterrupt::RxFifoFull);
-        }
-    });
+fn uart_irq_handler() {
+    // Чтение RX FIFO и дозапись TX FIFO
+    UART.on_interrupt();
 }
 
 esp_bootloader_esp_idf::esp_app_desc!();
@@ -109,7 +140,47 @@ fn main() -> ! {
     io.set_interrupt_handler(button_irq_handler);
     io.set_interrupt_priority(esp_hal::interrupt::Priority::Priority1);
 
-    let mut led = Output::new(peripherals.GPIO7, Level::Low, OutputConfig::default());
+    // Светодиод через LEDC, чтобы команды могли задавать и яркость
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+    let mut lstimer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    if let Err(e) = lstimer0.configure(timer::config::Config {
+        duty: timer::config::Duty::Duty5Bit,
+        clock_source: timer::LSClockSource::APBClk,
+        frequency: Rate::from_khz(24),
+    }) {
+        error!("Failed to configure timer: {:?}", e);
+    }
+    let mut channel0 = ledc.channel(channel::Number::Channel0, peripherals.GPIO7);
+    if let Err(e) = channel0.configure(channel::config::Config {
+        timer: &lstimer0,
+        duty_pct: 0,
+        drive_mode: DriveMode::PushPull,
+    }) {
+        error!("Failed to configure channel: {:?}", e);
+    }
+
+    // SHTC3 на I2C0
+    let i2c = I2c::new(
+        peripherals.I2C0,
+        master::Config::default().with_frequency(Rate::from_khz(400)),
+    )
+    .unwrap()
+    .with_sda(peripherals.GPIO10)
+    .with_scl(peripherals.GPIO8);
+    let mut sht = shtcx::shtc3(i2c);
+    let mut delay = Delay::new();
+    if let Err(e) = sht.wakeup(&mut delay) {
+        warn!("SHTC3 does not respond: {:?}", e);
+    }
+
+    let mut device = Device {
+        led: channel0,
+        led_state: LedState::Off,
+        sht,
+        delay,
+        rtc: Rtc::new(peripherals.LPWR),
+    };
 
     let mut button = Input::new(
         peripherals.GPIO9,
@@ -144,7 +215,7 @@ fn main() -> ! {
             .with_fifo_full_threshold(64)
             .with_timeout(10),
     );
-    let mut serial_instance = Uart::new(
+    let serial_instance = Uart::new(
         peripherals.UART1,
         config.with_baudrate(9600).with_data_bits(DataBits::_8),
     )
@@ -152,31 +223,64 @@ fn main() -> ! {
     .with_rx(peripherals.GPIO20)
     .with_tx(peripherals.GPIO21);
 
-    serial_instance.set_interrupt_handler(uart_rx_irq_handler);
-
-    let msg_instance = MessageBuffer::new();
-
-    critical_section::with(|cs| {
-        serial_instance.listen(UartInterrupt::RxTimeout | UartInterrupt::RxFifoFull);
-        UART.borrow_ref_mut(cs).replace(serial_instance);
-        MSG_BUFFER.borrow_ref_mut(cs).replace(msg_instance);
-    });
+    let (mut rx, mut tx) = UART.init(serial_instance, uart_irq_handler).unwrap();
 
+    // Каждое сообщение - команда, ответ уходит отдельным кадром. Кадры serial-link
+    // отправляет другая прошивка или программа с тем же крейтом, для терминала - rx_tx_blocking
+    let mut link = Link::new(LinkConfig::default());
+    let start = Instant::now();
+    let mut chunk = [0u8; 64];
+    let mut frame = [0u8; FRAME_MAX];
+    let mut reply = [0u8; REPLY_MAX];
+    let mut replies: Deque<Vec<u8, REPLY_MAX>, REPLIES_MAX> = Deque::new();
     let mut count = 0;
 
     info!("Main thread has started...");
     loop {
-        critical_section::with(|cs| {
-            if MSG_RECEIVED.borrow(cs).get() {
-                MSG_RECEIVED.borrow(cs).set(false);
-                let buffer = MSG_BUFFER.borrow_ref_mut(cs);
-                info!(
-                    "Received message: {:?}",
-                    core::str::from_utf8(
-                        &buffer.as_ref().unwrap().message[0..buffer.as_ref().unwrap().length]
-                    )
-                );
+        let received = rx.try_read(&mut chunk);
+        for &byte in &chunk[..received] {
+            match link.push(byte) {
+                Some(LinkEvent::Received(payload)) => {
+                    info!("Command: {:?}", core::str::from_utf8(payload));
+                    let answer = dispatch(payload, &mut device);
+                    info!("Reply: {}", answer);
+                    // Ответ ждёт в очереди, пока предыдущий не подтверждён
+                    let queued = Vec::from_slice(answer.encode(&mut reply))
+                        .ok()
+                        .is_some_and(|bytes| replies.push_back(bytes).is_ok());
+                    if !queued {
+                        warn!("Reply queue is full, reply dropped");
+                    }
+                }
+                Some(LinkEvent::Delivered) => info!("Reply delivered"),
+                Some(LinkEvent::Corrupted(e)) => warn!("Corrupted frame: {:?}", e),
+                None => {}
             }
+        }
+
+        // Следующий ответ - после подтверждения (или таймаута) предыдущего
+        if link.is_idle()
+            && let Some(next) = replies.pop_front()
+            && let Err(e) = link.send(&next)
+        {
+            warn!("Reply dropped: {:?}", e);
+        }
+
+        // Отправка ACK/NAK, ответов и повторов
+        loop {
+            match link.poll(start.elapsed().as_millis(), &mut frame) {
+                Ok(Some(mut bytes)) => {
+                    while !bytes.is_empty() {
+                        let written = tx.try_write(bytes);
+                        bytes = &bytes[written..];
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => error!("Reply was not acknowledged: {:?}", e),
+            }
+        }
+
+        critical_section::with(|cs| {
             if BTN_PRESSED.borrow(cs).get() {
                 BTN_PRESSED.borrow(cs).set(false);
                 count += 1;
@@ -186,7 +290,11 @@ fn main() -> ! {
             if PERIOD_ELAPSED.borrow(cs).get() {
                 PERIOD_ELAPSED.borrow(cs).set(false);
                 warn!("Periodic Timer period elapsed!");
-                led.toggle();
+                info!(
+                    "UART overflows: {}, line errors: {}",
+                    rx.overflows(),
+                    rx.rx_errors()
+                );
                 wdt_timer.feed();
                 info!("Watchdog feeded");
             }
@@ -11,14 +11,24 @@ use esp_backtrace as _;
 
 use critical_section::Mutex;
 use esp_hal::clock::CpuClock;
-use esp_hal::gpio::{Event, Input, InputConfig, Io, Level, Output, OutputConfig};
-
-use esp_hal::time::Duration;
+use esp_hal::delay::Delay;
+use esp_hal::gpio::{DriveMode, Event, Input, InputConfig, Io};
+use esp_hal::i2c::master::{self, I2c};
+use esp_hal::ledc::channel::{self, ChannelIFace};
+use esp_hal::ledc::timer::{self, TimerIFace};
+use esp_hal::ledc::{LSGlobalClkSource, Ledc, LowSpeed};
+use esp_hal::rtc_cntl::Rtc;
+use esp_hal::time::{Duration, Rate};
 use esp_hal::timer::PeriodicTimer;
 use esp_hal::timer::timg::{MwdtStage, TimerGroup};
 use esp_hal::uart::{Config, DataBits, Uart, UartRx, UartTx};
 use esp_hal::{Blocking, handler, main};
+use heapless::Vec;
 use log::{error, info, warn};
+use shtcx::{LowPower, PowerMode, ShtC3};
+use uart_commands::{
+    ErrorCode, Handler, LINE_MAX, LedState, Measurement, REPLY_MAX, Reply, TimeOfDay, dispatch,
+};
 
 static BUTTON: Mutex<RefCell<Option<Input>>> = Mutex::new(RefCell::new(None));
 static TIMER: Mutex<RefCell<Option<PeriodicTimer<'_, Blocking>>>> = Mutex::new(RefCell::new(None));
@@ -26,6 +36,66 @@ static UART: Mutex<RefCell<Option<PeriodicTimer<'_, Blocking>>>> = Mutex::new(Re
 static PERIOD_ELAPSED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
 static BTN_PRESSED: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
 
+/// Peripherals controlled by the UART commands
+struct Device<'a> {
+    led: channel::Channel<'a, LowSpeed>,
+    led_state: LedState,
+    sht: ShtC3<I2c<'a, Blocking>>,
+    delay: Delay,
+    rtc: Rtc<'a>,
+}
+
+impl Handler for Device<'_> {
+    fn set_led(&mut self, state: LedState) -> Result<(), ErrorCode> {
+        // Светодиод всегда на канале LEDC, ON/OFF - это 100% и 0%
+        let duty = match state {
+            LedState::Off => 0,
+            LedState::On => 100,
+            LedState::Pwm(duty) => duty,
+        };
+        self.led.set_duty(duty).map_err(|_| ErrorCode::Device)?;
+        self.led_state = state;
+        Ok(())
+    }
+
+    fn led(&mut self) -> LedState {
+        self.led_state
+    }
+
+    fn measure(&mut self) -> Result<Measurement, ErrorCode> {
+        let measurement = self
+            .sht
+            .measure(PowerMode::NormalMode, &mut self.delay)
+            .map_err(|_| ErrorCode::Device)?;
+        Ok(Measurement {
+            millicelsius: measurement.temperature.as_millidegrees_celsius(),
+            millipercent: measurement.humidity.as_millipercent(),
+        })
+    }
+
+    fn set_time(&mut self, time: TimeOfDay) -> Result<(), ErrorCode> {
+        self.rtc
+            .set_current_time_us(u64::from(time.seconds_since_midnight()) * 1_000_000);
+        Ok(())
+    }
+
+    fn time(&mut self) -> TimeOfDay {
+        TimeOfDay::from_seconds(self.rtc.current_time_us() / 1_000_000)
+    }
+}
+
+/// Write all bytes, waiting for free space in the TX FIFO
+///
+/// * `uart` - UART
+/// * `bytes` - Bytes to write
+fn write_all(uart: &mut Uart<'_, Blocking>, mut bytes: &[u8]) {
+    // write() кладёт в FIFO сколько поместится
+    while !bytes.is_empty() {
+        let written = uart.write(bytes).unwrap();
+        bytes = &bytes[written..];
+    }
+}
+
 #[handler]
 fn button_irq_handler() {
     critical_section::with(|cs| {
@@ -71,7 +141,47 @@ fn main() -> ! {
     io.set_interrupt_handler(button_irq_handler);
     io.set_interrupt_priority(esp_hal::interrupt::Priority::Priority1);
 
-    let mut led = Output::new(peripherals.GPIO7, Level::Low, OutputConfig::default());
+    // Светодиод через LEDC, чтобы команды могли задавать и яркость
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+    let mut lstimer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    if let Err(e) = lstimer0.configure(timer::config::Config {
+        duty: timer::config::Duty::Duty5Bit,
+        clock_source: timer::LSClockSource::APBClk,
+        frequency: Rate::from_khz(24),
+    }) {
+        error!("Failed to configure timer: {:?}", e);
+    }
+    let mut channel0 = ledc.channel(channel::Number::Channel0, peripherals.GPIO7);
+    if let Err(e) = channel0.configure(channel::config::Config {
+        timer: &lstimer0,
+        duty_pct: 0,
+        drive_mode: DriveMode::PushPull,
+    }) {
+        error!("Failed to configure channel: {:?}", e);
+    }
+
+    // SHTC3 на I2C0
+    let i2c = I2c::new(
+        peripherals.I2C0,
+        master::Config::default().with_frequency(Rate::from_khz(400)),
+    )
+    .unwrap()
+    .with_sda(peripherals.GPIO10)
+    .with_scl(peripherals.GPIO8);
+    let mut sht = shtcx::shtc3(i2c);
+    let mut delay = Delay::new();
+    if let Err(e) = sht.wakeup(&mut delay) {
+        warn!("SHTC3 does not respond: {:?}", e);
+    }
+
+    let mut device = Device {
+        led: channel0,
+        led_state: LedState::Off,
+        sht,
+        delay,
+        rtc: Rtc::new(peripherals.LPWR),
+    };
 
     let mut button = Input::new(
         peripherals.GPIO9,
@@ -110,18 +220,39 @@ fn main() -> ! {
     .with_rx(peripherals.GPIO20)
     .with_tx(peripherals.GPIO21);
 
+    // Команды - строки текста, как их набирают в терминале (конец строки CR или LF),
+    // ответ - строка с CR LF. Кадры с CRC и подтверждением - в примере rx_idle_irq
+    let mut line: Vec<u8, LINE_MAX> = Vec::new();
+    let mut line_too_long = false;
+    let mut reply = [0u8; REPLY_MAX];
+
     let mut count = 0;
 
     info!("Main thread has started...");
     loop {
         if serial_instance.read_ready() {
-            let mut buf = [0u8; 1];
+            let mut buf = [0u8; 32];
             if let Ok(serial_bytes) = serial_instance.read_buffered(&mut buf) {
-                if serial_bytes > 0 {
-                    info!("Received: {:?}", buf[0] as char);
-                    serial_instance
-                        .write("received something!".as_bytes())
-                        .unwrap();
+                for &byte in &buf[..serial_bytes] {
+                    if byte != b'\r' && byte != b'\n' {
+                        line_too_long |= line.push(byte).is_err();
+                        continue;
+                    }
+                    // Пустые строки (в том числе LF после CR) пропускаются
+                    if line.is_empty() && !line_too_long {
+                        continue;
+                    }
+                    info!("Command: {:?}", core::str::from_utf8(&line));
+                    let answer = if line_too_long {
+                        Reply::Error(ErrorCode::Line)
+                    } else {
+                        dispatch(&line, &mut device)
+                    };
+                    info!("Reply: {}", answer);
+                    write_all(&mut serial_instance, answer.encode(&mut reply));
+                    write_all(&mut serial_instance, b"\r\n");
+                    line.clear();
+                    line_too_long = false;
                 }
             }
         }
@@ -136,7 +267,6 @@ fn main() -> ! {
             if PERIOD_ELAPSED.borrow(cs).get() {
                 PERIOD_ELAPSED.borrow(cs).set(false);
                 warn!("timg0.timer0 period elapsed!");
-                led.toggle();
                 wdt_timer.feed();
             }
         });